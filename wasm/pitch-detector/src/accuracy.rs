@@ -0,0 +1,82 @@
+use wasm_bindgen::prelude::*;
+
+use crate::detect_pitch;
+use crate::note::cents_between;
+use crate::signal::sine_wave;
+
+/// Detection accuracy over a set of synthetic test tones
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccuracyReport {
+    /// Mean absolute error of the detected tones, in cents (NaN if none detected)
+    pub mean_cents_error: f32,
+    /// Worst absolute error of the detected tones, in cents (NaN if none detected)
+    pub max_cents_error: f32,
+    /// Fraction of tones for which no pitch was detected
+    pub miss_rate: f32,
+}
+
+/// Run detection on a sine wave at each frequency and summarize the error
+/// Useful to check how well the detector performs for a given buffer size
+#[wasm_bindgen]
+pub fn evaluate_accuracy(freqs: &[f32], sample_rate: f32, buffer_size: usize) -> AccuracyReport {
+    let mut detected = 0usize;
+    let mut total_error = 0.0f32;
+    let mut max_error = 0.0f32;
+
+    for &frequency in freqs {
+        let samples = sine_wave(frequency, sample_rate, buffer_size);
+        let estimate = detect_pitch(&samples, sample_rate);
+        if estimate <= 0.0 {
+            continue;
+        }
+        let error = cents_between(estimate, frequency).abs();
+        detected += 1;
+        total_error += error;
+        max_error = max_error.max(error);
+    }
+
+    if detected == 0 {
+        return AccuracyReport {
+            mean_cents_error: f32::NAN,
+            max_cents_error: f32::NAN,
+            miss_rate: if freqs.is_empty() { 0.0 } else { 1.0 },
+        };
+    }
+
+    AccuracyReport {
+        mean_cents_error: total_error / detected as f32,
+        max_cents_error: max_error,
+        miss_rate: (freqs.len() - detected) as f32 / freqs.len() as f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guitar_strings_accuracy() {
+        let guitar = [82.41, 110.0, 146.83, 196.0, 246.94, 329.63];
+        let report = evaluate_accuracy(&guitar, 44100.0, 2048);
+        assert_eq!(report.miss_rate, 0.0);
+        assert!(
+            report.mean_cents_error < 3.0,
+            "mean error {} cents",
+            report.mean_cents_error
+        );
+        assert!(report.max_cents_error >= report.mean_cents_error);
+    }
+
+    #[test]
+    fn test_out_of_range_counts_as_miss() {
+        // 30 Hz is below the detection range
+        let report = evaluate_accuracy(&[30.0, 440.0], 44100.0, 2048);
+        assert_eq!(report.miss_rate, 0.5);
+        assert!(report.mean_cents_error.is_finite());
+
+        let report = evaluate_accuracy(&[30.0], 44100.0, 2048);
+        assert_eq!(report.miss_rate, 1.0);
+        assert!(report.mean_cents_error.is_nan());
+    }
+}
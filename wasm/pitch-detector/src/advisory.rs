@@ -0,0 +1,153 @@
+use crate::yin::{absolute_threshold, effective_range, gated_cmndf};
+use crate::{calculate_rms, DEFAULT_THRESHOLD, MIN_FREQUENCY, RMS_THRESHOLD};
+
+/// Samples at or above this magnitude are at the rail
+const CLIP_LEVEL: f32 = 0.99;
+
+/// Consecutive samples at the rail that mark clipping; a lone full-scale
+/// peak is legitimate
+const CLIP_MIN_RUN: usize = 3;
+
+/// The octave-above trough counts as a rival when it dips below this
+const RIVAL_OCTAVE_CMNDF: f32 = 0.3;
+
+/// Below this depth the chosen trough is clean and the octave below, being
+/// periodic too, is expected to match it
+const CLEAN_TROUGH_CMNDF: f32 = 0.02;
+
+/// A user-facing warning about why a frame's detection may be unreliable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Advisory {
+    /// The signal is below the silence gate
+    TooQuiet,
+    /// The waveform is flattened against full scale
+    Clipped,
+    /// The buffer is too short to resolve the lowest supported frequency
+    BufferTooShort,
+    /// There is signal but no clear period
+    LikelyNoise,
+    /// A pitch was found but the octave above or below is nearly as likely
+    OctaveAmbiguous,
+}
+
+/// Every reason the frame's detection may be unreliable, in declaration order
+/// of [`Advisory`]; empty for a clean, well-sized frame
+pub fn detection_advisory(samples: &[f32], sample_rate: f32) -> Vec<Advisory> {
+    let mut advisories = Vec::new();
+    if calculate_rms(samples) < RMS_THRESHOLD {
+        advisories.push(Advisory::TooQuiet);
+    }
+    if is_clipped(samples) {
+        advisories.push(Advisory::Clipped);
+    }
+    let (lowest, _) = effective_range(samples.len(), sample_rate);
+    if lowest == 0.0 || lowest > MIN_FREQUENCY {
+        advisories.push(Advisory::BufferTooShort);
+    }
+
+    if let Some(cmndf) = gated_cmndf(samples) {
+        match absolute_threshold(&cmndf, DEFAULT_THRESHOLD) {
+            None => advisories.push(Advisory::LikelyNoise),
+            Some(tau) if is_octave_ambiguous(&cmndf, tau) => {
+                advisories.push(Advisory::OctaveAmbiguous)
+            }
+            Some(_) => {}
+        }
+    }
+    advisories
+}
+
+fn is_clipped(samples: &[f32]) -> bool {
+    let mut run = 0;
+    for sample in samples {
+        if sample.abs() >= CLIP_LEVEL {
+            run += 1;
+            if run >= CLIP_MIN_RUN {
+                return true;
+            }
+        } else {
+            run = 0;
+        }
+    }
+    false
+}
+
+/// Whether the trough at `tau` has a close rival an octave away: one at half
+/// the lag that nearly passed the threshold, or one at twice the lag that is
+/// much deeper than a trough which is itself not clean
+fn is_octave_ambiguous(cmndf: &[f32], tau: usize) -> bool {
+    let trough_near = |lag: usize| {
+        let lo = lag.saturating_sub(1).max(2);
+        let hi = (lag + 1).min(cmndf.len() - 1);
+        (lo..=hi).map(|t| cmndf[t]).fold(f32::INFINITY, f32::min)
+    };
+    if tau / 2 >= 2 && trough_near(tau / 2) < RIVAL_OCTAVE_CMNDF {
+        return true;
+    }
+    2 * tau < cmndf.len()
+        && cmndf[tau] > CLEAN_TROUGH_CMNDF
+        && trough_near(2 * tau) < 0.5 * cmndf[tau]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{add_noise, generate_tone, NoiseKind, Waveform};
+
+    #[test]
+    fn test_clipped_short_buffer() {
+        let sample_rate = 44100.0;
+        let clipped: Vec<f32> = generate_tone(440.0, sample_rate, 256, Waveform::Sine)
+            .into_iter()
+            .map(|x| (2.0 * x).clamp(-1.0, 1.0))
+            .collect();
+        let advisories = detection_advisory(&clipped, sample_rate);
+        assert!(advisories.contains(&Advisory::Clipped), "{:?}", advisories);
+        assert!(
+            advisories.contains(&Advisory::BufferTooShort),
+            "{:?}",
+            advisories
+        );
+        assert!(!advisories.contains(&Advisory::TooQuiet));
+    }
+
+    #[test]
+    fn test_clean_frame_has_no_advisories() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(220.0, sample_rate, 2048, Waveform::Saw);
+        let scaled: Vec<f32> = samples.iter().map(|x| 0.5 * x).collect();
+        assert_eq!(detection_advisory(&scaled, sample_rate), Vec::new());
+    }
+
+    #[test]
+    fn test_quiet_noisy_and_ambiguous_frames() {
+        let sample_rate = 44100.0;
+        assert_eq!(
+            detection_advisory(&[0.0; 2048], sample_rate),
+            vec![Advisory::TooQuiet]
+        );
+
+        // Practically pure noise, brought back below full scale
+        let mut noise = generate_tone(440.0, sample_rate, 2048, Waveform::Sine);
+        add_noise(&mut noise, -30.0, NoiseKind::White);
+        let scale = 0.3 / calculate_rms(&noise);
+        let noise: Vec<f32> = noise.iter().map(|x| x * scale).collect();
+        assert_eq!(
+            detection_advisory(&noise, sample_rate),
+            vec![Advisory::LikelyNoise]
+        );
+
+        // A weak fundamental under a strong second harmonic
+        let fundamental = generate_tone(110.0, sample_rate, 2048, Waveform::Sine);
+        let harmonic = generate_tone(220.0, sample_rate, 2048, Waveform::Sine);
+        let ambiguous: Vec<f32> = fundamental
+            .iter()
+            .zip(&harmonic)
+            .map(|(f, h)| 0.15 * f + 0.8 * h)
+            .collect();
+        assert_eq!(
+            detection_advisory(&ambiguous, sample_rate),
+            vec![Advisory::OctaveAmbiguous]
+        );
+    }
+}
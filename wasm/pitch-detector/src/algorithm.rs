@@ -0,0 +1,80 @@
+use crate::consensus::detect_pitch_autocorrelation;
+use crate::pyin::detect_pitch_pyin;
+use crate::session::Algorithm;
+use crate::yin::detect_pitch_full_lag_range;
+use crate::{detect_pitch_with_threshold, DEFAULT_THRESHOLD};
+
+/// A frame-by-frame pitch estimator
+/// Lets generic code such as [`crate::detect_pitch_track_with`] run any of
+/// the built-in algorithms, or one supplied by the caller, the same way
+pub trait PitchAlgorithm {
+    /// Detected frequency in Hz, or -1.0 if no pitch detected
+    fn detect(&self, samples: &[f32], sample_rate: f32) -> f32;
+}
+
+/// YIN with a fixed threshold, as in `detect_pitch_with_threshold`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Yin {
+    pub threshold: f32,
+}
+
+impl Default for Yin {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+        }
+    }
+}
+
+impl PitchAlgorithm for Yin {
+    fn detect(&self, samples: &[f32], sample_rate: f32) -> f32 {
+        detect_pitch_with_threshold(samples, sample_rate, self.threshold)
+    }
+}
+
+/// Autocorrelation peak picking, as in `detect_pitch_autocorrelation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Autocorrelation;
+
+impl PitchAlgorithm for Autocorrelation {
+    fn detect(&self, samples: &[f32], sample_rate: f32) -> f32 {
+        detect_pitch_autocorrelation(samples, sample_rate)
+    }
+}
+
+/// The most probable `detect_pitch_pyin` candidate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pyin;
+
+impl PitchAlgorithm for Pyin {
+    fn detect(&self, samples: &[f32], sample_rate: f32) -> f32 {
+        detect_pitch_pyin(samples, sample_rate)
+            .first()
+            .map_or(-1.0, |candidate| candidate.frequency)
+    }
+}
+
+/// The strongest spectral peak, as in `detect_pitch_spectral_peak`
+#[cfg(feature = "fft")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpectralPeak;
+
+#[cfg(feature = "fft")]
+impl PitchAlgorithm for SpectralPeak {
+    fn detect(&self, samples: &[f32], sample_rate: f32) -> f32 {
+        crate::spectral::detect_pitch_spectral_peak(samples, sample_rate)
+    }
+}
+
+/// A session's algorithm with the default threshold and range
+impl PitchAlgorithm for Algorithm {
+    fn detect(&self, samples: &[f32], sample_rate: f32) -> f32 {
+        match self {
+            Algorithm::Yin => Yin::default().detect(samples, sample_rate),
+            Algorithm::YinFullLagRange => {
+                detect_pitch_full_lag_range(samples, sample_rate, DEFAULT_THRESHOLD).frequency
+            }
+            Algorithm::Autocorrelation => Autocorrelation.detect(samples, sample_rate),
+        }
+    }
+}
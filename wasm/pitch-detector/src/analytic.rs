@@ -0,0 +1,78 @@
+use std::f32::consts::PI;
+
+use wasm_bindgen::prelude::*;
+
+use crate::smoothing::median;
+use crate::{MAX_FREQUENCY, MIN_FREQUENCY, RMS_THRESHOLD};
+
+/// Pitch detection on an analytic (Hilbert-transformed) or I/Q signal
+/// `re` and `im` are the real and imaginary parts, which must have the same
+/// length. The phase advance between consecutive samples is the
+/// instantaneous frequency; its median over the buffer shrugs off the
+/// glitches around amplitude dips. Samples too quiet to carry a reliable
+/// phase are skipped, and a buffer that is mostly that quiet is unvoiced.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_analytic(re: &[f32], im: &[f32], sample_rate: f32) -> f32 {
+    if re.len() != im.len() || re.len() < 2 {
+        return -1.0;
+    }
+    let floor = RMS_THRESHOLD * RMS_THRESHOLD;
+    let frequencies: Vec<f32> = (1..re.len())
+        .filter(|&i| re[i] * re[i] + im[i] * im[i] >= floor)
+        .filter(|&i| re[i - 1] * re[i - 1] + im[i - 1] * im[i - 1] >= floor)
+        .map(|i| {
+            // z[i] * conj(z[i - 1])
+            let cross_re = re[i] * re[i - 1] + im[i] * im[i - 1];
+            let cross_im = im[i] * re[i - 1] - re[i] * im[i - 1];
+            cross_im.atan2(cross_re) * sample_rate / (2.0 * PI)
+        })
+        .collect();
+    if frequencies.len() < re.len() / 2 {
+        return -1.0;
+    }
+    let frequency = median(frequencies);
+    if (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+        frequency
+    } else {
+        -1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{add_noise_seeded, NoiseKind};
+
+    fn analytic_tone(frequency: f32, sample_rate: f32, len: usize) -> (Vec<f32>, Vec<f32>) {
+        let phase = |i: usize| 2.0 * PI * frequency * i as f32 / sample_rate;
+        (
+            (0..len).map(|i| 0.5 * phase(i).cos()).collect(),
+            (0..len).map(|i| 0.5 * phase(i).sin()).collect(),
+        )
+    }
+
+    #[test]
+    fn test_analytic_440hz() {
+        let sample_rate = 44100.0;
+        let (re, mut im) = analytic_tone(440.0, sample_rate, 1024);
+        let detected = detect_pitch_analytic(&re, &im, sample_rate);
+        assert!((detected - 440.0).abs() < 0.1, "got {}", detected);
+
+        add_noise_seeded(&mut im, 30.0, NoiseKind::White, 3);
+        let detected = detect_pitch_analytic(&re, &im, sample_rate);
+        assert!((detected - 440.0).abs() < 2.0, "got {}", detected);
+    }
+
+    #[test]
+    fn test_analytic_rejects_bad_input() {
+        let (re, im) = analytic_tone(440.0, 44100.0, 1024);
+        assert_eq!(detect_pitch_analytic(&re, &im[..1000], 44100.0), -1.0);
+        assert_eq!(
+            detect_pitch_analytic(&[0.0; 512], &[0.0; 512], 44100.0),
+            -1.0
+        );
+        // Negative frequencies (clockwise rotation) are out of range
+        assert_eq!(detect_pitch_analytic(&im, &re, 44100.0), -1.0);
+    }
+}
@@ -0,0 +1,76 @@
+use wasm_bindgen::prelude::*;
+
+use crate::detect_pitch;
+use crate::filter::Biquad;
+
+/// Filter sections per band edge; two cascaded Butterworth sections give a
+/// 24 dB/octave slope
+const BAND_FILTER_ORDER: usize = 2;
+
+/// YIN pitch detection per frequency band
+/// `band_edges` lists ascending edges in Hz, so N + 1 edges define N bands.
+/// Each band is band-pass filtered and analyzed on its own, which can
+/// separate simultaneous notes that fall in different bands.
+/// Returns one frequency per band, -1.0 where no pitch was found inside it
+#[wasm_bindgen]
+pub fn detect_pitch_bands(samples: &[f32], sample_rate: f32, band_edges: &[f32]) -> Vec<f32> {
+    band_edges
+        .windows(2)
+        .map(|band| {
+            let (low, high) = (band[0], band[1]);
+            if !(low > 0.0 && low < high && high < sample_rate / 2.0) {
+                return -1.0;
+            }
+
+            let mut filtered = samples.to_vec();
+            for _ in 0..BAND_FILTER_ORDER {
+                Biquad::high_pass(low, sample_rate).process_in_place(&mut filtered);
+                Biquad::low_pass(high, sample_rate).process_in_place(&mut filtered);
+            }
+
+            let frequency = detect_pitch(&filtered, sample_rate);
+            if (low..=high).contains(&frequency) {
+                frequency
+            } else {
+                -1.0
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+
+    #[test]
+    fn test_two_sines_in_separate_bands() {
+        let sample_rate = 44100.0;
+        let low = generate_tone(220.0, sample_rate, 4096, Waveform::Sine);
+        let high = generate_tone(1000.0, sample_rate, 4096, Waveform::Sine);
+        let mixed: Vec<f32> = low.iter().zip(&high).map(|(a, b)| 0.5 * (a + b)).collect();
+
+        let detected = detect_pitch_bands(&mixed, sample_rate, &[100.0, 450.0, 1800.0]);
+        assert_eq!(detected.len(), 2);
+        assert!(
+            (detected[0] - 220.0).abs() < 3.0,
+            "low band {}",
+            detected[0]
+        );
+        assert!(
+            (detected[1] - 1000.0).abs() < 10.0,
+            "high band {}",
+            detected[1]
+        );
+    }
+
+    #[test]
+    fn test_invalid_bands() {
+        let samples = generate_tone(220.0, 44100.0, 2048, Waveform::Sine);
+        assert!(detect_pitch_bands(&samples, 44100.0, &[100.0]).is_empty());
+        assert_eq!(
+            detect_pitch_bands(&samples, 44100.0, &[400.0, 100.0]),
+            vec![-1.0]
+        );
+    }
+}
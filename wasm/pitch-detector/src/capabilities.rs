@@ -0,0 +1,94 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{DEFAULT_THRESHOLD, MAX_FREQUENCY, MIN_FREQUENCY, RMS_THRESHOLD};
+
+/// Optional features compiled into this build
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Panics are forwarded to the browser console
+    pub console_error_panic_hook: bool,
+    /// Test-signal generators are available
+    pub testutil: bool,
+    /// `PitchDetector` accepts a per-frame diagnostics callback
+    pub debug_callback: bool,
+    /// Frequency-domain analysis (`spectral_*` functions) is available
+    pub fft: bool,
+    /// `DetectionSession::from_js` accepts plain JS config objects and
+    /// `analyze_full` is available
+    pub serde: bool,
+}
+
+/// Report which optional features this build was compiled with, so JS can
+/// avoid calling into paths that are not present
+#[wasm_bindgen]
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        console_error_panic_hook: cfg!(feature = "console_error_panic_hook"),
+        testutil: cfg!(feature = "testutil"),
+        debug_callback: cfg!(feature = "debug-callback"),
+        fft: cfg!(feature = "fft"),
+        serde: cfg!(feature = "serde"),
+    }
+}
+
+/// The version and default parameters of this build, for research logs
+/// Recording it next to a result set pins down exactly which detector
+/// produced it
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectorInfo {
+    /// Crate version, e.g. "0.1.0"
+    pub version: String,
+    /// YIN threshold used when none is given
+    pub default_threshold: f32,
+    /// Detection range in Hz
+    pub min_frequency: f32,
+    pub max_frequency: f32,
+    /// RMS below which a frame is treated as silence
+    pub rms_threshold: f32,
+    pub capabilities: Capabilities,
+}
+
+/// Report the crate version, default parameters and compiled-in features
+#[wasm_bindgen]
+pub fn detector_info() -> DetectorInfo {
+    DetectorInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        default_threshold: DEFAULT_THRESHOLD,
+        min_frequency: MIN_FREQUENCY,
+        max_frequency: MAX_FREQUENCY,
+        rms_threshold: RMS_THRESHOLD,
+        capabilities: capabilities(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_match_features() {
+        let caps = capabilities();
+        assert_eq!(
+            caps.console_error_panic_hook,
+            cfg!(feature = "console_error_panic_hook")
+        );
+        assert_eq!(caps.testutil, cfg!(feature = "testutil"));
+        assert_eq!(caps.debug_callback, cfg!(feature = "debug-callback"));
+        assert_eq!(caps.fft, cfg!(feature = "fft"));
+        assert_eq!(caps.serde, cfg!(feature = "serde"));
+    }
+
+    #[test]
+    fn test_detector_info() {
+        let info = detector_info();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.default_threshold, DEFAULT_THRESHOLD);
+        assert_eq!(
+            (info.min_frequency, info.max_frequency),
+            (MIN_FREQUENCY, MAX_FREQUENCY)
+        );
+        assert_eq!(info.capabilities, capabilities());
+    }
+}
@@ -0,0 +1,90 @@
+use wasm_bindgen::prelude::*;
+
+use crate::detector::PitchDetector;
+use crate::note::cents_between;
+
+/// Streaming detector that only reports pitch changes
+/// Wraps a [`PitchDetector`] and keeps quiet until a frame's pitch moves more
+/// than `threshold_cents` from the last value it reported, which cuts the
+/// messages sent to JS for a held note to one. Losing the pitch is also a
+/// change and is reported once as -1.0
+#[wasm_bindgen]
+pub struct ChangeDetector {
+    detector: PitchDetector,
+    threshold_cents: f32,
+    /// Last value handed out, None before the first report
+    reported: Option<f32>,
+}
+
+#[wasm_bindgen]
+impl ChangeDetector {
+    #[wasm_bindgen(constructor)]
+    pub fn new(threshold_cents: f32) -> Self {
+        Self {
+            detector: PitchDetector::new(),
+            threshold_cents,
+            reported: None,
+        }
+    }
+
+    pub fn threshold_cents(&self) -> f32 {
+        self.threshold_cents
+    }
+
+    pub fn set_threshold_cents(&mut self, threshold_cents: f32) {
+        self.threshold_cents = threshold_cents;
+    }
+
+    /// Detect the pitch of one frame
+    /// Returns the new frequency in Hz (-1.0 once the pitch is lost) if it
+    /// changed since the last report, None otherwise
+    pub fn process(&mut self, samples: &[f32], sample_rate: f32) -> Option<f32> {
+        let frequency = self.detector.process(samples, sample_rate);
+        let changed = match self.reported {
+            None => frequency > 0.0,
+            Some(last) if last <= 0.0 || frequency <= 0.0 => (last > 0.0) != (frequency > 0.0),
+            Some(last) => cents_between(frequency, last).abs() > self.threshold_cents,
+        };
+        if !changed {
+            return None;
+        }
+        self.reported = Some(frequency);
+        self.reported
+    }
+
+    /// Forget the last report, so the next voiced frame is reported
+    pub fn reset(&mut self) {
+        self.detector.reset();
+        self.reported = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+
+    #[test]
+    fn test_reports_only_changes() {
+        let sample_rate = 44100.0;
+        let a4 = generate_tone(440.0, sample_rate, 2048, Waveform::Sine);
+        let a4_sharp = generate_tone(441.0, sample_rate, 2048, Waveform::Sine);
+        let b4 = generate_tone(493.88, sample_rate, 2048, Waveform::Sine);
+        let mut changes = ChangeDetector::new(10.0);
+
+        let first = changes.process(&a4, sample_rate).unwrap();
+        assert!((first - 440.0).abs() < 2.0);
+        for _ in 0..5 {
+            assert_eq!(changes.process(&a4, sample_rate), None);
+        }
+        // 4 cents is within the threshold
+        assert_eq!(changes.process(&a4_sharp, sample_rate), None);
+
+        let changed = changes.process(&b4, sample_rate).unwrap();
+        assert!((changed - 493.88).abs() < 2.0);
+        assert_eq!(changes.process(&b4, sample_rate), None);
+
+        assert_eq!(changes.process(&[0.0; 2048], sample_rate), Some(-1.0));
+        assert_eq!(changes.process(&[0.0; 2048], sample_rate), None);
+    }
+}
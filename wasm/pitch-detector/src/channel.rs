@@ -0,0 +1,193 @@
+use wasm_bindgen::prelude::*;
+
+use crate::config::DetectorConfig;
+use crate::detect_pitch;
+use crate::yin::{detect_full_source, SampleSource, Strided};
+
+/// YIN pitch detection on one channel of an interleaved multi-channel buffer
+/// Reads the channel in place instead of deinterleaving a copy
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected or the
+/// channel index is out of range
+#[wasm_bindgen]
+pub fn detect_pitch_channel(
+    interleaved: &[f32],
+    num_channels: usize,
+    channel: usize,
+    sample_rate: f32,
+) -> f32 {
+    if channel >= num_channels {
+        return -1.0;
+    }
+    let source = Strided::new(interleaved, num_channels, channel);
+    detect_full_source(&source, sample_rate, &DetectorConfig::default()).frequency
+}
+
+/// YIN pitch detection on one channel of a planar (non-interleaved) buffer
+/// Planar data is laid out like consecutive `AudioBuffer.getChannelData`
+/// arrays, so channel `c` of a buffer starts at `channel_offset = c * length`;
+/// any other in-bounds offset works too for analyzing a sub-range.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected or
+/// `channel_offset..channel_offset + length` is out of bounds
+#[wasm_bindgen]
+pub fn detect_pitch_audiobuffer_channel(
+    flat: &[f32],
+    length: usize,
+    channel_offset: usize,
+    sample_rate: f32,
+) -> f32 {
+    let channel = channel_offset
+        .checked_add(length)
+        .and_then(|end| flat.get(channel_offset..end));
+    match channel {
+        Some(samples) => detect_pitch(samples, sample_rate),
+        None => -1.0,
+    }
+}
+
+/// Mid (L+R)/2 of an interleaved stereo buffer, read in place
+struct Mid<'a>(&'a [f32]);
+
+impl SampleSource for Mid<'_> {
+    fn sample_count(&self) -> usize {
+        self.0.len() / 2
+    }
+
+    fn sample(&self, index: usize) -> f32 {
+        0.5 * (self.0[2 * index] + self.0[2 * index + 1])
+    }
+}
+
+/// Pitch of the most tonal view of a stereo buffer
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelResult {
+    /// Detected frequency in Hz, or -1.0 if no pitch detected
+    pub frequency: f32,
+    /// Channel the pitch came from: 0 for left, 1 for right, 2 for mid;
+    /// 0 if none is voiced
+    pub channel: u8,
+}
+
+/// YIN pitch detection on whichever of left, right and mid is most tonal
+/// For stereo recordings where the instrument sits in one channel, or the
+/// room in the other. Each of the three is analyzed in place and the voiced
+/// one with the deepest CMNDF trough (highest clarity) wins, earlier ones
+/// on a tie.
+/// Returns the frequency with the winning channel, or -1.0 and channel 0 if
+/// none is voiced
+#[wasm_bindgen]
+pub fn detect_pitch_best_channel(interleaved: &[f32], sample_rate: f32) -> ChannelResult {
+    let config = DetectorConfig::default();
+    let left = detect_full_source(&Strided::new(interleaved, 2, 0), sample_rate, &config);
+    let right = detect_full_source(&Strided::new(interleaved, 2, 1), sample_rate, &config);
+    let mid = detect_full_source(&Mid(interleaved), sample_rate, &config);
+
+    let mut best = ChannelResult {
+        frequency: -1.0,
+        channel: 0,
+    };
+    let mut best_cmndf = f32::INFINITY;
+    for (channel, result) in [left, right, mid].into_iter().enumerate() {
+        if result.frequency > 0.0 && result.cmndf < best_cmndf {
+            best = ChannelResult {
+                frequency: result.frequency,
+                channel: channel as u8,
+            };
+            best_cmndf = result.cmndf;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signal::sine_wave;
+
+    fn interleave(left: &[f32], right: &[f32]) -> Vec<f32> {
+        left.iter().zip(right).flat_map(|(&l, &r)| [l, r]).collect()
+    }
+
+    #[test]
+    fn test_stereo_channels_detected_independently() {
+        let sample_rate = 44100.0;
+        let left = sine_wave(220.0, sample_rate, 2048);
+        let right = sine_wave(330.0, sample_rate, 2048);
+        let stereo = interleave(&left, &right);
+
+        let detected_left = detect_pitch_channel(&stereo, 2, 0, sample_rate);
+        let detected_right = detect_pitch_channel(&stereo, 2, 1, sample_rate);
+        assert!(
+            (detected_left - 220.0).abs() < 2.0,
+            "left {}",
+            detected_left
+        );
+        assert!(
+            (detected_right - 330.0).abs() < 2.0,
+            "right {}",
+            detected_right
+        );
+    }
+
+    #[test]
+    fn test_planar_channel_slice() {
+        let sample_rate = 44100.0;
+        let length = 2048;
+        let mut planar = sine_wave(220.0, sample_rate, length);
+        planar.extend(sine_wave(330.0, sample_rate, length));
+
+        let second = detect_pitch_audiobuffer_channel(&planar, length, length, sample_rate);
+        assert!((second - 330.0).abs() < 2.0, "second channel {}", second);
+        let first = detect_pitch_audiobuffer_channel(&planar, length, 0, sample_rate);
+        assert!((first - 220.0).abs() < 2.0, "first channel {}", first);
+
+        assert_eq!(
+            detect_pitch_audiobuffer_channel(&planar, length, length + 1, sample_rate),
+            -1.0
+        );
+        assert_eq!(
+            detect_pitch_audiobuffer_channel(&planar, usize::MAX, 1, sample_rate),
+            -1.0
+        );
+    }
+
+    #[test]
+    fn test_invalid_channel() {
+        let stereo = vec![0.5f32; 512];
+        assert_eq!(detect_pitch_channel(&stereo, 2, 2, 44100.0), -1.0);
+        assert_eq!(detect_pitch_channel(&stereo, 0, 0, 44100.0), -1.0);
+    }
+
+    #[test]
+    fn test_best_channel_picks_the_tonal_one() {
+        use crate::testutil::{add_noise_seeded, NoiseKind};
+
+        let sample_rate = 44100.0;
+        let tone = sine_wave(330.0, sample_rate, 2048);
+        let mut noise = vec![1e-3; 2048];
+        add_noise_seeded(&mut noise, -50.0, NoiseKind::White, 11);
+
+        // Only the right channel holds the instrument; mid is half noise
+        let ChannelResult { frequency, channel } =
+            detect_pitch_best_channel(&interleave(&noise, &tone), sample_rate);
+        assert!((frequency - 330.0).abs() < 2.0, "{}", frequency);
+        assert_eq!(channel, 1);
+
+        // Opposite-phase hum in the two channels cancels in the mid
+        let hum = sine_wave(60.0, sample_rate, 2048);
+        let left: Vec<f32> = tone.iter().zip(&hum).map(|(t, h)| t + h).collect();
+        let right: Vec<f32> = tone.iter().zip(&hum).map(|(t, h)| t - h).collect();
+        let ChannelResult { frequency, channel } =
+            detect_pitch_best_channel(&interleave(&left, &right), sample_rate);
+        assert!((frequency - 330.0).abs() < 2.0, "{}", frequency);
+        assert_eq!(channel, 2);
+
+        assert_eq!(
+            detect_pitch_best_channel(&[0.0; 4096], sample_rate),
+            ChannelResult {
+                frequency: -1.0,
+                channel: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use wasm_bindgen::prelude::*;
+
+use crate::detect_pitch;
+
+/// Full-scale value of the 16-bit linear samples the G.711 codes expand to
+const PCM_FULL_SCALE: f32 = 32768.0;
+
+/// G.711 μ-law code to 16-bit linear PCM
+const MULAW_TABLE: [i16; 256] = build_mulaw_table();
+
+/// G.711 A-law code to 16-bit linear PCM
+const ALAW_TABLE: [i16; 256] = build_alaw_table();
+
+const fn build_mulaw_table() -> [i16; 256] {
+    let mut table = [0i16; 256];
+    let mut code = 0;
+    while code < 256 {
+        let u = !(code as u8);
+        let exponent = (u >> 4) & 0x07;
+        let mantissa = (u & 0x0F) as i16;
+        let magnitude = (((mantissa << 3) + 0x84) << exponent) - 0x84;
+        table[code] = if u & 0x80 != 0 { -magnitude } else { magnitude };
+        code += 1;
+    }
+    table
+}
+
+const fn build_alaw_table() -> [i16; 256] {
+    let mut table = [0i16; 256];
+    let mut code = 0;
+    while code < 256 {
+        let a = code as u8 ^ 0x55;
+        let exponent = (a >> 4) & 0x07;
+        let mantissa = (a & 0x0F) as i16;
+        let magnitude = if exponent == 0 {
+            (mantissa << 4) + 8
+        } else {
+            ((mantissa << 4) + 0x108) << (exponent - 1)
+        };
+        // A-law sets the sign bit for positive samples
+        table[code] = if a & 0x80 != 0 { magnitude } else { -magnitude };
+        code += 1;
+    }
+    table
+}
+
+fn decode(encoded: &[u8], table: &[i16; 256]) -> Vec<f32> {
+    encoded
+        .iter()
+        .map(|&code| table[code as usize] as f32 / PCM_FULL_SCALE)
+        .collect()
+}
+
+/// YIN pitch detection on G.711 μ-law encoded audio (telephony, legacy
+/// embedded sources), decoded to f32 first
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_mulaw(encoded: &[u8], sample_rate: f32) -> f32 {
+    detect_pitch(&decode(encoded, &MULAW_TABLE), sample_rate)
+}
+
+/// YIN pitch detection on G.711 A-law encoded audio, decoded to f32 first
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_alaw(encoded: &[u8], sample_rate: f32) -> f32 {
+    detect_pitch(&decode(encoded, &ALAW_TABLE), sample_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+
+    /// Encode by picking the code whose decoded value is nearest
+    fn encode(samples: &[f32], table: &[i16; 256]) -> Vec<u8> {
+        samples
+            .iter()
+            .map(|&x| {
+                let target = x * PCM_FULL_SCALE;
+                (0..=255u8)
+                    .min_by(|&a, &b| {
+                        let da = (table[a as usize] as f32 - target).abs();
+                        let db = (table[b as usize] as f32 - target).abs();
+                        da.total_cmp(&db)
+                    })
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_tables() {
+        assert_eq!(MULAW_TABLE[0xFF], 0);
+        assert_eq!(MULAW_TABLE[0x80], 32124);
+        assert_eq!(MULAW_TABLE[0x00], -32124);
+        assert_eq!(ALAW_TABLE[0xD5], 8);
+        assert_eq!(ALAW_TABLE[0xAA], 32256);
+        assert_eq!(ALAW_TABLE[0x2A], -32256);
+    }
+
+    #[test]
+    fn test_detect_encoded_tone() {
+        let sample_rate = 8000.0;
+        let tone: Vec<f32> = generate_tone(330.0, sample_rate, 1024, Waveform::Sine)
+            .iter()
+            .map(|x| 0.5 * x)
+            .collect();
+
+        let mulaw = detect_pitch_mulaw(&encode(&tone, &MULAW_TABLE), sample_rate);
+        assert!((mulaw - 330.0).abs() < 2.0, "mu-law {}", mulaw);
+        let alaw = detect_pitch_alaw(&encode(&tone, &ALAW_TABLE), sample_rate);
+        assert!((alaw - 330.0).abs() < 2.0, "A-law {}", alaw);
+    }
+}
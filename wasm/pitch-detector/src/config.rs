@@ -0,0 +1,98 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{DEFAULT_THRESHOLD, MAX_FREQUENCY, MIN_FREQUENCY, RMS_THRESHOLD};
+
+/// What to report for a clear pitch outside the frequency range
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RangeBehavior {
+    /// Report no pitch (-1.0). An out-of-range reading is usually an octave
+    /// error or a note the instrument cannot play, so this is the safe default
+    #[default]
+    Reject,
+    /// Report the nearest range boundary instead, keeping the readout
+    /// continuous while a sweep passes the edge. The boundary value then no
+    /// longer tells a real note there apart from one far outside it
+    Clamp,
+}
+
+/// Tunable detection parameters, defaulting to the built-in constants
+/// Build one with the `with_*` methods, e.g.
+/// `DetectorConfig::default().with_threshold(0.15).with_frequency_range(70.0, 1200.0)`
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectorConfig {
+    /// CMNDF threshold a trough must dip under (lower is stricter)
+    pub threshold: f32,
+    /// Lowest frequency reported, in Hz
+    pub min_frequency: f32,
+    /// Highest frequency reported, in Hz
+    pub max_frequency: f32,
+    /// Frames with a lower RMS are treated as silence
+    pub rms_threshold: f32,
+    /// Handling of clear pitches outside `min_frequency..=max_frequency`
+    pub range_behavior: RangeBehavior,
+}
+
+#[wasm_bindgen]
+impl DetectorConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DetectorConfig {
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_frequency_range(mut self, min_frequency: f32, max_frequency: f32) -> Self {
+        self.min_frequency = min_frequency;
+        self.max_frequency = max_frequency;
+        self
+    }
+
+    pub fn with_rms_threshold(mut self, rms_threshold: f32) -> Self {
+        self.rms_threshold = rms_threshold;
+        self
+    }
+
+    pub fn with_range_behavior(mut self, range_behavior: RangeBehavior) -> Self {
+        self.range_behavior = range_behavior;
+        self
+    }
+
+    /// Whether `frequency` lies in the reported range
+    pub(crate) fn in_range(&self, frequency: f32) -> bool {
+        (self.min_frequency..=self.max_frequency).contains(&frequency)
+    }
+
+    /// The frequency to report for a detected `frequency`, after the range
+    /// behaviour; None if it is rejected
+    pub(crate) fn apply_range(&self, frequency: f32) -> Option<f32> {
+        if self.in_range(frequency) {
+            return Some(frequency);
+        }
+        match self.range_behavior {
+            RangeBehavior::Reject => None,
+            RangeBehavior::Clamp if frequency.is_finite() && frequency > 0.0 => {
+                Some(frequency.clamp(self.min_frequency, self.max_frequency))
+            }
+            RangeBehavior::Clamp => None,
+        }
+    }
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            min_frequency: MIN_FREQUENCY,
+            max_frequency: MAX_FREQUENCY,
+            rms_threshold: RMS_THRESHOLD,
+            range_behavior: RangeBehavior::default(),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use wasm_bindgen::prelude::*;
+
+use crate::algorithm::{Autocorrelation, PitchAlgorithm, Yin};
+use crate::note::cents_between;
+use crate::yin::parabolic_interpolation;
+use crate::{calculate_rms, MAX_FREQUENCY, MIN_FREQUENCY, RMS_THRESHOLD};
+
+/// Estimates within this many cents count as agreeing
+const AGREEMENT_CENTS: f32 = 50.0;
+
+/// YIN and autocorrelation estimates of one frame, side by side
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConsensusResult {
+    /// The YIN estimate when both agree, otherwise -1.0
+    pub frequency: f32,
+    /// Both algorithms found a pitch and they are within a quarter tone
+    pub agreed: bool,
+    /// YIN estimate in Hz, or -1.0 if it found no pitch
+    pub yin_hz: f32,
+    /// Autocorrelation estimate in Hz, or -1.0 if it found no pitch
+    pub alt_hz: f32,
+    /// Absolute interval between the two estimates in cents, NaN unless both
+    /// found a pitch; about 1200 means an octave disagreement
+    pub disagreement_cents: f32,
+}
+
+/// Run YIN and autocorrelation on the same frame and report whether they agree
+/// Agreement between two methods with different failure modes is a strong
+/// sign the estimate can be trusted; an octave disagreement shows up directly
+/// in `disagreement_cents`
+#[wasm_bindgen]
+pub fn detect_pitch_consensus(samples: &[f32], sample_rate: f32) -> ConsensusResult {
+    detect_pitch_consensus_with(samples, sample_rate, &Yin::default(), &Autocorrelation)
+}
+
+/// [`detect_pitch_consensus`] between any two [`PitchAlgorithm`]s
+/// `primary` fills the `yin_hz` slot and is reported when the two agree;
+/// `alternative` fills `alt_hz`
+pub fn detect_pitch_consensus_with<P, A>(
+    samples: &[f32],
+    sample_rate: f32,
+    primary: &P,
+    alternative: &A,
+) -> ConsensusResult
+where
+    P: PitchAlgorithm + ?Sized,
+    A: PitchAlgorithm + ?Sized,
+{
+    let yin_hz = primary.detect(samples, sample_rate);
+    let alt_hz = alternative.detect(samples, sample_rate);
+    let disagreement_cents = cents_between(alt_hz, yin_hz).abs();
+    let agreed = disagreement_cents <= AGREEMENT_CENTS;
+    ConsensusResult {
+        frequency: if agreed { yin_hz } else { -1.0 },
+        agreed,
+        yin_hz,
+        alt_hz,
+        disagreement_cents,
+    }
+}
+
+/// YIN and spectral-peak estimates of one frame, side by side
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DualResult {
+    /// YIN estimate in Hz, or -1.0 if it found no pitch
+    pub yin_hz: f32,
+    /// Spectral-peak estimate in Hz, or -1.0 if it found no pitch or the
+    /// `fft` feature is off
+    pub spectral_hz: f32,
+    /// Both estimates found a pitch and they are within a quarter tone
+    pub agree: bool,
+}
+
+/// Run YIN and a spectral-peak search on the same frame, for cross-checking
+/// The time-domain and frequency-domain estimates fail differently (the
+/// spectral peak lands on an overtone stronger than the fundamental, YIN on
+/// a subharmonic), so agreement is good evidence of a trustworthy pitch.
+/// Without the `fft` feature only YIN runs and nothing is ever agreed on
+#[wasm_bindgen]
+pub fn detect_pitch_dual(samples: &[f32], sample_rate: f32) -> DualResult {
+    let yin_hz = Yin::default().detect(samples, sample_rate);
+    #[cfg(feature = "fft")]
+    let spectral_hz = crate::algorithm::SpectralPeak.detect(samples, sample_rate);
+    #[cfg(not(feature = "fft"))]
+    let spectral_hz = -1.0;
+    DualResult {
+        yin_hz,
+        spectral_hz,
+        agree: cents_between(spectral_hz, yin_hz).abs() <= AGREEMENT_CENTS,
+    }
+}
+
+/// Autocorrelation pitch detection, independent of YIN
+/// Picks the highest autocorrelation peak over the detection range. The
+/// correlation sums shrink with the lag, which favours the shortest period
+/// among equally strong ones
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_autocorrelation(samples: &[f32], sample_rate: f32) -> f32 {
+    let buffer_size = samples.len();
+    if buffer_size < 2 || calculate_rms(samples) < RMS_THRESHOLD {
+        return -1.0;
+    }
+
+    let max_tau = ((sample_rate / MIN_FREQUENCY).ceil() as usize + 1).min(buffer_size - 1);
+    let min_tau = ((sample_rate / MAX_FREQUENCY) as usize).max(1);
+    if min_tau + 1 >= max_tau {
+        return -1.0;
+    }
+
+    // Negated so the peak becomes a trough for `parabolic_interpolation`
+    let negated: Vec<f32> = (0..=max_tau)
+        .map(|tau| {
+            -samples[..buffer_size - tau]
+                .iter()
+                .zip(&samples[tau..])
+                .map(|(a, b)| a * b)
+                .sum::<f32>()
+        })
+        .collect();
+
+    let peak = (min_tau..max_tau)
+        .filter(|&tau| negated[tau] < negated[tau - 1] && negated[tau] <= negated[tau + 1])
+        .min_by(|&a, &b| negated[a].total_cmp(&negated[b]));
+    let tau = match peak {
+        Some(t) if negated[t] < 0.0 => t,
+        _ => return -1.0,
+    };
+
+    let frequency = sample_rate / parabolic_interpolation(&negated, tau);
+    if (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+        frequency
+    } else {
+        -1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+
+    #[test]
+    fn test_autocorrelation_detects_tones() {
+        let sample_rate = 44100.0;
+        for frequency in [82.41, 220.0, 440.0, 1000.0] {
+            let samples = generate_tone(frequency, sample_rate, 2048, Waveform::Sine);
+            let detected = detect_pitch_autocorrelation(&samples, sample_rate);
+            assert!(
+                (detected - frequency).abs() / frequency < 0.01,
+                "{} Hz: got {}",
+                frequency,
+                detected
+            );
+        }
+        assert_eq!(
+            detect_pitch_autocorrelation(&[0.0; 2048], sample_rate),
+            -1.0
+        );
+    }
+
+    #[test]
+    fn test_consensus_agreement() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(330.0, sample_rate, 2048, Waveform::Sine);
+        let result = detect_pitch_consensus(&samples, sample_rate);
+        assert!(result.agreed, "{:?}", result);
+        assert_eq!(result.frequency, result.yin_hz);
+        assert!(result.disagreement_cents < 10.0);
+    }
+
+    #[test]
+    fn test_consensus_with_any_algorithms() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(220.0, sample_rate, 2048, Waveform::Saw);
+        assert_eq!(
+            detect_pitch_consensus_with(&samples, sample_rate, &Yin::default(), &Autocorrelation),
+            detect_pitch_consensus(&samples, sample_rate)
+        );
+
+        // A strict and a lenient YIN on a clean tone
+        let strict = Yin { threshold: 0.05 };
+        let result = detect_pitch_consensus_with(&samples, sample_rate, &strict, &Yin::default());
+        assert!(result.agreed, "{:?}", result);
+        assert_eq!(result.disagreement_cents, 0.0);
+    }
+
+    #[test]
+    fn test_consensus_octave_disagreement() {
+        let sample_rate = 44100.0;
+        // A2 fundamental under a louder A3: YIN finds the full period, the
+        // shrinking autocorrelation sums favour the harmonic's
+        let fundamental = generate_tone(110.0, sample_rate, 1024, Waveform::Sine);
+        let harmonic = generate_tone(220.0, sample_rate, 1024, Waveform::Sine);
+        let samples: Vec<f32> = fundamental
+            .iter()
+            .zip(&harmonic)
+            .map(|(f, h)| 0.3 * f + h)
+            .collect();
+
+        let result = detect_pitch_consensus(&samples, sample_rate);
+        assert!(!result.agreed, "{:?}", result);
+        assert_eq!(result.frequency, -1.0);
+        assert!(
+            (result.disagreement_cents - 1200.0).abs() < 20.0,
+            "{:?}",
+            result
+        );
+
+        let silence = detect_pitch_consensus(&[0.0; 1024], sample_rate);
+        assert!(!silence.agreed);
+        assert!(silence.disagreement_cents.is_nan());
+    }
+
+    #[test]
+    fn test_dual_agrees_on_clean_tone() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(440.0, sample_rate, 2048, Waveform::Sine);
+        let result = detect_pitch_dual(&samples, sample_rate);
+        assert!((result.yin_hz - 440.0).abs() < 2.0, "{:?}", result);
+        if cfg!(feature = "fft") {
+            assert!(result.agree, "{:?}", result);
+            assert!((result.spectral_hz - 440.0).abs() < 2.0, "{:?}", result);
+        } else {
+            assert!(!result.agree);
+            assert_eq!(result.spectral_hz, -1.0);
+        }
+
+        let silence = detect_pitch_dual(&[0.0; 2048], sample_rate);
+        assert!(!silence.agree);
+        assert_eq!(silence.yin_hz, -1.0);
+    }
+}
@@ -0,0 +1,1122 @@
+use std::collections::VecDeque;
+
+use wasm_bindgen::prelude::*;
+
+use crate::gain::AutoGain;
+use crate::note::cents_between;
+use crate::yin::{
+    absolute_threshold, cumulative_mean_normalized_difference, difference_function,
+    parabolic_interpolation,
+};
+use crate::{calculate_rms, DEFAULT_THRESHOLD, MAX_FREQUENCY, MIN_FREQUENCY, RMS_THRESHOLD};
+
+/// How far (in cents) a new estimate may be from an exact octave of the
+/// previous pitch and still be treated as an octave jump
+const OCTAVE_JUMP_TOLERANCE_CENTS: f32 = 50.0;
+
+/// Relative search radius around the expected tau of the previous pitch
+const CONTINUITY_SEARCH_RADIUS: f32 = 0.05;
+
+/// How much shallower than the chosen trough the previous pitch's trough may
+/// be while still being preferred for continuity
+const CONTINUITY_MARGIN: f32 = 0.1;
+
+/// Roughly how many samples of a frame the memoization hash reads
+const MEMO_HASH_SAMPLES: usize = 256;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// How `PitchDetector::process` reports frames where no pitch is found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HoldPolicy {
+    /// Report -1.0 for every unvoiced frame
+    #[default]
+    Immediate,
+    /// Keep reporting the last pitch for up to this many consecutive unvoiced
+    /// frames, bridging dropouts within a note
+    Hold(usize),
+    /// Keep reporting the last pitch while the unvoiced frames keep getting
+    /// quieter above the silence gate, as a note ringing out does; report
+    /// -1.0 from the first frame that is silent or no quieter than the one
+    /// before
+    FadeOut,
+}
+
+/// Stateful YIN detector for streaming input
+/// Reuses its scratch buffers between frames and can bias estimates toward
+/// the previously detected pitch
+#[wasm_bindgen]
+pub struct PitchDetector {
+    threshold: f32,
+    continuity: bool,
+    last_frequency: f32,
+    difference: Vec<f32>,
+    cmndf: Vec<f32>,
+    clarity_window: usize,
+    clarity_history: VecDeque<f32>,
+    hold_policy: HoldPolicy,
+    /// Frequency `process` last reported for the current note, -1.0 once
+    /// released
+    held_frequency: f32,
+    unvoiced_run: usize,
+    previous_rms: f32,
+    /// Agreeing frames needed before a new note is reported, 0 for no warmup
+    warmup_frames: usize,
+    warmup_tolerance_cents: f32,
+    /// Consecutive voiced frames each within the warmup tolerance of the one
+    /// before
+    stable_run: usize,
+    /// Consecutive frames below the silence gate that release a note, 0 if
+    /// any one does
+    dropout_frames: usize,
+    /// Consecutive frames below the silence gate so far
+    quiet_run: usize,
+    /// Consecutive unvoiced frames after which the history is reset, 0 for
+    /// never
+    silence_timeout_frames: usize,
+    auto_gain: Option<AutoGain>,
+    /// Gain-adjusted copy of the current frame
+    gained: Vec<f32>,
+    /// Region JS writes frames into for `process_at`
+    input: Vec<f32>,
+    memoize: bool,
+    /// Detection of the last frame analyzed
+    memo: Option<Memo>,
+    cache_hits: u32,
+    #[cfg(feature = "debug-callback")]
+    debug_hook: Option<DebugHook>,
+}
+
+/// What `detect` found for a frame, kept for memoization
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Memo {
+    hash: u64,
+    /// `last_frequency` when continuity was consulted, else -1.0
+    previous: f32,
+    frequency: f32,
+    clarity: f32,
+}
+
+#[cfg(feature = "debug-callback")]
+type DebugHook = Box<dyn FnMut(&DetectionDiagnostics)>;
+
+/// Per-frame decision details passed to the debug hook
+#[cfg(feature = "debug-callback")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectionDiagnostics {
+    /// Detected frequency in Hz, or -1.0 if no pitch detected
+    pub frequency: f32,
+    /// Interpolated tau of the chosen trough, 0.0 if none
+    pub tau: f32,
+    /// 1.0 minus the CMNDF at the chosen trough, 0.0 if none
+    pub clarity: f32,
+    /// The frame was rejected by the RMS gate before analysis
+    pub gated: bool,
+}
+
+#[wasm_bindgen]
+impl PitchDetector {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            continuity: false,
+            last_frequency: -1.0,
+            difference: Vec::new(),
+            cmndf: Vec::new(),
+            clarity_window: 1,
+            clarity_history: VecDeque::new(),
+            hold_policy: HoldPolicy::Immediate,
+            held_frequency: -1.0,
+            unvoiced_run: 0,
+            previous_rms: 0.0,
+            warmup_frames: 0,
+            warmup_tolerance_cents: 0.0,
+            stable_run: 0,
+            dropout_frames: 0,
+            quiet_run: 0,
+            silence_timeout_frames: 0,
+            auto_gain: None,
+            gained: Vec::new(),
+            input: Vec::new(),
+            memoize: false,
+            memo: None,
+            cache_hits: 0,
+            #[cfg(feature = "debug-callback")]
+            debug_hook: None,
+        }
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+        self.memo = None;
+    }
+
+    /// Whether octave jumps away from the previous pitch are suppressed
+    pub fn continuity(&self) -> bool {
+        self.continuity
+    }
+
+    pub fn set_continuity(&mut self, enabled: bool) {
+        self.continuity = enabled;
+        self.memo = None;
+    }
+
+    /// Most recent voiced frequency in Hz, or -1.0 if none since the last reset
+    pub fn last_frequency(&self) -> f32 {
+        self.last_frequency
+    }
+
+    /// Consecutive unvoiced frames since the last voiced one (or since the
+    /// last reset), 0 right after a voiced frame
+    /// Counts frames without a detected pitch even while the hold policy
+    /// still reports one, so a UI can fade the display on its own schedule
+    pub fn frames_since_voiced(&self) -> u32 {
+        u32::try_from(self.unvoiced_run).unwrap_or(u32::MAX)
+    }
+
+    /// Number of recent frames whose clarity is averaged for `voiced`
+    pub fn clarity_window(&self) -> usize {
+        self.clarity_window
+    }
+
+    /// A window of 0 is treated as 1 (the current frame only)
+    pub fn set_clarity_window(&mut self, frames: usize) {
+        self.clarity_window = frames.max(1);
+        while self.clarity_history.len() > self.clarity_window {
+            self.clarity_history.pop_front();
+        }
+    }
+
+    /// Mean clarity (1.0 minus the trough's CMNDF) over the clarity window,
+    /// 0.0 before any frame has been processed
+    pub fn mean_clarity(&self) -> f32 {
+        if self.clarity_history.is_empty() {
+            return 0.0;
+        }
+        self.clarity_history.iter().sum::<f32>() / self.clarity_history.len() as f32
+    }
+
+    /// Whether the recent frames are voiced on average
+    /// True when the mean clarity clears the level a single frame needs to
+    /// pass the threshold, so one marginal frame between clear ones does not
+    /// drop the voiced state (with a window above 1)
+    pub fn voiced(&self) -> bool {
+        !self.clarity_history.is_empty() && self.mean_clarity() >= 1.0 - self.threshold
+    }
+
+    /// Forget the pitch and clarity history
+    pub fn reset(&mut self) {
+        self.forget_history();
+        self.unvoiced_run = 0;
+        self.quiet_run = 0;
+    }
+
+    /// Hold back a new note until its pitch settles
+    /// After an onset (or a reset), `process` reports -1.0 until `frames`
+    /// consecutive voiced frames each lie within `tolerance_cents` of the
+    /// one before, so the display does not flail through the attack. A note
+    /// already being reported, including one bridged by the hold policy, is
+    /// not held back again. `frames` of 0 or 1 turns warmup off
+    pub fn set_warmup(&mut self, frames: usize, tolerance_cents: f32) {
+        self.warmup_frames = frames;
+        self.warmup_tolerance_cents = tolerance_cents;
+    }
+
+    pub fn clear_warmup(&mut self) {
+        self.warmup_frames = 0;
+    }
+
+    /// Agreeing frames a new note needs before it is reported, 0 if warmup
+    /// is off
+    pub fn warmup_frames(&self) -> usize {
+        self.warmup_frames
+    }
+
+    /// Ride out brief dips below the silence gate within a note
+    /// A note keeps being reported through quiet frames, such as those
+    /// between bow changes, until the level has stayed below the gate for
+    /// `frames` consecutive frames; only then is it released. Unpitched but
+    /// audible frames are left to the hold policy. `frames` of 0 or 1 turns
+    /// the gate off
+    pub fn set_dropout_frames(&mut self, frames: usize) {
+        self.dropout_frames = frames;
+    }
+
+    /// Quiet frames in a row that release a note, 0 if the gate is off
+    pub fn dropout_frames(&self) -> usize {
+        self.dropout_frames
+    }
+
+    /// Report -1.0 for every unvoiced frame, see `HoldPolicy::Immediate`
+    pub fn set_hold_immediate(&mut self) {
+        self.set_hold_policy(HoldPolicy::Immediate);
+    }
+
+    /// Keep reporting the last pitch for up to `frames` consecutive unvoiced
+    /// frames, see `HoldPolicy::Hold`
+    pub fn set_hold_frames(&mut self, frames: usize) {
+        self.set_hold_policy(HoldPolicy::Hold(frames));
+    }
+
+    /// Keep reporting the last pitch while a note rings out, see
+    /// `HoldPolicy::FadeOut`
+    pub fn set_hold_fade_out(&mut self) {
+        self.set_hold_policy(HoldPolicy::FadeOut);
+    }
+
+    /// Forget the history after `frames` consecutive unvoiced frames, as
+    /// `reset` would, so the next note starts fresh instead of being pulled
+    /// toward (or held back by) one that ended long ago. The timeout frame
+    /// reports -1.0 whatever the hold policy. Unlike `reset`, the unvoiced
+    /// run is kept, so `frames_since_voiced` goes on counting through the
+    /// silence. `frames` of 0 turns the timeout off
+    pub fn set_silence_timeout_frames(&mut self, frames: usize) {
+        self.silence_timeout_frames = frames;
+    }
+
+    /// Unvoiced frames in a row that reset the detector, 0 if never
+    pub fn silence_timeout_frames(&self) -> usize {
+        self.silence_timeout_frames
+    }
+
+    /// Run every frame through an `AutoGain` before detection, so quiet input
+    /// clears the silence gate and levels stay consistent between sessions
+    pub fn set_auto_gain(&mut self, target_rms: f32, max_gain: f32) {
+        self.auto_gain = Some(AutoGain::new(target_rms, max_gain));
+        self.memo = None;
+    }
+
+    pub fn clear_auto_gain(&mut self) {
+        self.auto_gain = None;
+        self.memo = None;
+    }
+
+    /// Whether `process` skips analyzing frames identical to the previous one
+    pub fn memoize(&self) -> bool {
+        self.memoize
+    }
+
+    /// Opt in to reusing the previous frame's analysis when `process` is
+    /// given the same frame again, as with runs of digital silence or UI
+    /// code re-rendering within one audio block
+    /// Only the YIN analysis is skipped: every call still counts as a frame,
+    /// so the hold policy, warmup, dropout gate, silence timeout and clarity
+    /// window advance exactly as without memoization (the debug hook is not
+    /// called for a reused analysis). Frames are compared by
+    /// an FNV hash of their length, the sample rate and about 256 evenly
+    /// spaced samples, so a change confined to the samples in between goes
+    /// unnoticed
+    pub fn set_memoize(&mut self, enabled: bool) {
+        self.memoize = enabled;
+        self.memo = None;
+    }
+
+    /// Frames whose analysis was reused since the detector was created
+    pub fn cache_hits(&self) -> u32 {
+        self.cache_hits
+    }
+
+    /// Reallocate the scratch buffers for frames of up to `max_buffer_size`
+    /// samples and forget all history, as a new detector would
+    /// Frames of other sizes still work, but a longer one grows the buffers
+    /// again; shrinking releases the memory a larger block size needed
+    pub fn resize(&mut self, max_buffer_size: usize) {
+        let lags = max_buffer_size / 2;
+        self.difference = Vec::with_capacity(lags);
+        self.cmndf = Vec::with_capacity(lags);
+        self.reset();
+    }
+
+    /// Detect the pitch of one frame
+    /// `sample_rate` may differ between calls: the pitch history is kept in
+    /// Hz and every lag is derived from the rate passed to the current call.
+    /// Returns the detected frequency in Hz, or for an unvoiced frame -1.0 or
+    /// the held pitch as the hold policy decides
+    pub fn process(&mut self, samples: &[f32], sample_rate: f32) -> f32 {
+        match self.auto_gain.as_mut() {
+            Some(auto_gain) => {
+                let mut gained = std::mem::take(&mut self.gained);
+                gained.clear();
+                gained.extend_from_slice(samples);
+                auto_gain.process(&mut gained);
+                let frequency = self.process_frame(&gained, sample_rate);
+                self.gained = gained;
+                frequency
+            }
+            None => self.process_frame(samples, sample_rate),
+        }
+    }
+
+    /// Allocate a zeroed input region of `len` samples inside the detector and
+    /// return its address in wasm linear memory
+    /// JS wraps it as `new Float32Array(memory.buffer, ptr, len)`, writes
+    /// samples there and calls `process_at`, so frames are never copied
+    /// across the boundary. Contract: the address is valid until the next
+    /// call to this method or until the detector is freed, and only `len`
+    /// samples from it may be written. Any allocation can grow the memory,
+    /// which detaches existing views, so re-create the view whenever
+    /// `memory.buffer` has changed. Nothing may write the region while a
+    /// `process_at` call is running
+    pub fn input_region(&mut self, len: usize) -> *mut f32 {
+        self.input = vec![0.0; len];
+        self.input.as_mut_ptr()
+    }
+
+    /// `process` on `len` samples starting `offset` samples into the input
+    /// region, reading them in place
+    /// Returns -1.0 without touching any history if the span does not lie
+    /// within the region
+    pub fn process_at(&mut self, offset: usize, len: usize, sample_rate: f32) -> f32 {
+        let end = match offset.checked_add(len) {
+            Some(end) if end <= self.input.len() => end,
+            _ => return -1.0,
+        };
+        let input = std::mem::take(&mut self.input);
+        let frequency = self.process(&input[offset..end], sample_rate);
+        self.input = input;
+        frequency
+    }
+
+    /// Detect one frame and return its deviation from `target_hz` in cents
+    /// Positive means sharp; NaN if no pitch detected
+    pub fn cents_error_to(&mut self, samples: &[f32], sample_rate: f32, target_hz: f32) -> f32 {
+        cents_between(self.process(samples, sample_rate), target_hz)
+    }
+}
+
+impl Default for PitchDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "debug-callback")]
+#[wasm_bindgen]
+impl PitchDetector {
+    /// Call `callback` after every processed frame with an object
+    /// `{ frequency, tau, clarity, gated }` describing the decision
+    pub fn set_debug_callback(&mut self, callback: &js_sys::Function) {
+        let callback = callback.clone();
+        self.set_debug_hook(move |diagnostics| {
+            let object = js_sys::Object::new();
+            let fields = [
+                ("frequency", JsValue::from(diagnostics.frequency)),
+                ("tau", JsValue::from(diagnostics.tau)),
+                ("clarity", JsValue::from(diagnostics.clarity)),
+                ("gated", JsValue::from(diagnostics.gated)),
+            ];
+            for (key, value) in fields {
+                // Setting a property on a fresh plain object cannot fail
+                let _ = js_sys::Reflect::set(&object, &JsValue::from_str(key), &value);
+            }
+            // Errors thrown by the callback are not the detector's concern
+            let _ = callback.call1(&JsValue::NULL, &object);
+        });
+    }
+
+    pub fn clear_debug_callback(&mut self) {
+        self.debug_hook = None;
+    }
+}
+
+#[cfg(feature = "debug-callback")]
+impl PitchDetector {
+    /// Rust-side equivalent of `set_debug_callback`
+    pub fn set_debug_hook(&mut self, hook: impl FnMut(&DetectionDiagnostics) + 'static) {
+        self.debug_hook = Some(Box::new(hook));
+    }
+
+    fn emit(&mut self, frequency: f32, tau: f32, cmndf: f32, gated: bool) {
+        if let Some(hook) = self.debug_hook.as_mut() {
+            hook(&DetectionDiagnostics {
+                frequency,
+                tau,
+                clarity: 1.0 - cmndf,
+                gated,
+            });
+        }
+    }
+}
+
+#[cfg(not(feature = "debug-callback"))]
+impl PitchDetector {
+    #[inline]
+    fn emit(&mut self, _frequency: f32, _tau: f32, _cmndf: f32, _gated: bool) {}
+}
+
+impl PitchDetector {
+    pub fn hold_policy(&self) -> HoldPolicy {
+        self.hold_policy
+    }
+
+    pub fn set_hold_policy(&mut self, policy: HoldPolicy) {
+        self.hold_policy = policy;
+    }
+
+    /// `process` on the frame actually analyzed, after any auto gain
+    fn process_frame(&mut self, samples: &[f32], sample_rate: f32) -> f32 {
+        let frequency = self.detect_memoized(samples, sample_rate);
+        let rms = calculate_rms(samples);
+        self.quiet_run = if rms < RMS_THRESHOLD {
+            self.quiet_run + 1
+        } else {
+            0
+        };
+        if frequency > 0.0 {
+            let continues = self.unvoiced_run == 0
+                && self.last_frequency > 0.0
+                && cents_between(frequency, self.last_frequency).abs()
+                    <= self.warmup_tolerance_cents;
+            self.stable_run = if continues { self.stable_run + 1 } else { 1 };
+            self.last_frequency = frequency;
+            self.unvoiced_run = 0;
+            if self.held_frequency > 0.0 || self.stable_run >= self.warmup_frames {
+                self.held_frequency = frequency;
+            }
+        } else {
+            self.unvoiced_run += 1;
+            let keep = match self.hold_policy {
+                HoldPolicy::Immediate => false,
+                HoldPolicy::Hold(frames) => self.unvoiced_run <= frames,
+                HoldPolicy::FadeOut => rms >= RMS_THRESHOLD && rms < self.previous_rms,
+            } || (self.quiet_run > 0 && self.quiet_run < self.dropout_frames);
+            if !keep {
+                self.held_frequency = -1.0;
+            }
+            if self.silence_timeout_frames > 0 && self.unvoiced_run >= self.silence_timeout_frames {
+                self.forget_history();
+                return -1.0;
+            }
+        }
+        self.previous_rms = rms;
+        self.held_frequency
+    }
+
+    /// Everything `reset` clears except the unvoiced and quiet runs
+    fn forget_history(&mut self) {
+        self.last_frequency = -1.0;
+        self.clarity_history.clear();
+        self.held_frequency = -1.0;
+        self.previous_rms = 0.0;
+        self.stable_run = 0;
+        self.memo = None;
+        if let Some(auto_gain) = self.auto_gain.as_mut() {
+            auto_gain.reset();
+        }
+    }
+
+    /// `detect`, reusing the previous frame's result when memoizing and the
+    /// frame (and, with continuity, the pitch it was biased toward) is the
+    /// same
+    fn detect_memoized(&mut self, samples: &[f32], sample_rate: f32) -> f32 {
+        if !self.memoize {
+            return self.detect(samples, sample_rate);
+        }
+        let hash = frame_hash(samples, sample_rate);
+        let previous = if self.continuity {
+            self.last_frequency
+        } else {
+            -1.0
+        };
+        if let Some(memo) = self.memo {
+            if memo.hash == hash && memo.previous.to_bits() == previous.to_bits() {
+                self.cache_hits = self.cache_hits.saturating_add(1);
+                self.record_clarity(memo.clarity);
+                return memo.frequency;
+            }
+        }
+        let frequency = self.detect(samples, sample_rate);
+        self.memo = Some(Memo {
+            hash,
+            previous,
+            frequency,
+            clarity: self.clarity_history.back().copied().unwrap_or(0.0),
+        });
+        frequency
+    }
+
+    fn detect(&mut self, samples: &[f32], sample_rate: f32) -> f32 {
+        let buffer_size = samples.len();
+        if buffer_size < 2 || calculate_rms(samples) < RMS_THRESHOLD {
+            self.record_clarity(0.0);
+            self.emit(-1.0, 0.0, 1.0, true);
+            return -1.0;
+        }
+
+        let half_buffer_size = buffer_size / 2;
+        self.difference.resize(half_buffer_size, 0.0);
+        self.cmndf.resize(half_buffer_size, 0.0);
+        difference_function(samples, &mut self.difference);
+        cumulative_mean_normalized_difference(&self.difference, &mut self.cmndf);
+
+        let mut tau = match absolute_threshold(&self.cmndf, self.threshold) {
+            Some(t) => t,
+            None => {
+                // Still record how close the frame came
+                let deepest = self.cmndf.iter().skip(2).copied().fold(1.0f32, f32::min);
+                self.record_clarity(1.0 - deepest);
+                self.emit(-1.0, 0.0, deepest, false);
+                return -1.0;
+            }
+        };
+
+        if self.continuity && self.last_frequency > 0.0 {
+            if let Some(previous_tau) = self.continuity_tau(tau, sample_rate) {
+                tau = previous_tau;
+            }
+        }
+
+        let better_tau = parabolic_interpolation(&self.cmndf, tau);
+        let frequency = sample_rate / better_tau;
+        if !(MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+            // A clean trough outside the range must not keep the frame voiced
+            self.record_clarity(0.0);
+            self.emit(-1.0, better_tau, 1.0, false);
+            return -1.0;
+        }
+        self.record_clarity(1.0 - self.cmndf[tau]);
+        self.emit(frequency, better_tau, self.cmndf[tau], false);
+        frequency
+    }
+
+    fn record_clarity(&mut self, clarity: f32) {
+        if self.clarity_history.len() == self.clarity_window {
+            self.clarity_history.pop_front();
+        }
+        self.clarity_history.push_back(clarity);
+    }
+
+    /// If `tau` is an octave away from the previous pitch and the previous
+    /// pitch's trough is comparably deep, return that trough instead
+    fn continuity_tau(&self, tau: usize, sample_rate: f32) -> Option<usize> {
+        let candidate = sample_rate / tau as f32;
+        let octaves = (candidate / self.last_frequency).log2();
+        let off_by = (octaves.abs() - 1.0).abs() * 1200.0;
+        if off_by > OCTAVE_JUMP_TOLERANCE_CENTS {
+            return None;
+        }
+
+        let expected_tau = sample_rate / self.last_frequency;
+        let radius = (expected_tau * CONTINUITY_SEARCH_RADIUS).ceil();
+        let lo = ((expected_tau - radius).max(2.0)) as usize;
+        let hi = ((expected_tau + radius) as usize).min(self.cmndf.len() - 1);
+        if lo >= hi {
+            return None;
+        }
+
+        let trough = (lo..=hi).min_by(|&a, &b| self.cmndf[a].total_cmp(&self.cmndf[b]))?;
+        (self.cmndf[trough] <= self.cmndf[tau] + CONTINUITY_MARGIN).then_some(trough)
+    }
+}
+
+/// FNV-1a over a frame's length, the sample rate and a decimated subset of
+/// its samples
+fn frame_hash(samples: &[f32], sample_rate: f32) -> u64 {
+    let stride = (samples.len() / MEMO_HASH_SAMPLES).max(1);
+    let words = [samples.len() as u64, u64::from(sample_rate.to_bits())]
+        .into_iter()
+        .chain(
+            samples
+                .iter()
+                .step_by(stride)
+                .map(|x| u64::from(x.to_bits())),
+        );
+    words.fold(FNV_OFFSET_BASIS, |hash, word| {
+        word.to_le_bytes().iter().fold(hash, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{add_noise, generate_tone, NoiseKind, Waveform};
+
+    fn generate_sine_wave(frequency: f32, sample_rate: f32, duration_samples: usize) -> Vec<f32> {
+        generate_tone(frequency, sample_rate, duration_samples, Waveform::Sine)
+    }
+
+    /// A weak A2 fundamental under a strong A3 second harmonic, which plain
+    /// YIN reads as A3
+    fn octave_ambiguous_a2(sample_rate: f32, len: usize) -> Vec<f32> {
+        let fundamental = generate_sine_wave(110.0, sample_rate, len);
+        let harmonic = generate_sine_wave(220.0, sample_rate, len);
+        fundamental
+            .iter()
+            .zip(&harmonic)
+            .map(|(f, h)| 0.15 * f + h)
+            .collect()
+    }
+
+    #[test]
+    fn test_process_matches_detect_pitch() {
+        let sample_rate = 44100.0;
+        let samples = generate_sine_wave(440.0, sample_rate, 2048);
+        let mut detector = PitchDetector::new();
+        assert_eq!(
+            detector.process(&samples, sample_rate),
+            crate::detect_pitch(&samples, sample_rate)
+        );
+        assert_eq!(
+            detector.last_frequency(),
+            detector.process(&samples, sample_rate)
+        );
+    }
+
+    #[test]
+    fn test_wasm_hold_setters_match_policies() {
+        let mut detector = PitchDetector::new();
+        detector.set_hold_frames(4);
+        assert_eq!(detector.hold_policy(), HoldPolicy::Hold(4));
+        detector.set_hold_fade_out();
+        assert_eq!(detector.hold_policy(), HoldPolicy::FadeOut);
+        detector.set_hold_immediate();
+        assert_eq!(detector.hold_policy(), HoldPolicy::Immediate);
+    }
+
+    #[test]
+    fn test_hold_policies_bridge_unvoiced_frames() {
+        let sample_rate = 44100.0;
+        let tone = generate_sine_wave(330.0, sample_rate, 2048);
+        let silence = [0.0; 2048];
+
+        let mut immediate = PitchDetector::new();
+        immediate.process(&tone, sample_rate);
+        assert_eq!(immediate.process(&silence, sample_rate), -1.0);
+
+        let mut hold = PitchDetector::new();
+        hold.set_hold_policy(HoldPolicy::Hold(3));
+        let voiced = hold.process(&tone, sample_rate);
+        assert!((voiced - 330.0).abs() < 2.0);
+        assert_eq!(hold.process(&silence, sample_rate), voiced);
+        assert_eq!(hold.process(&tone, sample_rate), voiced);
+        for _ in 0..3 {
+            assert_eq!(hold.process(&silence, sample_rate), voiced);
+        }
+        assert_eq!(hold.process(&silence, sample_rate), -1.0);
+        assert_eq!(hold.process(&silence, sample_rate), -1.0);
+
+        // Unpitched frames getting quieter, then louder again
+        // Practically pure noise at unit RMS
+        let mut noise = generate_sine_wave(330.0, sample_rate, 2048);
+        add_noise(&mut noise, -30.0, NoiseKind::White);
+        let unit = 1.0 / calculate_rms(&noise);
+        let scaled = |gain: f32| -> Vec<f32> { noise.iter().map(|x| gain * unit * x).collect() };
+        let mut fade = PitchDetector::new();
+        fade.set_hold_policy(HoldPolicy::FadeOut);
+        fade.process(&tone, sample_rate);
+        assert_eq!(fade.process(&scaled(0.5), sample_rate), voiced);
+        assert_eq!(fade.process(&scaled(0.3), sample_rate), voiced);
+        assert_eq!(fade.process(&scaled(0.6), sample_rate), -1.0);
+        assert_eq!(fade.process(&scaled(0.2), sample_rate), -1.0);
+    }
+
+    #[test]
+    fn test_auto_gain_lifts_quiet_tone() {
+        let sample_rate = 44100.0;
+        // Under the silence gate on its own
+        let quiet: Vec<f32> = generate_sine_wave(220.0, sample_rate, 2048)
+            .iter()
+            .map(|x| 0.005 * x)
+            .collect();
+
+        let mut plain = PitchDetector::new();
+        let mut gained = PitchDetector::new();
+        gained.set_auto_gain(0.1, 30.0);
+        for _ in 0..4 {
+            plain.process(&quiet, sample_rate);
+            gained.process(&quiet, sample_rate);
+        }
+        assert_eq!(plain.mean_clarity(), 0.0);
+        assert!(gained.mean_clarity() > 0.9, "{}", gained.mean_clarity());
+        assert!((gained.last_frequency() - 220.0).abs() < 1.0);
+
+        // Silence stays silent
+        assert_eq!(gained.process(&[0.0; 2048], sample_rate), -1.0);
+    }
+
+    #[test]
+    fn test_process_at_reads_input_region_in_place() {
+        let sample_rate = 44100.0;
+        let tone = generate_sine_wave(330.0, sample_rate, 2048);
+        let mut detector = PitchDetector::new();
+        let ptr = detector.input_region(4096);
+
+        // What JS does through a Float32Array view of linear memory
+        // SAFETY: `ptr` addresses the 4096 samples just allocated, and the
+        // detector is not used while this view is alive
+        let view = unsafe { std::slice::from_raw_parts_mut(ptr, 4096) };
+        view[1024..3072].copy_from_slice(&tone);
+
+        let detected = detector.process_at(1024, 2048, sample_rate);
+        assert_eq!(detected, PitchDetector::new().process(&tone, sample_rate));
+        assert!((detected - 330.0).abs() < 1.0);
+
+        // Spans leaving the region are refused without affecting history
+        assert_eq!(detector.process_at(3000, 2048, sample_rate), -1.0);
+        assert_eq!(detector.process_at(usize::MAX, 2, sample_rate), -1.0);
+        assert_eq!(detector.last_frequency(), detected);
+    }
+
+    #[test]
+    fn test_frames_since_voiced() {
+        let sample_rate = 44100.0;
+        let tone = generate_sine_wave(262.0, sample_rate, 2048);
+        let mut detector = PitchDetector::new();
+        assert_eq!(detector.frames_since_voiced(), 0);
+
+        detector.process(&tone, sample_rate);
+        assert_eq!(detector.frames_since_voiced(), 0);
+        for expected in 1..=4 {
+            detector.process(&[0.0; 2048], sample_rate);
+            assert_eq!(detector.frames_since_voiced(), expected);
+        }
+        detector.process(&tone, sample_rate);
+        assert_eq!(detector.frames_since_voiced(), 0);
+    }
+
+    #[test]
+    fn test_warmup_suppresses_unsettled_onset() {
+        let sample_rate = 44100.0;
+        let attack = generate_sine_wave(500.0, sample_rate, 2048);
+        let tone = generate_sine_wave(330.0, sample_rate, 2048);
+        let silence = [0.0; 2048];
+        let mut detector = PitchDetector::new();
+        detector.set_warmup(3, 10.0);
+
+        // The attack frame and the first two settled ones are held back
+        let outputs: Vec<f32> = [&attack, &tone, &tone, &tone, &tone]
+            .iter()
+            .map(|frame| detector.process(frame, sample_rate))
+            .collect();
+        assert_eq!(outputs[..3], [-1.0; 3]);
+        assert!(
+            outputs[3..].iter().all(|f| (f - 330.0).abs() < 2.0),
+            "{:?}",
+            outputs
+        );
+        // Detection itself went on underneath
+        assert_eq!(detector.frames_since_voiced(), 0);
+
+        // A move to another note mid-phrase is reported at once
+        assert!((detector.process(&attack, sample_rate) - 500.0).abs() < 2.0);
+
+        // Silence ends the note; the next onset warms up again
+        assert_eq!(detector.process(&silence, sample_rate), -1.0);
+        assert_eq!(detector.process(&tone, sample_rate), -1.0);
+        assert_eq!(detector.process(&tone, sample_rate), -1.0);
+        assert!((detector.process(&tone, sample_rate) - 330.0).abs() < 2.0);
+
+        detector.clear_warmup();
+        detector.reset();
+        assert!((detector.process(&tone, sample_rate) - 330.0).abs() < 2.0);
+    }
+
+    #[test]
+    fn test_dropout_gate_ignores_brief_dip() {
+        let sample_rate = 44100.0;
+        let tone = generate_sine_wave(196.0, sample_rate, 2048);
+        let dip = [0.0; 2048];
+
+        let mut plain = PitchDetector::new();
+        plain.process(&tone, sample_rate);
+        assert_eq!(plain.process(&dip, sample_rate), -1.0);
+
+        let mut gated = PitchDetector::new();
+        gated.set_dropout_frames(3);
+        let voiced = gated.process(&tone, sample_rate);
+        assert!((voiced - 196.0).abs() < 2.0);
+        assert_eq!(gated.process(&dip, sample_rate), voiced);
+        assert!((gated.process(&tone, sample_rate) - 196.0).abs() < 2.0);
+
+        // Staying quiet for the full count ends the note
+        let voiced = gated.last_frequency();
+        assert_eq!(gated.process(&dip, sample_rate), voiced);
+        assert_eq!(gated.process(&dip, sample_rate), voiced);
+        assert_eq!(gated.process(&dip, sample_rate), -1.0);
+        assert_eq!(gated.process(&dip, sample_rate), -1.0);
+
+        // Quiet frames before any note report nothing
+        gated.reset();
+        assert_eq!(gated.process(&dip, sample_rate), -1.0);
+    }
+
+    #[test]
+    fn test_silence_timeout_resets_history() {
+        let sample_rate = 44100.0;
+        let tone = generate_sine_wave(294.0, sample_rate, 2048);
+        let silence = [0.0; 2048];
+        let mut detector = PitchDetector::new();
+        detector.set_hold_policy(HoldPolicy::Hold(10));
+        detector.set_silence_timeout_frames(4);
+
+        let voiced = detector.process(&tone, sample_rate);
+        for _ in 0..3 {
+            assert_eq!(detector.process(&silence, sample_rate), voiced);
+            assert_eq!(detector.last_frequency(), voiced);
+        }
+        // The fourth silent frame times out despite the longer hold
+        assert_eq!(detector.process(&silence, sample_rate), -1.0);
+        assert_eq!(detector.last_frequency(), -1.0);
+        assert_eq!(detector.frames_since_voiced(), 4);
+        // The unvoiced count runs on past the timeout
+        for expected in 5..=12 {
+            assert_eq!(detector.process(&silence, sample_rate), -1.0);
+            assert_eq!(detector.frames_since_voiced(), expected);
+        }
+
+        // Off by default
+        let mut plain = PitchDetector::new();
+        plain.process(&tone, sample_rate);
+        for _ in 0..10 {
+            plain.process(&silence, sample_rate);
+        }
+        assert_eq!(plain.last_frequency(), voiced);
+    }
+
+    #[test]
+    fn test_memoize_skips_repeated_frame() {
+        let sample_rate = 44100.0;
+        let tone = generate_sine_wave(330.0, sample_rate, 2048);
+        let other = generate_sine_wave(440.0, sample_rate, 2048);
+        let mut detector = PitchDetector::new();
+        detector.process(&tone, sample_rate);
+        detector.process(&tone, sample_rate);
+        assert_eq!(detector.cache_hits(), 0);
+
+        detector.set_memoize(true);
+        let first = detector.process(&tone, sample_rate);
+        assert_eq!(detector.process(&tone, sample_rate), first);
+        assert_eq!(detector.cache_hits(), 1);
+        // Settings applied after detection keep the memo...
+        detector.set_hold_policy(HoldPolicy::Hold(1));
+        detector.set_warmup(3, 50.0);
+        assert_eq!(detector.process(&tone, sample_rate), first);
+        assert_eq!(detector.cache_hits(), 2);
+        // ...while one that changes the analysis starts over
+        detector.set_threshold(DEFAULT_THRESHOLD);
+        assert_eq!(detector.process(&tone, sample_rate), first);
+        assert_eq!(detector.process(&tone, sample_rate), first);
+        assert_eq!(detector.cache_hits(), 3);
+
+        // A new frame, a new rate or a reset is computed afresh
+        assert!((detector.process(&other, sample_rate) - 440.0).abs() < 2.0);
+        detector.process(&other, 48000.0);
+        detector.reset();
+        detector.process(&other, 48000.0);
+        assert_eq!(detector.cache_hits(), 3);
+    }
+
+    #[test]
+    fn test_memoized_silence_still_advances_history() {
+        let sample_rate = 44100.0;
+        let tone = generate_sine_wave(330.0, sample_rate, 2048);
+        let silence = [0.0; 2048];
+        let run = |memoize: bool, configure: &dyn Fn(&mut PitchDetector)| {
+            let mut detector = PitchDetector::new();
+            configure(&mut detector);
+            detector.set_memoize(memoize);
+            let mut outputs = vec![detector.process(&tone, sample_rate)];
+            for _ in 0..8 {
+                outputs.push(detector.process(&silence, sample_rate));
+            }
+            (
+                outputs,
+                detector.frames_since_voiced(),
+                detector.cache_hits(),
+            )
+        };
+
+        let hold = |d: &mut PitchDetector| d.set_hold_policy(HoldPolicy::Hold(2));
+        let (plain, plain_since, _) = run(false, &hold);
+        let (memoized, memoized_since, hits) = run(true, &hold);
+        assert_eq!(memoized, plain);
+        assert_eq!(memoized[3..], [-1.0; 6]);
+        assert_eq!(memoized_since, 8);
+        assert_eq!(plain_since, 8);
+        assert_eq!(hits, 7);
+
+        let timeout = |d: &mut PitchDetector| {
+            d.set_hold_policy(HoldPolicy::Hold(10));
+            d.set_silence_timeout_frames(4);
+        };
+        let (plain, plain_since, _) = run(false, &timeout);
+        let (memoized, memoized_since, _) = run(true, &timeout);
+        assert_eq!(memoized, plain);
+        assert_eq!(memoized[4..], [-1.0; 5]);
+        assert_eq!(memoized_since, plain_since);
+    }
+
+    #[test]
+    fn test_resize_up_and_down() {
+        let sample_rate = 44100.0;
+        let mut detector = PitchDetector::new();
+        detector.set_hold_policy(HoldPolicy::Hold(2));
+        detector.process(&generate_sine_wave(330.0, sample_rate, 2048), sample_rate);
+
+        detector.resize(4096);
+        assert_eq!(detector.last_frequency(), -1.0);
+        // History is gone, so nothing is held over the silence
+        assert_eq!(detector.process(&[0.0; 4096], sample_rate), -1.0);
+        let low = generate_sine_wave(82.41, sample_rate, 4096);
+        assert!((detector.process(&low, sample_rate) - 82.41).abs() < 0.5);
+        assert!(detector.difference.capacity() >= 2048);
+
+        detector.resize(512);
+        assert!(detector.difference.capacity() < 2048);
+        let high = generate_sine_wave(880.0, sample_rate, 512);
+        assert!((detector.process(&high, sample_rate) - 880.0).abs() < 3.0);
+    }
+
+    #[test]
+    fn test_sample_rate_may_change_between_calls() {
+        let mut detector = PitchDetector::new();
+        detector.set_continuity(true);
+        for sample_rate in [44100.0, 48000.0, 44100.5, 22050.0, 48000.0] {
+            let samples = generate_sine_wave(220.0, sample_rate, 2048);
+            let f = detector.process(&samples, sample_rate);
+            assert!((f - 220.0).abs() < 1.0, "{} Hz at {}", f, sample_rate);
+        }
+    }
+
+    #[test]
+    fn test_continuity_suppresses_octave_jump() {
+        let sample_rate = 44100.0;
+        let steady = generate_sine_wave(110.0, sample_rate, 2048);
+        let errant = octave_ambiguous_a2(sample_rate, 2048);
+
+        let mut plain = PitchDetector::new();
+        for _ in 0..3 {
+            plain.process(&steady, sample_rate);
+        }
+        let jumped = plain.process(&errant, sample_rate);
+        assert!((jumped - 220.0).abs() < 5.0, "expected A3, got {}", jumped);
+
+        let mut detector = PitchDetector::new();
+        detector.set_continuity(true);
+        for _ in 0..3 {
+            let f = detector.process(&steady, sample_rate);
+            assert!((f - 110.0).abs() < 2.0);
+        }
+        let held = detector.process(&errant, sample_rate);
+        assert!((held - 110.0).abs() < 2.0, "expected A2, got {}", held);
+    }
+
+    #[test]
+    fn test_cents_error_to_target() {
+        let sample_rate = 44100.0;
+        let target = 220.0;
+        let sharp = target * 2f32.powf(50.0 / 1200.0);
+        let samples = generate_sine_wave(sharp, sample_rate, 2048);
+
+        let mut detector = PitchDetector::new();
+        let error = detector.cents_error_to(&samples, sample_rate, target);
+        assert!((error - 50.0).abs() < 2.0, "got {} cents", error);
+
+        let silence = vec![0.0f32; 2048];
+        assert!(detector
+            .cents_error_to(&silence, sample_rate, target)
+            .is_nan());
+    }
+
+    #[cfg(feature = "debug-callback")]
+    #[test]
+    fn test_debug_hook_receives_diagnostics() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let sample_rate = 44100.0;
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut detector = PitchDetector::new();
+        let sink = Rc::clone(&log);
+        detector.set_debug_hook(move |d| sink.borrow_mut().push(*d));
+
+        let frequency =
+            detector.process(&generate_sine_wave(441.0, sample_rate, 2048), sample_rate);
+        detector.process(&[0.0; 2048], sample_rate);
+
+        let entries = log.borrow();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].frequency, frequency);
+        assert!((entries[0].tau - 100.0).abs() < 0.5, "{:?}", entries[0]);
+        assert!(entries[0].clarity > 0.9);
+        assert!(!entries[0].gated);
+        assert!(entries[1].gated);
+        assert_eq!(entries[1].frequency, -1.0);
+        drop(entries);
+
+        // A frame that misses the threshold reports the clarity it records
+        let mut marginal = generate_sine_wave(220.0, sample_rate, 2048);
+        crate::testutil::add_noise(&mut marginal, 8.0, crate::testutil::NoiseKind::White);
+        detector.set_clarity_window(1);
+        assert_eq!(detector.process(&marginal, sample_rate), -1.0);
+        let missed = *log.borrow().last().unwrap();
+        assert!(!missed.gated);
+        assert!(missed.clarity > 0.0);
+        assert_eq!(missed.clarity, detector.mean_clarity());
+    }
+
+    #[test]
+    fn test_rolling_clarity_keeps_voiced() {
+        use crate::testutil::{add_noise, NoiseKind};
+
+        let sample_rate = 44100.0;
+        let clear = generate_sine_wave(220.0, sample_rate, 2048);
+        // Just misses the default threshold on its own
+        let mut marginal = clear.clone();
+        add_noise(&mut marginal, 8.0, NoiseKind::White);
+
+        let mut single = PitchDetector::new();
+        single.process(&marginal, sample_rate);
+        assert!(!single.voiced(), "clarity {}", single.mean_clarity());
+
+        let mut detector = PitchDetector::new();
+        detector.set_clarity_window(3);
+        for frame in 0..8 {
+            let samples = if frame % 2 == 0 { &clear } else { &marginal };
+            detector.process(samples, sample_rate);
+            assert!(
+                detector.voiced(),
+                "frame {}: clarity {}",
+                frame,
+                detector.mean_clarity()
+            );
+        }
+
+        // Silence drags the average down
+        for _ in 0..3 {
+            detector.process(&[0.0; 2048], sample_rate);
+        }
+        assert!(!detector.voiced());
+    }
+
+    #[test]
+    fn test_out_of_range_pitch_has_no_clarity() {
+        let sample_rate = 44100.0;
+        let mut detector = PitchDetector::new();
+        let whistle = generate_sine_wave(3000.0, sample_rate, 2048);
+        assert_eq!(detector.process(&whistle, sample_rate), -1.0);
+        assert_eq!(detector.mean_clarity(), 0.0);
+        assert!(!detector.voiced());
+    }
+
+    #[test]
+    fn test_reset_clears_history() {
+        let sample_rate = 44100.0;
+        let mut detector = PitchDetector::new();
+        detector.process(&generate_sine_wave(110.0, sample_rate, 2048), sample_rate);
+        assert!(detector.last_frequency() > 0.0);
+        assert!(detector.voiced());
+        detector.reset();
+        assert_eq!(detector.last_frequency(), -1.0);
+        assert!(!detector.voiced());
+        assert_eq!(detector.mean_clarity(), 0.0);
+    }
+}
@@ -0,0 +1,119 @@
+use std::f32::consts::PI;
+
+use crate::note::{cents_between, cents_to_ratio};
+use crate::yin::{gated_cmndf, local_minima, parabolic_interpolation};
+use crate::{calculate_rms, detect_pitch, MAX_FREQUENCY, MIN_FREQUENCY};
+
+/// CMNDF troughs below this are considered as the first voice
+const CANDIDATE_MAX_CMNDF: f32 = 0.8;
+
+/// The residual must keep at least this fraction of the input RMS to hold
+/// a second voice
+const RESIDUAL_MIN_RMS_RATIO: f32 = 0.1;
+
+/// Half-width of the 1-cent search refining the first voice
+const REFINE_RADIUS_CENTS: i32 = 100;
+
+/// A second estimate this close to the first is leftover of the first voice
+const SAME_VOICE_CENTS: f32 = 50.0;
+
+/// Two simultaneous pitches, strongest first
+/// Picks the first voice among the CMNDF troughs as the one with the most
+/// energy at its own fundamental, which keeps two voices from being read as
+/// the "missing fundamental" of their common period (a fifth apart, YIN alone
+/// finds the octave below the lower note). That voice and all its harmonics
+/// are then removed with a comb filter at its period, and the residual is
+/// detected again. The second entry is -1.0 if no second voice is found; both
+/// are -1.0 if the frame is unvoiced
+pub fn detect_duophonic(samples: &[f32], sample_rate: f32) -> [f32; 2] {
+    let first = match strongest_voice(samples, sample_rate) {
+        Some(f) => f,
+        None => return [-1.0, -1.0],
+    };
+
+    let residual = comb_cancel(samples, sample_rate / first);
+    if calculate_rms(&residual) < RESIDUAL_MIN_RMS_RATIO * calculate_rms(samples) {
+        return [first, -1.0];
+    }
+    let second = detect_pitch(&residual, sample_rate);
+    if second <= 0.0 || cents_between(second, first).abs() < SAME_VOICE_CENTS {
+        return [first, -1.0];
+    }
+    [first, second]
+}
+
+/// Frequency of the CMNDF trough with the most energy at its fundamental
+/// The other voice pulls the trough off the true period, so the pick is
+/// refined to the strongest frequency within a semitone, in 1-cent steps
+fn strongest_voice(samples: &[f32], sample_rate: f32) -> Option<f32> {
+    let cmndf = gated_cmndf(samples)?;
+    let coarse = local_minima(&cmndf)
+        .filter(|&tau| cmndf[tau] < CANDIDATE_MAX_CMNDF)
+        .map(|tau| sample_rate / parabolic_interpolation(&cmndf, tau))
+        .filter(|f| (MIN_FREQUENCY..=MAX_FREQUENCY).contains(f))
+        .map(|f| (f, tone_power(samples, f, sample_rate)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?
+        .0;
+    (-REFINE_RADIUS_CENTS..=REFINE_RADIUS_CENTS)
+        .map(|cents| coarse * cents_to_ratio(cents as f32))
+        .map(|f| (f, tone_power(samples, f, sample_rate)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(f, _)| f)
+}
+
+/// Power of the Hann-windowed signal at a single frequency (one DFT bin at
+/// an arbitrary frequency)
+fn tone_power(samples: &[f32], frequency: f32, sample_rate: f32) -> f32 {
+    let step = 2.0 * PI * frequency / sample_rate;
+    let len = samples.len() as f32;
+    let (re, im) = samples
+        .iter()
+        .enumerate()
+        .fold((0.0f32, 0.0f32), |(re, im), (n, &x)| {
+            let window = 0.5 - 0.5 * (2.0 * PI * n as f32 / len).cos();
+            let (sin, cos) = (step * n as f32).sin_cos();
+            (re + window * x * cos, im - window * x * sin)
+        });
+    re * re + im * im
+}
+
+/// `x[n] - x[n - period]` with a linearly interpolated fractional delay,
+/// which nulls a periodic signal of that period and all its harmonics
+fn comb_cancel(samples: &[f32], period: f32) -> Vec<f32> {
+    let whole = period.floor() as usize;
+    let fraction = period - whole as f32;
+    (whole + 1..samples.len())
+        .map(|n| {
+            let delayed = samples[n - whole] * (1.0 - fraction) + samples[n - whole - 1] * fraction;
+            samples[n] - delayed
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+
+    #[test]
+    fn test_fifth_both_recovered() {
+        let sample_rate = 44100.0;
+        let lower = generate_tone(220.0, sample_rate, 4096, Waveform::Sine);
+        let upper = generate_tone(330.0, sample_rate, 4096, Waveform::Sine);
+        let mixture: Vec<f32> = lower.iter().zip(&upper).map(|(l, u)| l + 0.6 * u).collect();
+
+        let [first, second] = detect_duophonic(&mixture, sample_rate);
+        assert!((first - 220.0).abs() < 2.0, "first {}", first);
+        assert!((second - 330.0).abs() < 3.0, "second {}", second);
+    }
+
+    #[test]
+    fn test_single_voice() {
+        let sample_rate = 44100.0;
+        let saw = generate_tone(196.0, sample_rate, 4096, Waveform::Saw);
+        let [first, second] = detect_duophonic(&saw, sample_rate);
+        assert!((first - 196.0).abs() < 2.0, "first {}", first);
+        assert_eq!(second, -1.0);
+        assert_eq!(detect_duophonic(&[0.0; 4096], sample_rate), [-1.0, -1.0]);
+    }
+}
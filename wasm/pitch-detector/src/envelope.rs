@@ -0,0 +1,90 @@
+use wasm_bindgen::prelude::*;
+
+use crate::calculate_rms;
+
+/// Attack/release level follower for input meters
+/// Each block's RMS is the detector stage; the envelope moves toward it with
+/// a one-pole filter whose time constant is the attack time while rising and
+/// the release time while falling, giving a VU-style reading
+#[wasm_bindgen]
+pub struct EnvelopeFollower {
+    attack_samples: f32,
+    release_samples: f32,
+    envelope: f32,
+}
+
+#[wasm_bindgen]
+impl EnvelopeFollower {
+    /// Attack and release are time constants in milliseconds; zero follows
+    /// the block RMS instantly
+    #[wasm_bindgen(constructor)]
+    pub fn new(attack_ms: f32, release_ms: f32, sample_rate: f32) -> Self {
+        Self {
+            attack_samples: attack_ms.max(0.0) * sample_rate / 1000.0,
+            release_samples: release_ms.max(0.0) * sample_rate / 1000.0,
+            envelope: 0.0,
+        }
+    }
+
+    /// Feed one block and return the updated envelope
+    pub fn process(&mut self, samples: &[f32]) -> f32 {
+        if samples.is_empty() {
+            return self.envelope;
+        }
+        let level = calculate_rms(samples);
+        let time_constant = if level > self.envelope {
+            self.attack_samples
+        } else {
+            self.release_samples
+        };
+        // Fraction of the remaining distance covered over this block
+        let step = if time_constant > 0.0 {
+            1.0 - (-(samples.len() as f32) / time_constant).exp()
+        } else {
+            1.0
+        };
+        self.envelope += (level - self.envelope) * step;
+        self.envelope
+    }
+
+    /// Current envelope value without feeding input
+    pub fn value(&self) -> f32 {
+        self.envelope
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+
+    #[test]
+    fn test_step_rises_then_settles() {
+        let sample_rate = 44100.0;
+        let block = 441; // 10 ms
+        let mut follower = EnvelopeFollower::new(50.0, 300.0, sample_rate);
+        assert_eq!(follower.process(&vec![0.0; block]), 0.0);
+
+        let tone = generate_tone(440.0, sample_rate, block, Waveform::Sine);
+        let target = calculate_rms(&tone);
+        let levels: Vec<f32> = (0..50).map(|_| follower.process(&tone)).collect();
+
+        assert!(levels.windows(2).all(|w| w[1] >= w[0]), "{:?}", levels);
+        // One time constant (5 blocks) in, about 63% of the way there
+        assert!(
+            (levels[4] / target - 0.632).abs() < 0.02,
+            "{} of {}",
+            levels[4],
+            target
+        );
+        assert!((levels[49] - target).abs() < 1e-3 * target);
+
+        // Release is slower than attack
+        let released = follower.process(&vec![0.0; block]);
+        assert!(released > 0.9 * target, "{}", released);
+    }
+}
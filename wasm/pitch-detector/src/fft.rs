@@ -0,0 +1,98 @@
+use std::f32::consts::PI;
+
+/// In-place iterative radix-2 FFT over split real/imaginary buffers
+/// Both buffers must have the same power-of-two length
+pub(crate) fn fft_in_place(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+    if n < 2 {
+        return;
+    }
+
+    // Bit-reversal permutation
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    // Butterflies
+    let mut size = 2;
+    while size <= n {
+        let half = size / 2;
+        let step = -2.0 * PI / size as f32;
+        for start in (0..n).step_by(size) {
+            for k in 0..half {
+                let (sin, cos) = (step * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + half;
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        size *= 2;
+    }
+}
+
+/// Magnitude spectrum of a Hann-windowed frame, zero-padded to a power of two
+/// Returns bins `0..=fft_size / 2` together with `fft_size`; bin `k` is
+/// centred on `k * sample_rate / fft_size` Hz
+pub(crate) fn magnitude_spectrum(samples: &[f32]) -> (Vec<f32>, usize) {
+    let (re, im) = complex_spectrum(samples);
+    let fft_size = re.len();
+    let magnitudes = re
+        .iter()
+        .zip(&im)
+        .take(fft_size / 2 + 1)
+        .map(|(r, i)| (r * r + i * i).sqrt())
+        .collect();
+    (magnitudes, fft_size)
+}
+
+/// Full complex spectrum `(re, im)` of a Hann-windowed frame, zero-padded to
+/// a power of two (at least 2) that is also the length of both halves
+pub(crate) fn complex_spectrum(samples: &[f32]) -> (Vec<f32>, Vec<f32>) {
+    let fft_size = samples.len().next_power_of_two().max(2);
+    let mut re = vec![0.0f32; fft_size];
+    let mut im = vec![0.0f32; fft_size];
+    let len = samples.len();
+    for (i, (value, &sample)) in re.iter_mut().zip(samples).enumerate() {
+        let window = 0.5 - 0.5 * (2.0 * PI * i as f32 / len as f32).cos();
+        *value = sample * window;
+    }
+
+    fft_in_place(&mut re, &mut im);
+    (re, im)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fft_matches_naive_dft() {
+        let n = 16;
+        let signal: Vec<f32> = (0..n).map(|i| ((i * 7 % 5) as f32) - 2.0).collect();
+        let mut re = signal.clone();
+        let mut im = vec![0.0; n];
+        fft_in_place(&mut re, &mut im);
+
+        for k in 0..n {
+            let (mut dft_re, mut dft_im) = (0.0f32, 0.0f32);
+            for (t, &x) in signal.iter().enumerate() {
+                let angle = -2.0 * PI * (k * t) as f32 / n as f32;
+                dft_re += x * angle.cos();
+                dft_im += x * angle.sin();
+            }
+            assert!((re[k] - dft_re).abs() < 1e-3, "bin {}", k);
+            assert!((im[k] - dft_im).abs() < 1e-3, "bin {}", k);
+        }
+    }
+}
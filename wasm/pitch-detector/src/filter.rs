@@ -0,0 +1,273 @@
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{calculate_rms, detect_pitch};
+
+/// Q of the mains notches in `detect_pitch_denotched`: about 2 Hz wide at 60 Hz
+const MAINS_NOTCH_Q: f32 = 30.0;
+
+/// Mains harmonics notched by `detect_pitch_denotched`, fundamental included
+const MAINS_HARMONICS: usize = 3;
+
+/// Second-order IIR section (RBJ audio-EQ cookbook), direct form I
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    fn from_coefficients(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    /// Butterworth low-pass
+    pub(crate) fn low_pass(cutoff_hz: f32, sample_rate: f32) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(cutoff_hz, sample_rate, FRAC_1_SQRT_2);
+        let b1 = 1.0 - cos_w0;
+        Self::from_coefficients(
+            b1 / 2.0,
+            b1,
+            b1 / 2.0,
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Butterworth high-pass
+    pub(crate) fn high_pass(cutoff_hz: f32, sample_rate: f32) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(cutoff_hz, sample_rate, FRAC_1_SQRT_2);
+        let b1 = 1.0 + cos_w0;
+        Self::from_coefficients(
+            b1 / 2.0,
+            -b1,
+            b1 / 2.0,
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Band-reject at `center_hz`; higher `q` gives a narrower notch
+    pub(crate) fn notch(center_hz: f32, q: f32, sample_rate: f32) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(center_hz, sample_rate, q);
+        Self::from_coefficients(
+            1.0,
+            -2.0 * cos_w0,
+            1.0,
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Start in the steady state of a notch that has long been removing `hum`
+    /// from a signal holding `first - hum(0)`, instead of from rest
+    /// `hum(n)` is the interference at sample `n`, negative before the frame
+    fn prime(&mut self, first: f32, hum: impl Fn(f32) -> f32) {
+        let rest = first - hum(0.0);
+        self.x1 = rest + hum(-1.0);
+        self.x2 = rest + hum(-2.0);
+        self.y1 = rest;
+        self.y2 = rest;
+    }
+
+    fn prewarp(frequency: f32, sample_rate: f32, q: f32) -> (f32, f32) {
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    pub(crate) fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+
+    pub(crate) fn process_in_place(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+}
+
+/// Remove a narrow band around `freq_hz` in place, e.g. mains hum
+/// `q` is the centre frequency over the -3 dB bandwidth. The filter starts
+/// from rest, so the notch takes about `q / (PI * freq_hz)` seconds to settle.
+/// Frequencies outside `0..sample_rate / 2` leave the samples untouched
+#[wasm_bindgen]
+pub fn notch_filter(samples: &mut [f32], freq_hz: f32, q: f32, sample_rate: f32) {
+    if !(freq_hz > 0.0 && freq_hz < sample_rate / 2.0 && q > 0.0) {
+        return;
+    }
+    Biquad::notch(freq_hz, q, sample_rate).process_in_place(samples);
+}
+
+/// YIN pitch detection with mains hum notched out first
+/// Narrow notches remove `mains_hz` (50 or 60) and its first harmonics. A
+/// notch starting from rest would ring for longer than a frame, so each one
+/// is primed with the hum's amplitude and phase as measured over the frame.
+/// Frames should span several hum periods for that measurement to hold.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_denotched(samples: &[f32], sample_rate: f32, mains_hz: f32) -> f32 {
+    let mut cleaned = samples.to_vec();
+    if !cleaned.is_empty() {
+        for harmonic in 1..=MAINS_HARMONICS {
+            let frequency = mains_hz * harmonic as f32;
+            if !(frequency > 0.0 && frequency < sample_rate / 2.0) {
+                break;
+            }
+            let hum = fitted_sinusoid(&cleaned, frequency, sample_rate);
+            let mut notch = Biquad::notch(frequency, MAINS_NOTCH_Q, sample_rate);
+            notch.prime(cleaned[0], hum);
+            notch.process_in_place(&mut cleaned);
+        }
+    }
+    detect_pitch(&cleaned, sample_rate)
+}
+
+/// YIN pitch detection with a weak or missing fundamental reinforced first
+/// Squaring the frame turns every pair of neighbouring harmonics into a
+/// component at their difference, the fundamental, much as the ear hears a
+/// missing fundamental. That product, level-matched to the input and
+/// scaled by `enhancement` (0.0 disables it, around 1.0 is typical), is added
+/// back before detection. Opt-in because it invents energy: a chord or an
+/// inharmonic sound gets difference tones that are no part of any note, and
+/// YIN may lock onto them.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_enhanced(samples: &[f32], sample_rate: f32, enhancement: f32) -> f32 {
+    if samples.is_empty() || enhancement <= 0.0 {
+        return detect_pitch(samples, sample_rate);
+    }
+    let mean_square = samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32;
+    let products: Vec<f32> = samples.iter().map(|x| x * x - mean_square).collect();
+    let product_rms = calculate_rms(&products);
+    if product_rms <= f32::EPSILON {
+        return detect_pitch(samples, sample_rate);
+    }
+
+    let gain = enhancement * calculate_rms(samples) / product_rms;
+    let enhanced: Vec<f32> = samples
+        .iter()
+        .zip(&products)
+        .map(|(x, p)| x + gain * p)
+        .collect();
+    detect_pitch(&enhanced, sample_rate)
+}
+
+/// Least-squares sinusoid at `frequency` over `samples`, as a function of the
+/// sample index
+pub(crate) fn fitted_sinusoid(
+    samples: &[f32],
+    frequency: f32,
+    sample_rate: f32,
+) -> impl Fn(f32) -> f32 {
+    let w = 2.0 * PI * frequency / sample_rate;
+    let (cos_sum, sin_sum) =
+        samples
+            .iter()
+            .enumerate()
+            .fold((0.0f32, 0.0f32), |(c, s), (n, &x)| {
+                let (sin, cos) = (w * n as f32).sin_cos();
+                (c + x * cos, s + x * sin)
+            });
+    let scale = 2.0 / samples.len() as f32;
+    let (a, b) = (cos_sum * scale, sin_sum * scale);
+    move |n| a * (w * n).cos() + b * (w * n).sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, mix_partials, Waveform};
+
+    #[test]
+    fn test_low_pass_attenuates_above_cutoff() {
+        let sample_rate = 44100.0;
+        let mut low = generate_tone(100.0, sample_rate, 4096, Waveform::Sine);
+        let mut high = generate_tone(4000.0, sample_rate, 4096, Waveform::Sine);
+        Biquad::low_pass(500.0, sample_rate).process_in_place(&mut low);
+        Biquad::low_pass(500.0, sample_rate).process_in_place(&mut high);
+        assert!(calculate_rms(&low[1024..]) > 0.65);
+        assert!(calculate_rms(&high[1024..]) < 0.02);
+    }
+
+    #[test]
+    fn test_notch_is_narrow() {
+        let sample_rate = 44100.0;
+        let mut hum = generate_tone(60.0, sample_rate, 88200, Waveform::Sine);
+        let mut nearby = generate_tone(82.41, sample_rate, 88200, Waveform::Sine);
+        notch_filter(&mut hum, 60.0, MAINS_NOTCH_Q, sample_rate);
+        notch_filter(&mut nearby, 60.0, MAINS_NOTCH_Q, sample_rate);
+        // Measured once the notch has settled
+        assert!(calculate_rms(&hum[44100..]) < 0.01);
+        assert!(calculate_rms(&nearby[44100..]) > 0.69);
+    }
+
+    #[test]
+    fn test_enhancement_restores_missing_fundamental() {
+        let sample_rate = 44100.0;
+        // Second and third harmonics of A2, no fundamental
+        let second = generate_tone(220.0, sample_rate, 2048, Waveform::Sine);
+        let third = generate_tone(330.0, sample_rate, 2048, Waveform::Sine);
+        let hollow: Vec<f32> = second
+            .iter()
+            .zip(&third)
+            .map(|(a, b)| a + 0.15 * b)
+            .collect();
+
+        let plain = detect_pitch(&hollow, sample_rate);
+        assert!((plain - 220.0).abs() < 2.0, "plain YIN got {}", plain);
+        assert_eq!(detect_pitch_enhanced(&hollow, sample_rate, 0.0), plain);
+        let enhanced = detect_pitch_enhanced(&hollow, sample_rate, 1.0);
+        assert!((enhanced - 110.0).abs() < 1.0, "got {}", enhanced);
+
+        // A pure tone has no harmonic pairs, so nothing is invented below it
+        let sine = generate_tone(220.0, sample_rate, 2048, Waveform::Sine);
+        let enhanced = detect_pitch_enhanced(&sine, sample_rate, 1.0);
+        assert!((enhanced - 220.0).abs() < 1.0, "got {}", enhanced);
+    }
+
+    #[test]
+    fn test_denotched_detection_through_hum() {
+        let sample_rate = 44100.0;
+        let len = 4096;
+        let tone = generate_tone(82.41, sample_rate, len, Waveform::Saw);
+        let hum = mix_partials(&[(60.0, 1.0), (120.0, 0.3), (180.0, 0.2)], sample_rate, len);
+        let mixed: Vec<f32> = tone.iter().zip(&hum).map(|(t, h)| 0.3 * t + h).collect();
+
+        let raw = detect_pitch(&mixed, sample_rate);
+        assert!(
+            (raw - 82.41).abs() > 5.0,
+            "hum should confuse plain YIN, got {}",
+            raw
+        );
+        let denotched = detect_pitch_denotched(&mixed, sample_rate, 60.0);
+        assert!((denotched - 82.41).abs() < 1.0, "got {}", denotched);
+    }
+}
@@ -0,0 +1,101 @@
+use wasm_bindgen::prelude::*;
+
+use crate::calculate_rms;
+
+/// Blocks quieter than this RMS are left untouched, so the gain never lifts a
+/// silent input's noise floor
+const AUTO_GAIN_SILENCE_RMS: f32 = 1e-4;
+
+/// Fraction of the way from the current to the wanted gain covered per block
+const AUTO_GAIN_STEP: f32 = 0.3;
+
+/// Automatic gain control ahead of detection
+/// Each block's gain moves part of the way toward the one that would bring
+/// its RMS to the target, never above `max_gain`, and is ramped across the
+/// block so changes do not click. Blocks at silence level pass through
+/// unchanged and leave the gain where it was
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoGain {
+    target_rms: f32,
+    max_gain: f32,
+    gain: f32,
+}
+
+#[wasm_bindgen]
+impl AutoGain {
+    /// A `max_gain` below 1.0 is treated as 1.0 (never attenuate below unity
+    /// because of the cap)
+    #[wasm_bindgen(constructor)]
+    pub fn new(target_rms: f32, max_gain: f32) -> Self {
+        Self {
+            target_rms: target_rms.max(0.0),
+            max_gain: max_gain.max(1.0),
+            gain: 1.0,
+        }
+    }
+
+    /// Apply the gain to one block in place
+    pub fn process(&mut self, samples: &mut [f32]) {
+        let rms = calculate_rms(samples);
+        if rms < AUTO_GAIN_SILENCE_RMS {
+            return;
+        }
+        let wanted = (self.target_rms / rms).min(self.max_gain);
+        let start = self.gain;
+        self.gain += (wanted - start) * AUTO_GAIN_STEP;
+
+        let step = (self.gain - start) / samples.len() as f32;
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample *= start + step * (i + 1) as f32;
+        }
+    }
+
+    /// Gain applied at the end of the last block
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    pub fn reset(&mut self) {
+        self.gain = 1.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+
+    #[test]
+    fn test_converges_to_target_within_cap() {
+        let quiet: Vec<f32> = generate_tone(220.0, 44100.0, 1024, Waveform::Sine)
+            .iter()
+            .map(|x| 0.02 * x)
+            .collect();
+        let mut agc = AutoGain::new(0.1, 10.0);
+        let mut block = quiet.clone();
+        for _ in 0..30 {
+            block.copy_from_slice(&quiet);
+            agc.process(&mut block);
+        }
+        assert!((calculate_rms(&block) - 0.1).abs() < 1e-3);
+
+        // Too quiet to reach the target under the cap
+        let whisper: Vec<f32> = quiet.iter().map(|x| 0.01 * x).collect();
+        for _ in 0..30 {
+            block.copy_from_slice(&whisper);
+            agc.process(&mut block);
+        }
+        assert!((agc.gain() - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_silence_is_not_amplified() {
+        let mut agc = AutoGain::new(0.1, 100.0);
+        let hiss: Vec<f32> = (0..1024).map(|i| 1e-5 * ((i * 7919) % 13) as f32).collect();
+        let mut block = hiss.clone();
+        agc.process(&mut block);
+        assert_eq!(block, hiss);
+        assert_eq!(agc.gain(), 1.0);
+    }
+}
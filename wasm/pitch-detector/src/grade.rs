@@ -0,0 +1,292 @@
+use crate::detect_pitch;
+use crate::meter::note_proximity;
+use crate::note::{cents_between, ReferencePitch};
+
+/// A sung or played note within this many cents of its target counts as hit
+const HIT_TOLERANCE_CENTS: f32 = 50.0;
+
+/// Frames further than this from the running note start a new note
+const SEGMENT_BREAK_CENTS: f32 = 50.0;
+
+/// Timing slack, in seconds: unvoiced gaps up to this long do not end a note,
+/// and notes shorter than this are dropped as glitches
+const TIMING_SLACK_SECONDS: f32 = 0.1;
+
+/// How a performance compares with a target melody
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradeReport {
+    /// Whether each target note was played, in target order
+    pub hits: Vec<bool>,
+    /// Deviation of each played note from its target in cents (positive is
+    /// sharp), NaN for missed notes
+    pub cents_errors: Vec<f32>,
+    /// Mean absolute deviation over the hit notes, NaN if none was hit
+    pub average_cents_error: f32,
+    /// Overall grade from 0.0 to 1.0: each target scores its
+    /// [`note_proximity`](crate::note_proximity) within the hit tolerance if
+    /// hit and 0.0 if missed, averaged over the targets
+    pub score: f32,
+}
+
+/// Grade a pitch track against the expected sequence of target frequencies
+/// The voiced track (frames `hop_size` samples apart, as from
+/// `detect_pitch_track`) is split into notes, which are then aligned with the
+/// targets in order so that as many targets as possible are hit; extra or
+/// missing notes only cost the targets they displace. Timing is not graded,
+/// only order and intonation
+pub fn grade_against_targets(
+    track: &[f32],
+    targets: &[f32],
+    hop_size: usize,
+    sample_rate: f32,
+) -> GradeReport {
+    let played: Vec<f32> = segment_notes(track, slack_frames(hop_size, sample_rate))
+        .iter()
+        .map(|note| note.frequency)
+        .collect();
+    let matches = align(&played, targets);
+
+    let cents_errors: Vec<f32> = matches
+        .iter()
+        .zip(targets)
+        .map(|(played_note, &target)| match played_note {
+            Some(frequency) => cents_between(*frequency, target),
+            None => f32::NAN,
+        })
+        .collect();
+    let hits: Vec<bool> = cents_errors.iter().map(|c| !c.is_nan()).collect();
+    let hit_count = hits.iter().filter(|&&hit| hit).count();
+    let average_cents_error = if hit_count == 0 {
+        f32::NAN
+    } else {
+        cents_errors
+            .iter()
+            .filter(|c| !c.is_nan())
+            .map(|c| c.abs())
+            .sum::<f32>()
+            / hit_count as f32
+    };
+    let score = if targets.is_empty() {
+        0.0
+    } else {
+        matches
+            .iter()
+            .zip(targets)
+            .map(|(played_note, &target)| match played_note {
+                Some(frequency) => note_proximity(*frequency, target, HIT_TOLERANCE_CENTS),
+                None => 0.0,
+            })
+            .sum::<f32>()
+            / targets.len() as f32
+    };
+
+    GradeReport {
+        hits,
+        cents_errors,
+        average_cents_error,
+        score,
+    }
+}
+
+/// One note found by [`segment_notes`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct NoteSegment {
+    /// Median frequency of the note's frames
+    pub frequency: f32,
+    /// The note began after an unvoiced gap (or the start of the track)
+    /// rather than by a pitch change
+    pub after_gap: bool,
+}
+
+/// Intonation profile of a scale run, one played note per frame
+/// Each frame is detected and compared with the 12-TET pitch of the MIDI
+/// note expected there, with A4 at `a4`, so a synth or a freshly fretted
+/// neck can be checked note by note across its range. Frames and notes are
+/// paired in order; extra entries on either side are ignored.
+/// Returns each note's deviation in cents (positive is sharp), NaN where
+/// no pitch was detected
+pub fn scale_intonation(
+    frames: &[&[f32]],
+    sample_rate: f32,
+    expected_notes: &[f32],
+    a4: f32,
+) -> Vec<f32> {
+    let reference = ReferencePitch::a4(a4);
+    frames
+        .iter()
+        .zip(expected_notes)
+        .map(|(frame, &note)| {
+            cents_between(
+                detect_pitch(frame, sample_rate),
+                reference.midi_to_frequency(note),
+            )
+        })
+        .collect()
+}
+
+/// Unvoiced frames tolerated inside a note for frames `hop_size` apart
+pub(crate) fn slack_frames(hop_size: usize, sample_rate: f32) -> usize {
+    if hop_size > 0 {
+        (TIMING_SLACK_SECONDS * sample_rate / hop_size as f32).round() as usize
+    } else {
+        0
+    }
+}
+
+/// Split a track into notes
+/// A note continues while frames stay within `SEGMENT_BREAK_CENTS` of its
+/// first frame and unvoiced gaps last at most `slack_frames`; notes with no
+/// more voiced frames than that are dropped
+pub(crate) fn segment_notes(track: &[f32], slack_frames: usize) -> Vec<NoteSegment> {
+    let mut notes = Vec::new();
+    let mut current: Vec<f32> = Vec::new();
+    let mut gap = 0;
+    let mut after_gap = true;
+    let mut finish = |current: &mut Vec<f32>, after_gap: bool| {
+        if current.len() > slack_frames {
+            current.sort_by(|a, b| a.total_cmp(b));
+            notes.push(NoteSegment {
+                frequency: current[current.len() / 2],
+                after_gap,
+            });
+        }
+        current.clear();
+    };
+
+    for &frequency in track {
+        if frequency <= 0.0 {
+            gap += 1;
+            if gap > slack_frames {
+                if !current.is_empty() {
+                    finish(&mut current, after_gap);
+                }
+                after_gap = true;
+            }
+            continue;
+        }
+        gap = 0;
+        if let Some(&first) = current.first() {
+            if cents_between(frequency, first).abs() > SEGMENT_BREAK_CENTS {
+                finish(&mut current, after_gap);
+                after_gap = false;
+            }
+        }
+        current.push(frequency);
+    }
+    finish(&mut current, after_gap);
+    notes
+}
+
+/// In-order alignment of played notes to targets maximizing the hits
+/// Returns the played frequency matched to each target, None where missed
+fn align(played: &[f32], targets: &[f32]) -> Vec<Option<f32>> {
+    let is_hit =
+        |p: usize, t: usize| cents_between(played[p], targets[t]).abs() <= HIT_TOLERANCE_CENTS;
+    // best[p][t]: most hits aligning played[p..] with targets[t..]
+    let mut best = vec![vec![0usize; targets.len() + 1]; played.len() + 1];
+    for p in (0..played.len()).rev() {
+        for t in (0..targets.len()).rev() {
+            let matched = if is_hit(p, t) {
+                1 + best[p + 1][t + 1]
+            } else {
+                0
+            };
+            best[p][t] = matched.max(best[p + 1][t]).max(best[p][t + 1]);
+        }
+    }
+
+    let mut matches = vec![None; targets.len()];
+    let (mut p, mut t) = (0, 0);
+    while p < played.len() && t < targets.len() {
+        if is_hit(p, t) && best[p][t] == 1 + best[p + 1][t + 1] {
+            matches[t] = Some(played[p]);
+            p += 1;
+            t += 1;
+        } else if best[p + 1][t] == best[p][t] {
+            p += 1;
+        } else {
+            t += 1;
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect_pitch_track;
+    use crate::testutil::{generate_tone, Waveform};
+
+    #[test]
+    fn test_slightly_sharp_two_note_performance() {
+        let sample_rate = 44100.0;
+        let sharp = 2f32.powf(8.0 / 1200.0);
+        let mut samples = generate_tone(440.0 * sharp, sample_rate, 22050, Waveform::Saw);
+        samples.extend(vec![0.0; 2048]);
+        samples.extend(generate_tone(
+            493.88 * sharp,
+            sample_rate,
+            22050,
+            Waveform::Saw,
+        ));
+        let track = detect_pitch_track(&samples, sample_rate, 2048, 512);
+
+        let report = grade_against_targets(&track, &[440.0, 493.88], 512, sample_rate);
+        assert_eq!(report.hits, vec![true, true]);
+        assert!(
+            (report.average_cents_error - 8.0).abs() < 2.0,
+            "{:?}",
+            report
+        );
+        assert!(report.cents_errors.iter().all(|&c| c > 0.0));
+        assert!(report.score > 0.8 && report.score < 1.0, "{:?}", report);
+    }
+
+    #[test]
+    fn test_missed_and_extra_notes() {
+        let a4 = [440.0; 20];
+        let c5 = [523.25; 20];
+        let glitch = [700.0; 2];
+        let track: Vec<f32> = [&a4[..], &glitch, &c5].concat();
+        // Frames 10 ms apart: the 2-frame glitch is below the timing slack
+        let report = grade_against_targets(&track, &[440.0, 493.88, 523.25], 441, 44100.0);
+        assert_eq!(report.hits, vec![true, false, true]);
+        assert!(report.cents_errors[1].is_nan());
+        assert!((report.score - 2.0 / 3.0).abs() < 1e-3, "{:?}", report);
+
+        let silent = grade_against_targets(&[-1.0; 50], &[440.0], 441, 44100.0);
+        assert_eq!(silent.hits, vec![false]);
+        assert!(silent.average_cents_error.is_nan());
+        assert_eq!(silent.score, 0.0);
+    }
+
+    #[test]
+    fn test_scale_run_shows_off_note() {
+        let sample_rate = 44100.0;
+        let reference = ReferencePitch::a4(442.0);
+        // C major from C4, with the F played 35 cents flat
+        let notes = [60.0, 62.0, 64.0, 65.0, 67.0, 69.0, 71.0, 72.0];
+        let frames: Vec<Vec<f32>> = notes
+            .iter()
+            .map(|&note| {
+                let detune = if note == 65.0 { -0.35 } else { 0.0 };
+                let frequency = reference.midi_to_frequency(note + detune);
+                generate_tone(frequency, sample_rate, 2048, Waveform::Saw)
+            })
+            .collect();
+        let frames: Vec<&[f32]> = frames.iter().map(|f| f.as_slice()).collect();
+
+        let profile = scale_intonation(&frames, sample_rate, &notes, 442.0);
+        assert_eq!(profile.len(), notes.len());
+        for (i, cents) in profile.iter().enumerate() {
+            if i == 3 {
+                assert!((cents + 35.0).abs() < 3.0, "{:?}", profile);
+            } else {
+                assert!(cents.abs() < 3.0, "{:?}", profile);
+            }
+        }
+
+        let silent: [&[f32]; 1] = [&[0.0; 2048]];
+        assert!(scale_intonation(&silent, sample_rate, &[60.0], 442.0)[0].is_nan());
+    }
+}
@@ -0,0 +1,382 @@
+use wasm_bindgen::prelude::*;
+
+use crate::detect_pitch;
+use crate::detector::PitchDetector;
+use crate::note::{cents_between, parse_note_name, ParseError, ReferencePitch};
+
+/// Largest deviation, in cents, still counted as in tune for locking
+const LOCK_TOLERANCE_CENTS: f32 = 5.0;
+
+/// Consecutive in-tune frames on the same string needed to lock
+const LOCK_FRAMES: u32 = 4;
+
+/// Standard guitar tuning (E2 A2 D3 G3 B3 E4) at A4 = 440 Hz, low string first
+pub const STANDARD_TUNING: [f32; 6] = [82.41, 110.0, 146.83, 196.0, 246.94, 329.63];
+
+/// Target frequencies for a tuning given as note names, with A4 at `a4`
+/// Names are in scientific pitch notation with sharps or flats, e.g.
+/// `["D2", "A2", "D3", "G3", "B3", "E4"]` for drop D or `["C#2", "Ab2"]`,
+/// in whatever order the strings should be listed.
+/// Returns the frequencies in the same order, or the first name that fails
+/// to parse
+pub fn tuning_from_note_names(names: &[&str], a4: f32) -> Result<Vec<f32>, ParseError> {
+    let reference = ReferencePitch::a4(a4);
+    names
+        .iter()
+        .map(|name| Ok(reference.midi_to_frequency(parse_note_name(name)? as f32)))
+        .collect()
+}
+
+/// A recording matched to the string it was meant to tune
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StringMatch {
+    /// Index into the tuning
+    pub string_index: usize,
+    /// Index into the recordings passed to [`analyze_guitar`]
+    pub recording_index: usize,
+    pub detected_frequency: f32,
+    pub target_frequency: f32,
+    /// Deviation from the target; positive means sharp
+    pub cents: f32,
+}
+
+/// Detect each recorded pluck and match it to a string of `tuning`
+/// Recordings may be in any order; each string is assigned at most one
+/// recording, closest pairs first. Unvoiced recordings are left unmatched.
+/// Returns the matches ordered by string
+pub fn analyze_guitar(frames: &[&[f32]], sample_rate: f32, tuning: &[f32]) -> Vec<StringMatch> {
+    let detected: Vec<f32> = frames
+        .iter()
+        .map(|frame| detect_pitch(frame, sample_rate))
+        .collect();
+
+    let mut pairs: Vec<StringMatch> = detected
+        .iter()
+        .enumerate()
+        .filter(|(_, &frequency)| frequency > 0.0)
+        .flat_map(|(recording_index, &frequency)| {
+            tuning
+                .iter()
+                .enumerate()
+                .map(move |(string_index, &target)| StringMatch {
+                    string_index,
+                    recording_index,
+                    detected_frequency: frequency,
+                    target_frequency: target,
+                    cents: cents_between(frequency, target),
+                })
+        })
+        .collect();
+    pairs.sort_by(|a, b| a.cents.abs().total_cmp(&b.cents.abs()));
+
+    let mut string_taken = vec![false; tuning.len()];
+    let mut recording_taken = vec![false; frames.len()];
+    let mut matches = Vec::new();
+    for pair in pairs {
+        if string_taken[pair.string_index] || recording_taken[pair.recording_index] {
+            continue;
+        }
+        string_taken[pair.string_index] = true;
+        recording_taken[pair.recording_index] = true;
+        matches.push(pair);
+    }
+
+    matches.sort_by_key(|m| m.string_index);
+    matches
+}
+
+/// A fret position on one string
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FretMatch {
+    /// Nearest 12-TET fret, 0 for the open string
+    pub fret: u8,
+    /// Deviation from that fret; positive means sharp
+    pub cents: f32,
+}
+
+/// Nearest fret being played on a string tuned to `open_string_hz`
+/// Pitches below the open string report fret 0 with negative cents.
+/// Non-positive inputs report fret 0 with NaN cents
+#[wasm_bindgen]
+pub fn nearest_fret(detected_hz: f32, open_string_hz: f32) -> FretMatch {
+    let semitones = cents_between(detected_hz, open_string_hz) / 100.0;
+    if semitones.is_nan() {
+        return FretMatch {
+            fret: 0,
+            cents: f32::NAN,
+        };
+    }
+    let fret = semitones.round().clamp(0.0, u8::MAX as f32);
+    FretMatch {
+        fret: fret as u8,
+        cents: (semitones - fret) * 100.0,
+    }
+}
+
+/// Where `frequency` sounds on each string of a fretboard
+/// One entry per string of `string_tunings`, in the same order: the nearest
+/// fret with its deviation (fret plus cents / 100 is the fractional fret),
+/// or None when the pitch is more than half a semitone below the open
+/// string or above the last of `frets` frets
+pub fn frequency_to_fretboard_position(
+    frequency: f32,
+    string_tunings: &[f32],
+    frets: u8,
+) -> Vec<Option<FretMatch>> {
+    string_tunings
+        .iter()
+        .map(|&open_string_hz| {
+            let position = nearest_fret(frequency, open_string_hz);
+            let on_board =
+                !position.cents.is_nan() && position.fret <= frets && position.cents.abs() <= 50.0;
+            on_board.then_some(position)
+        })
+        .collect()
+}
+
+/// What a [`GuitarTuner`] makes of one frame
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GuitarTunerState {
+    /// Detected frequency in Hz, or -1.0 if no pitch detected
+    pub frequency: f32,
+    /// Index into the tuning of the nearest string, None if unvoiced
+    pub string_index: Option<usize>,
+    /// Deviation from that string; positive means sharp, NaN if unvoiced
+    pub cents: f32,
+    /// The string has been held in tune for several consecutive frames
+    pub locked: bool,
+}
+
+/// Stateful guitar tuner: detection, string snapping and lock tracking
+/// Each frame is snapped to the nearest string of the tuning; holding one
+/// string within a few cents for several frames in a row locks it, and any
+/// unvoiced, out-of-tune or different-string frame releases the lock
+#[wasm_bindgen]
+pub struct GuitarTuner {
+    detector: PitchDetector,
+    targets: Vec<f32>,
+    held_string: Option<usize>,
+    held_frames: u32,
+}
+
+#[wasm_bindgen]
+impl GuitarTuner {
+    /// `tuning` is given in Hz at A4 = 440 (e.g. [`STANDARD_TUNING`]) and is
+    /// rescaled to the `a4` reference
+    #[wasm_bindgen(constructor)]
+    pub fn new(tuning: Vec<f32>, a4: f32) -> Self {
+        let mut detector = PitchDetector::new();
+        detector.set_continuity(true);
+        Self {
+            detector,
+            targets: tuning.iter().map(|&hz| hz * a4 / 440.0).collect(),
+            held_string: None,
+            held_frames: 0,
+        }
+    }
+
+    /// Target frequency of each string at the configured reference, in Hz
+    pub fn targets(&self) -> Vec<f32> {
+        self.targets.clone()
+    }
+
+    /// Analyze one frame
+    pub fn process(&mut self, samples: &[f32], sample_rate: f32) -> GuitarTunerState {
+        let frequency = self.detector.process(samples, sample_rate);
+        let nearest = self
+            .targets
+            .iter()
+            .map(|&target| cents_between(frequency, target))
+            .enumerate()
+            .filter(|(_, cents)| !cents.is_nan())
+            .min_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()));
+
+        let (string_index, cents) = match nearest {
+            Some((index, cents)) => (Some(index), cents),
+            None => (None, f32::NAN),
+        };
+
+        if string_index.is_some() && cents.abs() <= LOCK_TOLERANCE_CENTS {
+            if self.held_string == string_index {
+                self.held_frames += 1;
+            } else {
+                self.held_string = string_index;
+                self.held_frames = 1;
+            }
+        } else {
+            self.held_string = None;
+            self.held_frames = 0;
+        }
+
+        GuitarTunerState {
+            frequency,
+            string_index,
+            cents,
+            locked: self.held_frames >= LOCK_FRAMES,
+        }
+    }
+
+    /// Forget the pitch history and any lock in progress
+    pub fn reset(&mut self) {
+        self.detector.reset();
+        self.held_string = None;
+        self.held_frames = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+
+    #[test]
+    fn test_scrambled_plucks_assigned_to_strings() {
+        let sample_rate = 44100.0;
+        // Recording order and per-string detuning, in cents
+        let order = [3usize, 0, 5, 1, 4, 2];
+        let detune = [-12.0f32, 5.0, 20.0, -8.0, 0.0, 15.0];
+
+        let recordings: Vec<Vec<f32>> = order
+            .iter()
+            .map(|&string| {
+                let frequency = STANDARD_TUNING[string] * 2f32.powf(detune[string] / 1200.0);
+                generate_tone(frequency, sample_rate, 4096, Waveform::Saw)
+            })
+            .collect();
+        let frames: Vec<&[f32]> = recordings.iter().map(|r| r.as_slice()).collect();
+
+        let matches = analyze_guitar(&frames, sample_rate, &STANDARD_TUNING);
+        assert_eq!(matches.len(), 6);
+        for (string, m) in matches.iter().enumerate() {
+            assert_eq!(m.string_index, string);
+            assert_eq!(order[m.recording_index], string);
+            assert!(
+                (m.cents - detune[string]).abs() < 3.0,
+                "string {}: expected {} cents, got {}",
+                string,
+                detune[string],
+                m.cents
+            );
+        }
+    }
+
+    #[test]
+    fn test_nearest_fret() {
+        let low_e = STANDARD_TUNING[0];
+        // 5th fret of the low E string is A2
+        let fifth = nearest_fret(110.0, low_e);
+        assert_eq!(fifth.fret, 5);
+        assert!(fifth.cents.abs() < 1.0, "{:?}", fifth);
+
+        // 12th fret is the octave; a slightly sharp one
+        let twelfth = nearest_fret(low_e * 2.0 * 2f32.powf(8.0 / 1200.0), low_e);
+        assert_eq!(twelfth.fret, 12);
+        assert!((twelfth.cents - 8.0).abs() < 0.1, "{:?}", twelfth);
+
+        // Below the open string
+        let below = nearest_fret(low_e * 2f32.powf(-30.0 / 1200.0), low_e);
+        assert_eq!(below.fret, 0);
+        assert!((below.cents + 30.0).abs() < 0.1, "{:?}", below);
+
+        assert!(nearest_fret(-1.0, low_e).cents.is_nan());
+    }
+
+    #[test]
+    fn test_e4_across_standard_fretboard() {
+        let e4 = 329.63;
+        let positions = frequency_to_fretboard_position(e4, &STANDARD_TUNING, 22);
+        let frets: Vec<Option<u8>> = positions.iter().map(|p| p.map(|m| m.fret)).collect();
+        // Fret 24 of the low E string is past a 22-fret neck
+        assert_eq!(
+            frets,
+            vec![None, Some(19), Some(14), Some(9), Some(5), Some(0)]
+        );
+        for position in positions.iter().flatten() {
+            assert!(position.cents.abs() < 2.0, "{:?}", position);
+        }
+
+        // Below every open string
+        let low = frequency_to_fretboard_position(60.0, &STANDARD_TUNING, 22);
+        assert!(low.iter().all(Option::is_none));
+        assert!(frequency_to_fretboard_position(-1.0, &STANDARD_TUNING, 22)
+            .iter()
+            .all(Option::is_none));
+    }
+
+    #[test]
+    fn test_guitar_tuner_locks_low_e() {
+        let sample_rate = 44100.0;
+        let mut tuner = GuitarTuner::new(STANDARD_TUNING.to_vec(), 440.0);
+        let frame = |cents: f32| {
+            let frequency = STANDARD_TUNING[0] * 2f32.powf(cents / 1200.0);
+            generate_tone(frequency, sample_rate, 2048, Waveform::Saw)
+        };
+
+        // Tightening the string up from 40 cents flat
+        for cents in [-40.0, -25.0, -12.0] {
+            let state = tuner.process(&frame(cents), sample_rate);
+            assert_eq!(state.string_index, Some(0));
+            assert!((state.cents - cents).abs() < 3.0, "{:?}", state);
+            assert!(!state.locked);
+        }
+
+        // Held in tune: locks only after enough frames
+        let in_tune = frame(1.0);
+        for held in 1..=LOCK_FRAMES + 2 {
+            let state = tuner.process(&in_tune, sample_rate);
+            assert_eq!(state.string_index, Some(0));
+            assert_eq!(state.locked, held >= LOCK_FRAMES, "frame {}", held);
+        }
+
+        // Letting the string ring out releases the lock
+        let state = tuner.process(&[0.0; 2048], sample_rate);
+        assert_eq!(state.string_index, None);
+        assert!(state.cents.is_nan());
+        assert!(!state.locked);
+    }
+
+    #[test]
+    fn test_guitar_tuner_reference_pitch() {
+        let tuner = GuitarTuner::new(STANDARD_TUNING.to_vec(), 432.0);
+        assert!((tuner.targets()[1] - 108.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_unvoiced_recording_unmatched() {
+        let sample_rate = 44100.0;
+        let a = generate_tone(110.0, sample_rate, 4096, Waveform::Saw);
+        let silence = vec![0.0f32; 4096];
+        let matches = analyze_guitar(&[&silence, &a], sample_rate, &STANDARD_TUNING);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].string_index, 1);
+        assert_eq!(matches[0].recording_index, 1);
+    }
+
+    #[test]
+    fn test_tuning_from_note_names() {
+        let standard =
+            tuning_from_note_names(&["E2", "A2", "D3", "G3", "B3", "E4"], 440.0).unwrap();
+        for (parsed, expected) in standard.iter().zip(STANDARD_TUNING) {
+            assert!(
+                (parsed - expected).abs() < 0.01,
+                "{} vs {}",
+                parsed,
+                expected
+            );
+        }
+
+        // Open G against a lower reference
+        let open_g = tuning_from_note_names(&["D2", "G2", "D3", "G3", "B3", "D4"], 432.0).unwrap();
+        assert!((open_g[1] - 96.22).abs() < 0.01, "{}", open_g[1]);
+        let flats = tuning_from_note_names(&["Eb2", "Ab2"], 440.0).unwrap();
+        assert!((flats[0] - 77.78).abs() < 0.01);
+
+        assert_eq!(
+            tuning_from_note_names(&["E2", "A2", "X3"], 440.0),
+            Err(ParseError::InvalidLetter("X3".to_string()))
+        );
+        assert!(tuning_from_note_names(&[], 440.0).unwrap().is_empty());
+    }
+}
@@ -1,8 +1,134 @@
 use wasm_bindgen::prelude::*;
 
+mod accuracy;
+mod advisory;
+mod algorithm;
+mod analytic;
+mod bands;
+mod capabilities;
+mod change;
+mod channel;
+mod codec;
+mod config;
+mod consensus;
+mod detector;
+mod duophonic;
+mod envelope;
+#[cfg(feature = "fft")]
+mod fft;
+mod filter;
+mod gain;
+mod grade;
+mod guitar;
+mod meter;
+mod midi;
+mod note;
+mod pool;
+mod pyin;
+mod ring;
+mod session;
+mod signal;
+mod smoothing;
+mod snr;
+#[cfg(feature = "fft")]
+mod spectral;
+#[cfg(any(test, feature = "testutil"))]
+pub mod testutil;
+mod track;
+mod yin;
+
+pub use accuracy::{evaluate_accuracy, AccuracyReport};
+pub use advisory::{detection_advisory, Advisory};
+#[cfg(feature = "fft")]
+pub use algorithm::SpectralPeak;
+pub use algorithm::{Autocorrelation, PitchAlgorithm, Pyin, Yin};
+pub use analytic::detect_pitch_analytic;
+pub use bands::detect_pitch_bands;
+pub use capabilities::{capabilities, detector_info, Capabilities, DetectorInfo};
+pub use change::ChangeDetector;
+pub use channel::{
+    detect_pitch_audiobuffer_channel, detect_pitch_best_channel, detect_pitch_channel,
+    ChannelResult,
+};
+pub use codec::{detect_pitch_alaw, detect_pitch_mulaw};
+pub use config::{DetectorConfig, RangeBehavior};
+pub use consensus::{
+    detect_pitch_autocorrelation, detect_pitch_consensus, detect_pitch_consensus_with,
+    detect_pitch_dual, ConsensusResult, DualResult,
+};
+#[cfg(feature = "debug-callback")]
+pub use detector::DetectionDiagnostics;
+pub use detector::{HoldPolicy, PitchDetector};
+pub use duophonic::detect_duophonic;
+pub use envelope::EnvelopeFollower;
+pub use filter::{detect_pitch_denotched, detect_pitch_enhanced, notch_filter};
+pub use gain::AutoGain;
+pub use grade::{grade_against_targets, scale_intonation, GradeReport};
+pub use guitar::{
+    analyze_guitar, frequency_to_fretboard_position, nearest_fret, tuning_from_note_names,
+    FretMatch, GuitarTuner, GuitarTunerState, StringMatch, STANDARD_TUNING,
+};
+pub use meter::{meter_position, meter_position_with, note_proximity, MeterConfig};
+pub use midi::{MidiEvent, MonoTracker};
+pub use note::{
+    average_detuning, beat_frequency, cents_between, cents_to_ratio, correction_ratio,
+    frequency_to_edo_degree, frequency_to_note, frequency_to_note_with, nearest_scale_degree,
+    note_context, railsback_stretch, ratio_to_cents, snap_to_note, suggest_a4, wavelength_in_air,
+    wavelength_meters, EdoMatch, NamingConfig, NoteContext, NoteInfo, NoteNaming, ParseError,
+    ReferencePitch, ScaleMatch, Temperament,
+};
+pub use pool::{detect_pitch_pooled, ScratchPool};
+pub use pyin::{
+    detect_pitch_candidates, detect_pitch_candidates_into, detect_pitch_pyin, weighted_pitch,
+    PitchCandidate,
+};
+pub use ring::RingDetector;
+pub use session::{
+    detect_pitch_named, Algorithm, DetectionSession, DetectionSessionBuilder, InstrumentPreset,
+    InstrumentRegistry, RegistryError,
+};
+pub use smoothing::{
+    fuse_estimates, interpolate_pitch, robust_mean_frequency, robust_mean_frequency_with,
+    EmaSmoother, SmoothSpace,
+};
+pub use snr::estimate_snr_db;
+#[cfg(feature = "fft")]
+pub use spectral::{
+    chroma_vector, detect_pitch_from_stft, detect_pitch_instantaneous, detect_pitch_sinusoid,
+    detect_pitch_spectral_peak, is_instrument_present, spectral_centroid, spectral_peak_prominence,
+};
+#[cfg(feature = "serde")]
+pub use track::analyze_full;
+pub use track::{
+    count_distinct_notes, detect_pitch_segments, detect_pitch_track, detect_pitch_track_into,
+    detect_pitch_track_overlap, detect_pitch_track_timed, detect_pitch_track_with, detect_relative,
+    dominant_note, drift_analysis, pitch_histogram, pitch_stability, pitch_track_to_csv,
+    session_report, DriftInfo, NoteSummary, RelativeResult, SessionReport,
+};
+pub use yin::{
+    cmndf_minima, detect_period_unbounded, detect_pitch_capped, detect_pitch_circular,
+    detect_pitch_fast_reject, detect_pitch_full, detect_pitch_full_lag_range, detect_pitch_low,
+    detect_pitch_prefer_fundamental, detect_pitch_with_lag_fraction, difference_min_depth,
+    effective_range, frequency_resolution_hz, normalized_difference, octave_confusion_threshold,
+    threshold_sweep, PitchResult,
+};
+
 const MIN_FREQUENCY: f32 = 60.0;
 const MAX_FREQUENCY: f32 = 2000.0;
 const DEFAULT_THRESHOLD: f32 = 0.1;
+const RMS_THRESHOLD: f32 = 0.01;
+
+/// A frame is decaying when its second half has at most this fraction of
+/// the first half's RMS
+const DECAY_RATIO: f32 = 0.9;
+
+/// Decaying frames quieter than this RMS are rejected by
+/// `detect_pitch_decay_aware`
+const DECAY_FLOOR_RMS: f32 = 0.05;
+
+/// Values `process_worklet` writes: frequency, clarity, MIDI note, cents,
+/// voiced
+const WORKLET_RESULT_LEN: usize = 5;
 
 #[wasm_bindgen]
 pub fn init_panic_hook() {
@@ -11,6 +137,9 @@ pub fn init_panic_hook() {
 }
 
 /// YIN pitch detection algorithm
+/// Like every free function in this crate it is pure and reentrant: there is
+/// no global or thread-local state, so native callers may run it from any
+/// number of threads at once and always get the same result for the same input
 /// Returns the detected frequency in Hz, or -1.0 if no pitch detected
 #[wasm_bindgen]
 pub fn detect_pitch(samples: &[f32], sample_rate: f32) -> f32 {
@@ -20,87 +149,98 @@ pub fn detect_pitch(samples: &[f32], sample_rate: f32) -> f32 {
 /// YIN pitch detection with custom threshold
 #[wasm_bindgen]
 pub fn detect_pitch_with_threshold(samples: &[f32], sample_rate: f32, threshold: f32) -> f32 {
-    let buffer_size = samples.len();
-    if buffer_size < 2 {
-        return -1.0;
-    }
+    detect_pitch_cfg(
+        samples,
+        sample_rate,
+        &DetectorConfig::default().with_threshold(threshold),
+    )
+}
 
-    let half_buffer_size = buffer_size / 2;
+/// YIN pitch detection with every parameter taken from `config`
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_cfg(samples: &[f32], sample_rate: f32, config: &DetectorConfig) -> f32 {
+    yin::detect_full_source(samples, sample_rate, config).frequency
+}
 
-    // Check if signal has enough energy
-    let rms = calculate_rms(samples);
-    if rms < 0.01 {
+/// YIN pitch detection that also requires a minimum clarity
+/// Clarity here is 1.0 minus the CMNDF at the chosen period, read off the
+/// same analysis that found the pitch, so no second pass is needed.
+/// Returns -1.0 when no pitch is detected or the clarity is below
+/// `min_clarity`
+#[wasm_bindgen]
+pub fn detect_pitch_min_clarity(samples: &[f32], sample_rate: f32, min_clarity: f32) -> f32 {
+    let result = yin::detect_full_source(samples, sample_rate, &DetectorConfig::default());
+    if result.frequency < 0.0 || 1.0 - result.cmndf < min_clarity {
         return -1.0;
     }
+    result.frequency
+}
 
-    // Step 1: Difference function
-    let mut difference = vec![0.0f32; half_buffer_size];
-    for tau in 0..half_buffer_size {
-        let mut sum = 0.0f32;
-        for i in 0..half_buffer_size {
-            let delta = samples[i] - samples[i + tau];
-            sum += delta * delta;
-        }
-        difference[tau] = sum;
+/// YIN pitch detection that distrusts the tail of a decaying note
+/// Plucked and struck notes become weak and inharmonic as they die away. A
+/// frame whose second half is noticeably quieter than its first is taken to
+/// be decaying, and while decaying it must stay above a higher level floor
+/// than the usual silence gate. Sustained notes, even quiet ones, and the
+/// loud early part of a decay are detected as usual.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_decay_aware(samples: &[f32], sample_rate: f32) -> f32 {
+    let (first, second) = samples.split_at(samples.len() / 2);
+    let decaying = calculate_rms(second) <= DECAY_RATIO * calculate_rms(first);
+    if decaying && calculate_rms(samples) < DECAY_FLOOR_RMS {
+        return -1.0;
     }
+    detect_pitch(samples, sample_rate)
+}
 
-    // Step 2: Cumulative mean normalized difference function (CMNDF)
-    let mut cmndf = vec![0.0f32; half_buffer_size];
-    cmndf[0] = 1.0;
-    let mut running_sum = 0.0f32;
-
-    for tau in 1..half_buffer_size {
-        running_sum += difference[tau];
-        if running_sum > 0.0 {
-            cmndf[tau] = difference[tau] * (tau as f32) / running_sum;
-        } else {
-            cmndf[tau] = 1.0;
-        }
+/// Detected pitch as a display-ready label, e.g. "A4 +3.2¢ (443.2 Hz)"
+/// Cents are relative to the nearest note with A4 at `a4` Hz.
+/// Returns "—" if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_labeled(samples: &[f32], sample_rate: f32, a4: f32) -> String {
+    let frequency = detect_pitch(samples, sample_rate);
+    if frequency <= 0.0 {
+        return "—".to_string();
     }
-
-    // Step 3: Absolute threshold - find first tau where CMNDF < threshold
-    let mut tau_estimate: Option<usize> = None;
-    for tau in 2..half_buffer_size {
-        if cmndf[tau] < threshold {
-            // Find the local minimum
-            let mut min_tau = tau;
-            while min_tau + 1 < half_buffer_size && cmndf[min_tau + 1] < cmndf[min_tau] {
-                min_tau += 1;
-            }
-            tau_estimate = Some(min_tau);
-            break;
-        }
+    match frequency_to_note(frequency, ReferencePitch::a4(a4)) {
+        // Rounding first keeps a hair-flat reading from printing as "-0.0"
+        Some(note) => format!(
+            "{} {:+.1}¢ ({:.1} Hz)",
+            note.note_name,
+            (note.cents * 10.0).round() / 10.0 + 0.0,
+            frequency
+        ),
+        None => "—".to_string(),
     }
+}
 
-    let tau = match tau_estimate {
-        Some(t) => t,
-        None => return -1.0,
-    };
-
-    // Step 4: Parabolic interpolation for better precision
-    let better_tau = if tau > 0 && tau < half_buffer_size - 1 {
-        let s0 = cmndf[tau - 1];
-        let s1 = cmndf[tau];
-        let s2 = cmndf[tau + 1];
-        let denominator = 2.0 * s1 - s2 - s0;
-        if denominator.abs() > f32::EPSILON {
-            (tau as f32) + (s2 - s0) / (2.0 * denominator)
-        } else {
-            tau as f32
-        }
-    } else {
-        tau as f32
-    };
-
-    // Calculate frequency
-    let frequency = sample_rate / better_tau;
-
-    // Validate frequency range
-    if !(MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
-        return -1.0;
+/// Everything an AudioWorklet tuner needs from one buffer, written into a
+/// caller-owned array so nothing is marshalled across the boundary
+/// `out` receives `[frequency, clarity, midi_note, cents, voiced]`:
+/// frequency in Hz (-1.0 if unvoiced), YIN clarity from 0.0 to 1.0, the
+/// nearest MIDI note at `a4` Hz (-1.0 if unvoiced), the deviation from it in
+/// cents (NaN if unvoiced) and 1.0 if voiced, else 0.0. Clarity is reported
+/// even for unvoiced frames. An `out` shorter than five values is left
+/// untouched; extra values are not written
+#[wasm_bindgen]
+pub fn process_worklet(samples: &[f32], sample_rate: f32, a4: f32, out: &mut [f32]) {
+    if out.len() < WORKLET_RESULT_LEN {
+        return;
     }
-
-    frequency
+    let result = detect_pitch_full(samples, sample_rate, DEFAULT_THRESHOLD);
+    let clarity = (1.0 - result.cmndf).clamp(0.0, 1.0);
+    let values = match frequency_to_note(result.frequency, ReferencePitch::a4(a4)) {
+        Some(note) => [
+            result.frequency,
+            clarity,
+            note.midi_note as f32,
+            note.cents,
+            1.0,
+        ],
+        None => [-1.0, clarity, -1.0, f32::NAN, 0.0],
+    };
+    out[..WORKLET_RESULT_LEN].copy_from_slice(&values);
 }
 
 /// Calculate RMS (Root Mean Square) of the signal
@@ -114,10 +254,66 @@ pub fn calculate_rms(samples: &[f32]) -> f32 {
     (sum / samples.len() as f32).sqrt()
 }
 
+/// Approximate RMS from every `stride`-th sample
+/// Costs 1/stride of `calculate_rms`, good enough as a cheap silence gate
+/// before full detection; the estimate degrades once the stride approaches
+/// the period of the signal. A stride of 0 is treated as 1
+#[wasm_bindgen]
+pub fn calculate_rms_fast(samples: &[f32], stride: usize) -> f32 {
+    let stride = stride.max(1);
+    let (sum, count) = samples
+        .iter()
+        .step_by(stride)
+        .fold((0.0f32, 0usize), |(sum, count), &x| {
+            (sum + x * x, count + 1)
+        });
+    if count == 0 {
+        return 0.0;
+    }
+    (sum / count as f32).sqrt()
+}
+
 /// Calculate the clarity/confidence of the pitch detection
 /// Returns a value between 0.0 (low confidence) and 1.0 (high confidence)
 #[wasm_bindgen]
 pub fn get_pitch_clarity(samples: &[f32], sample_rate: f32) -> f32 {
+    clarity_with(samples, sample_rate, |_, _| {})
+}
+
+/// `get_pitch_clarity` that also leaves the autocorrelation in `scratch`
+/// `scratch` must hold at least `samples.len() / 2` values; on return
+/// `scratch[tau]` is the normalized correlation at each searched lag and 0.0
+/// elsewhere. A shorter scratch falls back to `get_pitch_clarity`
+#[wasm_bindgen]
+pub fn get_pitch_clarity_into(samples: &[f32], sample_rate: f32, scratch: &mut [f32]) -> f32 {
+    let half_buffer_size = samples.len() / 2;
+    if scratch.len() < half_buffer_size {
+        return get_pitch_clarity(samples, sample_rate);
+    }
+    scratch.fill(0.0);
+    clarity_with(samples, sample_rate, |tau, correlation| {
+        scratch[tau] = correlation;
+    })
+}
+
+/// [`get_pitch_clarity`] of many equal-length frames in one call
+/// `frames` holds the frames back to back, `frame_size` samples each; a
+/// trailing partial frame is dropped. The per-frame computation allocates
+/// nothing, so the whole batch costs one output allocation.
+/// Returns one clarity per frame, or an empty vector if `frame_size` is zero
+#[wasm_bindgen]
+pub fn clarity_batch(frames: &[f32], frame_size: usize, sample_rate: f32) -> Vec<f32> {
+    if frame_size == 0 {
+        return Vec::new();
+    }
+    frames
+        .chunks_exact(frame_size)
+        .map(|frame| get_pitch_clarity(frame, sample_rate))
+        .collect()
+}
+
+/// Clarity computation, reporting the normalized correlation of each searched lag
+fn clarity_with(samples: &[f32], sample_rate: f32, mut on_lag: impl FnMut(usize, f32)) -> f32 {
     let buffer_size = samples.len();
     if buffer_size < 2 {
         return 0.0;
@@ -147,6 +343,7 @@ pub fn get_pitch_clarity(samples: &[f32], sample_rate: f32) -> f32 {
         for i in 0..(half_buffer_size - tau) {
             correlation += samples[i] * samples[i + tau];
         }
+        on_lag(tau, correlation / zero_lag_correlation);
         if correlation > max_correlation {
             max_correlation = correlation;
         }
@@ -159,12 +356,10 @@ pub fn get_pitch_clarity(samples: &[f32], sample_rate: f32) -> f32 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::f32::consts::PI;
+    use crate::testutil::{generate_tone, Waveform};
 
     fn generate_sine_wave(frequency: f32, sample_rate: f32, duration_samples: usize) -> Vec<f32> {
-        (0..duration_samples)
-            .map(|i| (2.0 * PI * frequency * (i as f32) / sample_rate).sin())
-            .collect()
+        generate_tone(frequency, sample_rate, duration_samples, Waveform::Sine)
     }
 
     #[test]
@@ -198,10 +393,251 @@ mod tests {
         assert_eq!(detected, -1.0, "Expected -1.0 for silence");
     }
 
+    #[test]
+    fn test_custom_config() {
+        let sample_rate = 44100.0;
+        let quiet: Vec<f32> = generate_sine_wave(150.0, sample_rate, 2048)
+            .iter()
+            .map(|x| 0.02 * x)
+            .collect();
+        assert_eq!(
+            detect_pitch_cfg(&quiet, sample_rate, &DetectorConfig::default()),
+            detect_pitch(&quiet, sample_rate)
+        );
+
+        let config = DetectorConfig::default()
+            .with_threshold(0.05)
+            .with_frequency_range(200.0, 1000.0)
+            .with_rms_threshold(0.001);
+        // 150 Hz is below this range...
+        assert_eq!(detect_pitch_cfg(&quiet, sample_rate, &config), -1.0);
+        // ...while a tone in range passes the lowered RMS gate
+        let tone: Vec<f32> = generate_sine_wave(300.0, sample_rate, 2048)
+            .iter()
+            .map(|x| 0.002 * x)
+            .collect();
+        assert_eq!(detect_pitch(&tone, sample_rate), -1.0);
+        let detected = detect_pitch_cfg(&tone, sample_rate, &config);
+        assert!((detected - 300.0).abs() < 2.0, "got {}", detected);
+    }
+
+    #[test]
+    fn test_range_behavior() {
+        let sample_rate = 44100.0;
+        let just_below = generate_sine_wave(190.0, sample_rate, 2048);
+        let reject = DetectorConfig::default().with_frequency_range(200.0, 1000.0);
+        assert_eq!(detect_pitch_cfg(&just_below, sample_rate, &reject), -1.0);
+
+        let clamp = reject.with_range_behavior(RangeBehavior::Clamp);
+        assert_eq!(detect_pitch_cfg(&just_below, sample_rate, &clamp), 200.0);
+        let just_above = generate_sine_wave(1100.0, sample_rate, 2048);
+        assert_eq!(detect_pitch_cfg(&just_above, sample_rate, &clamp), 1000.0);
+
+        // Clamping never invents a pitch for an unvoiced frame
+        assert_eq!(detect_pitch_cfg(&[0.0; 2048], sample_rate, &clamp), -1.0);
+    }
+
+    #[test]
+    fn test_min_clarity_rejects_noisy_detection() {
+        use crate::testutil::{add_noise, NoiseKind};
+
+        let sample_rate = 44100.0;
+        let clean = generate_sine_wave(440.0, sample_rate, 2048);
+        let mut noisy = clean.clone();
+        add_noise(&mut noisy, 10.0, NoiseKind::White);
+
+        // The noisy tone still passes plain detection...
+        assert!((detect_pitch(&noisy, sample_rate) - 440.0).abs() < 2.0);
+        // ...but not the clarity requirement the clean tone meets
+        assert_eq!(detect_pitch_min_clarity(&noisy, sample_rate, 0.95), -1.0);
+        let detected = detect_pitch_min_clarity(&clean, sample_rate, 0.95);
+        assert!((detected - 440.0).abs() < 2.0, "got {}", detected);
+    }
+
+    #[test]
+    fn test_process_worklet() {
+        let sample_rate = 44100.0;
+        let samples = generate_sine_wave(440.0, sample_rate, 2048);
+        let mut out = [0.0f32; 6];
+        process_worklet(&samples, sample_rate, 440.0, &mut out);
+        let [frequency, clarity, midi, cents, voiced, untouched] = out;
+        assert!((frequency - 440.0).abs() < 1.0, "{:?}", out);
+        assert!(clarity > 0.95, "{:?}", out);
+        assert_eq!(midi, 69.0);
+        assert!(cents.abs() < 4.0, "{:?}", out);
+        assert_eq!((voiced, untouched), (1.0, 0.0));
+
+        process_worklet(&[0.0; 2048], sample_rate, 440.0, &mut out);
+        assert_eq!((out[0], out[2], out[4]), (-1.0, -1.0, 0.0));
+        assert!(out[3].is_nan());
+
+        let mut short = [7.0f32; 4];
+        process_worklet(&samples, sample_rate, 440.0, &mut short);
+        assert_eq!(short, [7.0; 4]);
+    }
+
+    #[test]
+    fn test_clarity_batch() {
+        use crate::testutil::{add_noise_seeded, NoiseKind};
+
+        let sample_rate = 44100.0;
+        let frame_size = 2048;
+        let tones: Vec<f32> = [110.0, 220.0, 440.0, 880.0]
+            .iter()
+            .flat_map(|&f| generate_sine_wave(f, sample_rate, frame_size))
+            .collect();
+        let clarity = clarity_batch(&tones, frame_size, sample_rate);
+        assert_eq!(clarity.len(), 4);
+        assert!(clarity.iter().all(|&c| c > 0.75), "{:?}", clarity);
+        assert_eq!(
+            clarity[2],
+            get_pitch_clarity(&tones[4096..6144], sample_rate)
+        );
+
+        let mut noise = vec![1e-3; frame_size * 4];
+        add_noise_seeded(&mut noise, -60.0, NoiseKind::White, 7);
+        let clarity = clarity_batch(&noise, frame_size, sample_rate);
+        assert!(clarity.iter().all(|&c| c < 0.3), "{:?}", clarity);
+
+        // Trailing partial frame dropped
+        assert_eq!(
+            clarity_batch(&noise[..5000], frame_size, sample_rate).len(),
+            2
+        );
+        assert!(clarity_batch(&noise, 0, sample_rate).is_empty());
+    }
+
+    #[test]
+    fn test_clarity_into_matches() {
+        let sample_rate = 44100.0;
+        let samples = generate_sine_wave(440.0, sample_rate, 2048);
+        let mut scratch = vec![1.0f32; 1024];
+        let clarity = get_pitch_clarity_into(&samples, sample_rate, &mut scratch);
+        assert_eq!(clarity, get_pitch_clarity(&samples, sample_rate));
+        // Peak of the stored correlation is the clarity, near one period
+        let (tau, &peak) = scratch
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+        assert_eq!(peak.clamp(0.0, 1.0), clarity);
+        assert!(
+            (tau as f32 - sample_rate / 440.0).abs() < 1.0,
+            "tau {}",
+            tau
+        );
+        assert_eq!(scratch[0], 0.0);
+
+        // Undersized scratch still gives the right answer
+        assert_eq!(
+            get_pitch_clarity_into(&samples, sample_rate, &mut [0.0; 4]),
+            clarity
+        );
+    }
+
+    #[test]
+    fn test_decay_aware_rejects_quiet_tail() {
+        let sample_rate = 44100.0;
+        // Pluck decaying with a 0.15 s time constant
+        let pluck: Vec<f32> = generate_sine_wave(220.0, sample_rate, sample_rate as usize)
+            .iter()
+            .enumerate()
+            .map(|(i, x)| x * (-(i as f32) / (0.15 * sample_rate)).exp())
+            .collect();
+
+        let early = &pluck[..2048];
+        let detected = detect_pitch_decay_aware(early, sample_rate);
+        assert!((detected - 220.0).abs() < 2.0, "got {}", detected);
+
+        // Around 0.5 s the amplitude is ~0.035: detectable, but untrusted
+        let start = (0.5 * sample_rate) as usize;
+        let late = &pluck[start..start + 2048];
+        assert!(detect_pitch(late, sample_rate) > 0.0);
+        assert_eq!(detect_pitch_decay_aware(late, sample_rate), -1.0);
+
+        // A sustained note at the same level is still detected
+        let sustained: Vec<f32> = generate_sine_wave(220.0, sample_rate, 2048)
+            .iter()
+            .map(|x| x * 0.03)
+            .collect();
+        let detected = detect_pitch_decay_aware(&sustained, sample_rate);
+        assert!((detected - 220.0).abs() < 2.0, "got {}", detected);
+    }
+
     #[test]
     fn test_rms() {
         let samples = vec![1.0, -1.0, 1.0, -1.0];
         let rms = calculate_rms(&samples);
         assert!((rms - 1.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_rms_fast() {
+        let samples = generate_sine_wave(440.0, 44100.0, 4096);
+        let exact = calculate_rms(&samples);
+        assert_eq!(calculate_rms_fast(&samples, 1), exact);
+        for stride in [2, 4, 8, 16] {
+            let approx = calculate_rms_fast(&samples, stride);
+            assert!(
+                (approx - exact).abs() < 0.02,
+                "stride {}: {} vs {}",
+                stride,
+                approx,
+                exact
+            );
+        }
+        assert_eq!(calculate_rms_fast(&[], 4), 0.0);
+    }
+
+    #[test]
+    fn test_concurrent_detection_is_deterministic() {
+        use crate::testutil::{add_noise_seeded, NoiseKind};
+
+        let sample_rate = 44100.0;
+        let frames: Vec<Vec<f32>> = [82.41, 196.0, 440.0, 1318.5]
+            .iter()
+            .enumerate()
+            .map(|(i, &f)| {
+                let mut frame = generate_tone(f, sample_rate, 2048, Waveform::Saw);
+                add_noise_seeded(&mut frame, 15.0, NoiseKind::Pink, i as u64);
+                frame
+            })
+            .collect();
+        let analyse = |frame: &[f32]| {
+            (
+                detect_pitch(frame, sample_rate),
+                get_pitch_clarity(frame, sample_rate),
+                detect_pitch_pyin(frame, sample_rate),
+            )
+        };
+        let expected: Vec<_> = frames.iter().map(|f| analyse(f)).collect();
+
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| frames.iter().map(|f| analyse(f)).collect::<Vec<_>>()))
+                .collect();
+            for worker in workers {
+                assert_eq!(worker.join().unwrap(), expected);
+            }
+        });
+    }
+
+    #[test]
+    fn test_labeled_pitch() {
+        let sample_rate = 44100.0;
+        let samples = generate_sine_wave(440.0, sample_rate, 2048);
+        let label = detect_pitch_labeled(&samples, sample_rate, 440.0);
+        // Interpolation leaves the estimate within a few hundredths of a Hz
+        assert!(
+            label == "A4 +0.0¢ (440.0 Hz)" || label == "A4 +0.1¢ (440.0 Hz)",
+            "{}",
+            label
+        );
+
+        // Same tone against a flatter reference reads sharp
+        let label = detect_pitch_labeled(&samples, sample_rate, 436.0);
+        assert!(label.starts_with("A4 +15."), "{}", label);
+
+        assert_eq!(detect_pitch_labeled(&[0.0; 2048], sample_rate, 440.0), "—");
+    }
 }
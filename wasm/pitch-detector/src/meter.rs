@@ -0,0 +1,125 @@
+use std::f32::consts::PI;
+
+use wasm_bindgen::prelude::*;
+
+use crate::note::cents_between;
+
+/// Mapping from a cents deviation to a position on a strip tuner display
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeterConfig {
+    /// Deviation shown at either end of the strip, in cents
+    pub range_cents: f32,
+    /// Pin out-of-range deviations to the ends instead of running past them
+    pub clamp: bool,
+    /// tanh warping strength; 0.0 is linear, larger values magnify small
+    /// deviations around the centre
+    pub warp: f32,
+}
+
+#[wasm_bindgen]
+impl MeterConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for MeterConfig {
+    fn default() -> Self {
+        Self {
+            range_cents: 50.0,
+            clamp: true,
+            warp: 0.0,
+        }
+    }
+}
+
+/// Position of a ±50 cent deviation on a strip: 0.0 at -50, 0.5 in tune,
+/// 1.0 at +50, clamped. NaN (unvoiced) maps to NaN
+#[wasm_bindgen]
+pub fn meter_position(cents: f32) -> f32 {
+    meter_position_with(cents, &MeterConfig::default())
+}
+
+/// Position of a deviation on a strip using the given mapping
+/// The warped mapping is normalized so the ends stay at 0.0 and 1.0
+#[wasm_bindgen]
+pub fn meter_position_with(cents: f32, config: &MeterConfig) -> f32 {
+    let mut x = cents / config.range_cents;
+    if config.clamp {
+        x = x.clamp(-1.0, 1.0);
+    }
+    if config.warp > 0.0 {
+        x = (config.warp * x).tanh() / config.warp.tanh();
+    }
+    0.5 + 0.5 * x
+}
+
+/// How close a detected pitch is to a target, for "hit the note" games
+/// Follows a raised cosine: 1.0 exactly on target, 0.5 at half the window,
+/// 0.0 at `window_cents` away and beyond.
+/// Returns 0.0 for an unvoiced (-1.0) detection
+#[wasm_bindgen]
+pub fn note_proximity(detected_hz: f32, target_hz: f32, window_cents: f32) -> f32 {
+    if detected_hz <= 0.0 || target_hz <= 0.0 || window_cents <= 0.0 {
+        return 0.0;
+    }
+    let x = cents_between(detected_hz, target_hz).abs() / window_cents;
+    if x >= 1.0 {
+        return 0.0;
+    }
+    0.5 + 0.5 * (PI * x).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_meter() {
+        assert_eq!(meter_position(-50.0), 0.0);
+        assert_eq!(meter_position(0.0), 0.5);
+        assert_eq!(meter_position(50.0), 1.0);
+        assert_eq!(meter_position(25.0), 0.75);
+        assert_eq!(meter_position(-80.0), 0.0);
+        assert!(meter_position(f32::NAN).is_nan());
+
+        let unclamped = MeterConfig {
+            clamp: false,
+            ..MeterConfig::default()
+        };
+        assert_eq!(meter_position_with(100.0, &unclamped), 1.5);
+    }
+
+    #[test]
+    fn test_warped_meter_magnifies_centre() {
+        let warped = MeterConfig {
+            warp: 3.0,
+            ..MeterConfig::default()
+        };
+        assert!((meter_position_with(-50.0, &warped)).abs() < 1e-6);
+        assert_eq!(meter_position_with(0.0, &warped), 0.5);
+        assert!((meter_position_with(50.0, &warped) - 1.0).abs() < 1e-6);
+
+        // 5 cents sharp moves the needle much further than linearly
+        let linear = meter_position(5.0) - 0.5;
+        let magnified = meter_position_with(5.0, &warped) - 0.5;
+        assert!(magnified > 2.5 * linear, "{} vs {}", magnified, linear);
+    }
+
+    #[test]
+    fn test_note_proximity() {
+        let target = 440.0;
+        assert_eq!(note_proximity(target, target, 50.0), 1.0);
+
+        let half_window = target * 2.0f32.powf(25.0 / 1200.0);
+        assert!((note_proximity(half_window, target, 50.0) - 0.5).abs() < 1e-3);
+        let flat_half = target * 2.0f32.powf(-25.0 / 1200.0);
+        assert!((note_proximity(flat_half, target, 50.0) - 0.5).abs() < 1e-3);
+
+        let beyond = target * 2.0f32.powf(80.0 / 1200.0);
+        assert_eq!(note_proximity(beyond, target, 50.0), 0.0);
+        assert_eq!(note_proximity(-1.0, target, 50.0), 0.0);
+    }
+}
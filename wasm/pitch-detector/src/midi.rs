@@ -0,0 +1,220 @@
+use crate::calculate_rms;
+use crate::detector::{HoldPolicy, PitchDetector};
+use crate::note::ReferencePitch;
+
+/// A pitch this many cents past the sounding note (beyond the ±50 cent note
+/// boundary) starts a new note; closer is bent instead, so a note wavering
+/// on a boundary does not retrigger
+const NOTE_CHANGE_CENTS: f32 = 70.0;
+
+/// Bend range the receiving synth is expected to use, either way
+const PITCH_BEND_RANGE_SEMITONES: f32 = 2.0;
+
+/// Centre of the 14-bit pitch bend range
+const PITCH_BEND_CENTRE: u16 = 8192;
+
+/// Smallest bend change worth sending, in cents
+const PITCH_BEND_STEP_CENTS: f32 = 2.0;
+
+/// A block this many times louder than the one before is a new attack
+const ONSET_RMS_RATIO: f32 = 2.0;
+
+/// Block RMS given full velocity
+const FULL_VELOCITY_RMS: f32 = 0.5;
+
+/// A MIDI channel voice message produced by [`MonoTracker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiEvent {
+    NoteOn {
+        note: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        note: u8,
+    },
+    /// 14-bit bend value, 8192 being no bend, for a range of ±2 semitones
+    PitchBend {
+        value: u16,
+    },
+}
+
+/// Monophonic audio-to-MIDI converter for guitar or bass
+/// Each block goes through a [`PitchDetector`] with octave continuity and
+/// a one-block hold, so a single dropped frame does not cut a note. The
+/// first voiced block sends a note on with a velocity from its level; the
+/// pitch is then followed with bends until it strays more than 70 cents
+/// from the note, or a new attack (a jump in level) arrives, either of
+/// which ends the note so the next block starts the new one. Unvoiced
+/// blocks end the note. At most one event is produced per block
+pub struct MonoTracker {
+    detector: PitchDetector,
+    reference: ReferencePitch,
+    /// The sounding note, None between notes
+    note: Option<u8>,
+    /// Bend last sent, in cents; it carries over into the next note until
+    /// a new bend is sent
+    bend_cents: f32,
+    previous_rms: f32,
+}
+
+impl MonoTracker {
+    /// Notes are numbered against A4 at `a4` Hz
+    pub fn new(a4: f32) -> Self {
+        let mut detector = PitchDetector::new();
+        detector.set_continuity(true);
+        detector.set_hold_policy(HoldPolicy::Hold(1));
+        Self {
+            detector,
+            reference: ReferencePitch::a4(a4),
+            note: None,
+            bend_cents: 0.0,
+            previous_rms: 0.0,
+        }
+    }
+
+    /// The note currently on, if any
+    pub fn current_note(&self) -> Option<u8> {
+        self.note
+    }
+
+    /// Analyze one block and return the event it produces, if any
+    pub fn process(&mut self, samples: &[f32], sample_rate: f32) -> Option<MidiEvent> {
+        let rms = calculate_rms(samples);
+        let onset = rms > ONSET_RMS_RATIO * self.previous_rms;
+        self.previous_rms = rms;
+        if onset && self.note.is_some() {
+            // Start the new attack without the old note's history
+            self.detector.reset();
+            return self.note_off();
+        }
+
+        let frequency = self.detector.process(samples, sample_rate);
+        if frequency <= 0.0 {
+            return self.note_off();
+        }
+        let midi = self.reference.frequency_to_midi(frequency);
+        let note = match self.note {
+            Some(note) => note,
+            None => {
+                let note = midi.round().clamp(0.0, 127.0) as u8;
+                self.note = Some(note);
+                let velocity = 1.0 + 126.0 * (rms / FULL_VELOCITY_RMS).min(1.0);
+                return Some(MidiEvent::NoteOn {
+                    note,
+                    velocity: velocity.round() as u8,
+                });
+            }
+        };
+
+        let cents = 100.0 * (midi - note as f32);
+        if cents.abs() > NOTE_CHANGE_CENTS {
+            return self.note_off();
+        }
+        if (cents - self.bend_cents).abs() < PITCH_BEND_STEP_CENTS {
+            return None;
+        }
+        self.bend_cents = cents;
+        Some(MidiEvent::PitchBend {
+            value: pitch_bend_value(cents),
+        })
+    }
+
+    /// End any sounding note and forget the pitch history
+    pub fn reset(&mut self) {
+        self.detector.reset();
+        self.note = None;
+        self.bend_cents = 0.0;
+        self.previous_rms = 0.0;
+    }
+
+    fn note_off(&mut self) -> Option<MidiEvent> {
+        self.note.take().map(|note| MidiEvent::NoteOff { note })
+    }
+}
+
+/// 14-bit bend value for a bend in cents, clamped to the bend range
+fn pitch_bend_value(cents: f32) -> u16 {
+    let fraction = (cents / (100.0 * PITCH_BEND_RANGE_SEMITONES)).clamp(-1.0, 1.0);
+    let value = PITCH_BEND_CENTRE as f32 + fraction * PITCH_BEND_CENTRE as f32;
+    (value.round() as u16).min(16383)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+
+    /// Blocks of a plucked 110 Hz saw: an attack decaying over `len` samples
+    fn pluck(sample_rate: f32, len: usize) -> Vec<f32> {
+        generate_tone(110.0, sample_rate, len, Waveform::Saw)
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| 0.8 * x * (-(i as f32) / (0.8 * sample_rate)).exp())
+            .collect()
+    }
+
+    #[test]
+    fn test_pluck_sustain_release() {
+        let sample_rate = 44100.0;
+        let block = 1024;
+        let mut signal = vec![0.0; 2 * block];
+        signal.extend(pluck(sample_rate, 40 * block));
+        signal.extend(vec![0.0; 4 * block]);
+
+        let mut tracker = MonoTracker::new(440.0);
+        let events: Vec<MidiEvent> = signal
+            .chunks_exact(block)
+            .filter_map(|chunk| tracker.process(chunk, sample_rate))
+            .collect();
+
+        let notes: Vec<MidiEvent> = events
+            .iter()
+            .copied()
+            .filter(|e| !matches!(e, MidiEvent::PitchBend { .. }))
+            .collect();
+        assert_eq!(notes.len(), 2, "{:?}", events);
+        match notes[0] {
+            MidiEvent::NoteOn { note, velocity } => {
+                assert_eq!(note, 45);
+                assert!(velocity > 64, "{}", velocity);
+            }
+            other => panic!("expected a note on, got {:?}", other),
+        }
+        assert_eq!(notes[1], MidiEvent::NoteOff { note: 45 });
+        assert_eq!(tracker.current_note(), None);
+    }
+
+    #[test]
+    fn test_bend_and_note_change() {
+        let sample_rate = 44100.0;
+        let tone = |f: f32| generate_tone(f, sample_rate, 2048, Waveform::Saw);
+        let mut tracker = MonoTracker::new(440.0);
+        assert!(matches!(
+            tracker.process(&tone(440.0), sample_rate),
+            Some(MidiEvent::NoteOn { note: 69, .. })
+        ));
+        assert_eq!(tracker.process(&tone(440.0), sample_rate), None);
+
+        // 40 cents sharp is still A4, bent up
+        let sharp = 440.0 * 2f32.powf(40.0 / 1200.0);
+        match tracker.process(&tone(sharp), sample_rate) {
+            Some(MidiEvent::PitchBend { value }) => {
+                assert!((value as i32 - 8192 - 1638).abs() < 100, "{}", value)
+            }
+            other => panic!("expected a bend, got {:?}", other),
+        }
+
+        // A whole tone up ends A4, and the next block starts B4
+        assert_eq!(
+            tracker.process(&tone(493.88), sample_rate),
+            Some(MidiEvent::NoteOff { note: 69 })
+        );
+        assert!(matches!(
+            tracker.process(&tone(493.88), sample_rate),
+            Some(MidiEvent::NoteOn { note: 71, .. })
+        ));
+        assert_eq!(pitch_bend_value(0.0), 8192);
+        assert_eq!(pitch_bend_value(-500.0), 0);
+        assert_eq!(pitch_bend_value(500.0), 16383);
+    }
+}
@@ -0,0 +1,882 @@
+use wasm_bindgen::prelude::*;
+
+const NOTE_NAMES_SHARP: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+const NOTE_NAMES_FLAT: [&str; 12] = [
+    "C", "Db", "D", "Eb", "E", "F", "Gb", "G", "Ab", "A", "Bb", "B",
+];
+
+const NOTE_NAMES_SOLFEGE: [&str; 12] = [
+    "Do", "Do#", "Re", "Re#", "Mi", "Fa", "Fa#", "Sol", "Sol#", "La", "La#", "Si",
+];
+
+const SEMITONES_PER_OCTAVE: f32 = 12.0;
+
+/// Octave numbers `parse_note_name` accepts, those of the MIDI range
+const NOTE_NAME_OCTAVES: std::ops::RangeInclusive<i32> = -1..=9;
+const CENTS_PER_OCTAVE: f32 = 1200.0;
+
+/// Speed of sound in dry air at 20 °C, in m/s
+const SPEED_OF_SOUND_AIR: f32 = 343.0;
+
+/// Railsback stretch at the ends of the piano keyboard, in cents: A0 is tuned
+/// this much flat and C8 this much sharp of equal temperament
+const RAILSBACK_BASS_CENTS: f32 = -30.0;
+const RAILSBACK_TREBLE_CENTS: f32 = 30.0;
+
+/// Entries in a stretch table, one per MIDI note
+const MIDI_NOTE_COUNT: usize = 128;
+
+/// MIDI notes of the lowest (A0) and highest (C8) piano keys
+const PIANO_LOWEST_MIDI: f32 = 21.0;
+const PIANO_HIGHEST_MIDI: f32 = 108.0;
+
+/// Refinement rounds for `suggest_a4`; one usually suffices
+const SUGGEST_A4_ITERATIONS: usize = 4;
+/// `suggest_a4` stops once a round moves the reference by less than this
+const SUGGEST_A4_TOLERANCE_CENTS: f32 = 0.01;
+
+/// A reference pitch anchoring equal temperament: `midi_note` sounds at `frequency` Hz
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReferencePitch {
+    pub midi_note: u8,
+    pub frequency: f32,
+}
+
+impl ReferencePitch {
+    pub const fn new(midi_note: u8, frequency: f32) -> Self {
+        Self {
+            midi_note,
+            frequency,
+        }
+    }
+
+    /// Conventional A4 reference (MIDI 69)
+    pub const fn a4(frequency: f32) -> Self {
+        Self::new(69, frequency)
+    }
+
+    /// Fractional MIDI note number for a frequency
+    pub fn frequency_to_midi(&self, frequency: f32) -> f32 {
+        SEMITONES_PER_OCTAVE * (frequency / self.frequency).log2() + self.midi_note as f32
+    }
+
+    /// `frequency_to_midi` with a polynomial log2 instead of the library call
+    /// Within 0.01 cents of the exact value for any positive frequency, for
+    /// integrators whose profiles show the conversion as hot. Non-positive
+    /// and non-finite input behaves as in `frequency_to_midi`
+    pub fn frequency_to_midi_fast(&self, frequency: f32) -> f32 {
+        SEMITONES_PER_OCTAVE * fast_log2(frequency / self.frequency) + self.midi_note as f32
+    }
+
+    /// Frequency of a (possibly fractional) MIDI note number
+    pub fn midi_to_frequency(&self, midi: f32) -> f32 {
+        self.frequency * 2f32.powf((midi - self.midi_note as f32) / SEMITONES_PER_OCTAVE)
+    }
+}
+
+impl Default for ReferencePitch {
+    fn default() -> Self {
+        Self::a4(440.0)
+    }
+}
+
+/// log2 from the float's exponent bits plus a short series for the mantissa
+/// The mantissa is folded into [sqrt(1/2), sqrt(2)), where the atanh series
+/// `log2(m) = 2 / ln 2 * (t + t^3 / 3 + t^5 / 5)` with `t = (m - 1) / (m + 1)`
+/// is within 2e-6 of exact, about 0.002 cents
+fn fast_log2(x: f32) -> f32 {
+    if !x.is_normal() || x < 0.0 {
+        return x.log2();
+    }
+    let bits = x.to_bits();
+    let mut exponent = ((bits >> 23) & 0xff) as i32 - 127;
+    let mut mantissa = f32::from_bits((bits & 0x007f_ffff) | 0x3f80_0000);
+    if mantissa > std::f32::consts::SQRT_2 {
+        mantissa *= 0.5;
+        exponent += 1;
+    }
+    let t = (mantissa - 1.0) / (mantissa + 1.0);
+    let t2 = t * t;
+    let series = t * (1.0 + t2 * (1.0 / 3.0 + t2 * (1.0 / 5.0)));
+    exponent as f32 + 2.0 * std::f32::consts::LOG2_E * series
+}
+
+/// Interval from `f2` up to `f1` in cents (1200 per octave)
+/// Returns NaN if either frequency is non-positive
+#[wasm_bindgen]
+pub fn cents_between(f1: f32, f2: f32) -> f32 {
+    if f1 <= 0.0 || f2 <= 0.0 {
+        return f32::NAN;
+    }
+    ratio_to_cents(f1 / f2)
+}
+
+/// Beat rate heard when a tone sounds together with a reference, in Hz
+/// `|detected - reference|`: the loudness wobble that slows to nothing as
+/// the two come into tune. Unlike cents it is absolute, so the same cents
+/// error beats faster on higher notes.
+/// Returns NaN if either frequency is non-positive
+#[wasm_bindgen]
+pub fn beat_frequency(detected_hz: f32, reference_hz: f32) -> f32 {
+    if detected_hz <= 0.0 || reference_hz <= 0.0 {
+        return f32::NAN;
+    }
+    (detected_hz - reference_hz).abs()
+}
+
+/// Frequency multiplier for an interval in cents: 2^(cents / 1200)
+#[wasm_bindgen]
+pub fn cents_to_ratio(cents: f32) -> f32 {
+    2f32.powf(cents / CENTS_PER_OCTAVE)
+}
+
+/// Interval in cents for a frequency multiplier: 1200 * log2(ratio)
+/// Returns NaN for non-positive ratios
+#[wasm_bindgen]
+pub fn ratio_to_cents(ratio: f32) -> f32 {
+    if ratio <= 0.0 {
+        return f32::NAN;
+    }
+    CENTS_PER_OCTAVE * ratio.log2()
+}
+
+/// Wavelength of a tone in metres: `speed_of_sound / freq`
+/// `speed_of_sound` is in m/s. Returns NaN if `freq` is non-positive
+#[wasm_bindgen]
+pub fn wavelength_meters(freq: f32, speed_of_sound: f32) -> f32 {
+    if freq <= 0.0 {
+        return f32::NAN;
+    }
+    speed_of_sound / freq
+}
+
+/// Wavelength of a tone in room-temperature air (343 m/s), in metres
+/// Returns NaN if `freq` is non-positive
+#[wasm_bindgen]
+pub fn wavelength_in_air(freq: f32) -> f32 {
+    wavelength_meters(freq, SPEED_OF_SOUND_AIR)
+}
+
+/// Resampling ratio that moves `detected_hz` onto the nearest note
+/// Returns `nearest_note_hz / detected_hz`, or 1.0 (no change) when unvoiced
+#[wasm_bindgen]
+pub fn correction_ratio(detected_hz: f32, a4: f32) -> f32 {
+    match frequency_to_note(detected_hz, ReferencePitch::a4(a4)) {
+        Some(note) => note.note_frequency / detected_hz,
+        None => 1.0,
+    }
+}
+
+/// Exact equal-tempered frequency of the note nearest to `freq`
+/// For snap-to-grid pitch correction and drawing target lines.
+/// Returns 0.0 when `freq` is not positive
+#[wasm_bindgen]
+pub fn snap_to_note(freq: f32, a4: f32) -> f32 {
+    frequency_to_note(freq, ReferencePitch::a4(a4)).map_or(0.0, |note| note.note_frequency)
+}
+
+/// Mean deviation of detected notes from equal temperament at `a4`, in cents
+/// A consistent offset suggests retuning the reference: moving A4 by
+/// `cents_to_ratio(average_detuning(..))` brings the notes in tune on
+/// average. Unvoiced entries are skipped; NaN if none are voiced
+#[wasm_bindgen]
+pub fn average_detuning(freqs: &[f32], a4: f32) -> f32 {
+    let reference = ReferencePitch::a4(a4);
+    let (sum, count) = freqs
+        .iter()
+        .filter_map(|&f| frequency_to_note(f, reference))
+        .fold((0.0f32, 0usize), |(sum, count), note| {
+            (sum + note.cents, count + 1)
+        });
+    if count == 0 {
+        return f32::NAN;
+    }
+    sum / count as f32
+}
+
+/// A4 reference that best fits a set of detected notes
+/// Minimizes the squared cents error of the voiced notes against their
+/// nearest notes, re-snapping after each step in case a note changed its
+/// nearest neighbour. Useful to pick up an ensemble's tuning by ear.
+/// Returns `current_a4` if no note is voiced
+#[wasm_bindgen]
+pub fn suggest_a4(freqs: &[f32], current_a4: f32) -> f32 {
+    let mut a4 = current_a4;
+    for _ in 0..SUGGEST_A4_ITERATIONS {
+        let detuning = average_detuning(freqs, a4);
+        if detuning.is_nan() {
+            break;
+        }
+        a4 *= cents_to_ratio(detuning);
+        if detuning.abs() < SUGGEST_A4_TOLERANCE_CENTS {
+            break;
+        }
+    }
+    a4
+}
+
+/// Position of a pitch within a custom scale
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaleMatch {
+    /// Index into the scale's degree list
+    pub degree: usize,
+    /// Deviation from that degree; positive means sharp
+    pub cents: f32,
+}
+
+/// Snap a frequency to the nearest degree of an arbitrary octave-repeating scale
+/// `cents_per_degree` lists each degree's offset above the tonic within one
+/// octave (e.g. `[0, 200, 400, 600, 800, 1000]` for a whole-tone scale), so
+/// maqam, raga and other microtonal scales work as well as 12-TET subsets.
+/// The octave a pitch lies in does not matter, and distances wrap around it.
+/// Returns degree 0 with NaN cents for non-positive input or an empty scale
+#[wasm_bindgen]
+pub fn nearest_scale_degree(freq: f32, tonic_hz: f32, cents_per_degree: &[f32]) -> ScaleMatch {
+    let position = cents_between(freq, tonic_hz).rem_euclid(CENTS_PER_OCTAVE);
+    let wrapped = |offset: f32| {
+        (position - offset + CENTS_PER_OCTAVE / 2.0).rem_euclid(CENTS_PER_OCTAVE)
+            - CENTS_PER_OCTAVE / 2.0
+    };
+    cents_per_degree
+        .iter()
+        .map(|&offset| wrapped(offset))
+        .enumerate()
+        .filter(|(_, cents)| !cents.is_nan())
+        .min_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+        .map(|(degree, cents)| ScaleMatch { degree, cents })
+        .unwrap_or(ScaleMatch {
+            degree: 0,
+            cents: f32::NAN,
+        })
+}
+
+/// A typical piano stretch (Railsback curve) as a `cents_offsets` table
+/// One entry per MIDI note 0-127 relative to A4: flat in the middle of the
+/// keyboard, then growing with the cube of the distance to about -30 cents at
+/// A0 and +30 cents at C8, the octave widening inharmonic piano strings need.
+/// Real pianos vary; this is a starting point, not a measurement
+pub fn railsback_stretch() -> [f32; MIDI_NOTE_COUNT] {
+    let a4 = ReferencePitch::default().midi_note as f32;
+    std::array::from_fn(|midi| {
+        let distance = midi as f32 - a4;
+        if distance >= 0.0 {
+            RAILSBACK_TREBLE_CENTS * (distance / (PIANO_HIGHEST_MIDI - a4)).powi(3)
+        } else {
+            RAILSBACK_BASS_CENTS * (distance / (PIANO_LOWEST_MIDI - a4)).powi(3)
+        }
+    })
+}
+
+/// Spelling convention for note names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoteNaming {
+    /// C, C#, D, ...
+    #[default]
+    Sharps,
+    /// C, Db, D, ...
+    Flats,
+    /// Do, Do#, Re, ...
+    Solfege,
+}
+
+impl NoteNaming {
+    fn names(self) -> &'static [&'static str; 12] {
+        match self {
+            Self::Sharps => &NOTE_NAMES_SHARP,
+            Self::Flats => &NOTE_NAMES_FLAT,
+            Self::Solfege => &NOTE_NAMES_SOLFEGE,
+        }
+    }
+}
+
+/// Tuning system notes are matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Temperament {
+    /// 12-tone equal temperament with conventional note names
+    #[default]
+    Equal,
+    /// The octave split into this many equal steps of `1200 / n` cents, e.g.
+    /// 19, 24 or 31. Degrees are counted up from the reference note and named
+    /// after it, so with A4 as reference the 24-EDO quarter tone above it is
+    /// "A4+1" and the octave above is "A5"
+    Edo(u16),
+}
+
+/// Options controlling how frequencies are mapped to note names
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NamingConfig {
+    pub reference: ReferencePitch,
+    pub naming: NoteNaming,
+    /// Semitones added to the sounding pitch for display, e.g. +2 for a Bb
+    /// trumpet or the capo fret. Only `note_name` and `octave` are shifted;
+    /// the MIDI note, cents and note frequency describe the actual sound
+    pub transpose_semitones: i32,
+    /// Target offset from equal temperament in cents, indexed by MIDI note,
+    /// for stretched tunings such as `railsback_stretch`; None for none.
+    /// Notes outside the MIDI range are unstretched. `cents` and
+    /// `note_frequency` are measured against the stretched target; the
+    /// nearest note is still chosen in equal temperament
+    pub cents_offsets: Option<[f32; MIDI_NOTE_COUNT]>,
+    /// Tuning the nearest note is chosen in. `transpose_semitones` and
+    /// `cents_offsets` only apply to `Temperament::Equal`
+    pub temperament: Temperament,
+}
+
+/// Nearest equal-tempered note for a frequency
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteInfo {
+    pub midi_note: i32,
+    /// Note name with octave in scientific pitch notation, e.g. "A4"
+    pub note_name: String,
+    /// Octave of `note_name`
+    pub octave: i32,
+    /// Deviation of the input from the nearest note, in cents
+    pub cents: f32,
+    /// Exact frequency of the nearest note
+    pub note_frequency: f32,
+}
+
+/// The nearest note to a pitch with its chromatic neighbours, for piano-roll
+/// style displays
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteContext {
+    /// The semitone below `current`
+    pub below: NoteInfo,
+    /// The nearest note, with the input's deviation in its `cents`
+    pub current: NoteInfo,
+    /// The semitone above `current`
+    pub above: NoteInfo,
+    /// Deviation of the input from `current`, in cents
+    pub cents: f32,
+}
+
+/// Nearest note and its neighbours a semitone either side, with A4 at `a4`
+/// The neighbours describe the exact notes, so their `cents` are zero. Notes
+/// past either end of the MIDI range are named by extending the usual scheme
+/// (MIDI -1 is "B-2", 128 is "G#9") rather than being cut off.
+/// Returns None for non-positive or non-finite frequencies
+pub fn note_context(freq: f32, a4: f32) -> Option<NoteContext> {
+    let reference = ReferencePitch::a4(a4);
+    let current = frequency_to_note(freq, reference)?;
+    let neighbour = |offset: i32| {
+        let mut note = frequency_to_note(
+            reference.midi_to_frequency((current.midi_note + offset) as f32),
+            reference,
+        )?;
+        note.cents = 0.0;
+        Some(note)
+    };
+    Some(NoteContext {
+        below: neighbour(-1)?,
+        above: neighbour(1)?,
+        cents: current.cents,
+        current,
+    })
+}
+
+/// Find the nearest note relative to the given reference pitch
+/// Returns None for non-positive or non-finite frequencies
+pub fn frequency_to_note(frequency: f32, reference: ReferencePitch) -> Option<NoteInfo> {
+    frequency_to_note_with(
+        frequency,
+        &NamingConfig {
+            reference,
+            ..NamingConfig::default()
+        },
+    )
+}
+
+/// Find the nearest note using the given naming options
+/// Returns None for non-positive or non-finite frequencies
+pub fn frequency_to_note_with(frequency: f32, config: &NamingConfig) -> Option<NoteInfo> {
+    if !frequency.is_finite() || frequency <= 0.0 {
+        return None;
+    }
+
+    if let Temperament::Edo(divisions) = config.temperament {
+        return edo_note(frequency, config, divisions);
+    }
+
+    let reference = config.reference;
+    let midi = reference.frequency_to_midi(frequency);
+    let midi_note = midi.round() as i32;
+    let written_note = midi_note + config.transpose_semitones;
+    let pitch_class = written_note.rem_euclid(12) as usize;
+    let octave = written_note.div_euclid(12) - 1;
+    let stretch = usize::try_from(midi_note)
+        .ok()
+        .and_then(|index| config.cents_offsets.as_ref()?.get(index))
+        .copied()
+        .unwrap_or(0.0);
+
+    Some(NoteInfo {
+        midi_note,
+        note_name: format!("{}{}", config.naming.names()[pitch_class], octave),
+        octave,
+        cents: (midi - midi_note as f32) * 100.0 - stretch,
+        note_frequency: reference.midi_to_frequency(midi_note as f32) * cents_to_ratio(stretch),
+    })
+}
+
+/// Why a note name could not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The name does not start with a letter from A to G
+    InvalidLetter(String),
+    /// The accidentals are not followed by an octave number from -1 to 9
+    InvalidOctave(String),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidLetter(name) => write!(f, "{:?} does not start with a note letter", name),
+            Self::InvalidOctave(name) => write!(f, "{:?} has no valid octave number", name),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// MIDI note of a name in scientific pitch notation, e.g. "E2", "F#3",
+/// "Bb4" or "C-1"
+/// The letter may be either case; any number of sharps (`#`) and flats
+/// (`b`) may follow it. Octaves run from -1 to 9, the MIDI range.
+/// Surrounding whitespace is ignored
+pub(crate) fn parse_note_name(name: &str) -> Result<i32, ParseError> {
+    let trimmed = name.trim();
+    let mut chars = trimmed.chars();
+    let letter = match chars.next().map(|c| c.to_ascii_uppercase()) {
+        Some(letter @ 'A'..='G') => letter,
+        _ => return Err(ParseError::InvalidLetter(name.to_string())),
+    };
+    let pitch_class = NOTE_NAMES_SHARP
+        .iter()
+        .position(|n| n.starts_with(letter))
+        .unwrap_or_default() as i32;
+
+    let rest = chars.as_str();
+    let octave_start = rest.find(|c| c != '#' && c != 'b').unwrap_or(rest.len());
+    let (accidentals, octave) = rest.split_at(octave_start);
+    let alteration: i32 = accidentals
+        .chars()
+        .map(|c| if c == '#' { 1 } else { -1 })
+        .sum();
+    let midi = octave
+        .parse::<i32>()
+        .ok()
+        .filter(|octave| NOTE_NAME_OCTAVES.contains(octave))
+        .and_then(|octave| (octave + 1).checked_mul(12))
+        .and_then(|c_midi| c_midi.checked_add(pitch_class + alteration));
+    midi.ok_or_else(|| ParseError::InvalidOctave(name.to_string()))
+}
+
+/// `frequency_to_note_with` for an EDO temperament
+/// `midi_note` is the equal-tempered note nearest the matched degree.
+/// Returns None for zero divisions
+fn edo_note(frequency: f32, config: &NamingConfig, divisions: u16) -> Option<NoteInfo> {
+    let reference = config.reference;
+    let (step, cents) = edo_step(frequency, reference.frequency, divisions)?;
+    let divisions = i32::from(divisions);
+    let degree = step.rem_euclid(divisions);
+    let octave = (reference.midi_note as i32).div_euclid(12) - 1 + step.div_euclid(divisions);
+    let name = config.naming.names()[reference.midi_note as usize % 12];
+    let note_frequency = reference.frequency * 2f32.powf(step as f32 / divisions as f32);
+
+    Some(NoteInfo {
+        midi_note: reference.frequency_to_midi(note_frequency).round() as i32,
+        note_name: if degree == 0 {
+            format!("{}{}", name, octave)
+        } else {
+            format!("{}{}+{}", name, octave, degree)
+        },
+        octave,
+        cents,
+        note_frequency,
+    })
+}
+
+/// Position of a pitch in an equal division of the octave
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdoMatch {
+    /// Step above A within the octave, from 0 to `divisions - 1`
+    pub degree: u16,
+    /// Deviation from that step; positive means sharp
+    pub cents: f32,
+}
+
+/// Nearest step of `divisions`-tone equal temperament, with A4 at `a4`
+/// Steps are `1200 / divisions` cents apart and counted from A in every
+/// octave, so the octave a pitch lies in does not matter.
+/// Returns degree 0 with NaN cents for non-positive input or zero divisions
+#[wasm_bindgen]
+pub fn frequency_to_edo_degree(freq: f32, a4: f32, divisions: u16) -> EdoMatch {
+    match edo_step(freq, a4, divisions) {
+        Some((step, cents)) => EdoMatch {
+            degree: step.rem_euclid(i32::from(divisions)) as u16,
+            cents,
+        },
+        None => EdoMatch {
+            degree: 0,
+            cents: f32::NAN,
+        },
+    }
+}
+
+/// Nearest step to `frequency` counted from `reference_hz`, and the
+/// deviation from it in cents
+fn edo_step(frequency: f32, reference_hz: f32, divisions: u16) -> Option<(i32, f32)> {
+    if divisions == 0 || !frequency.is_finite() || frequency <= 0.0 || reference_hz <= 0.0 {
+        return None;
+    }
+    let steps = divisions as f32 * (frequency / reference_hz).log2();
+    let step = steps.round();
+    Some((
+        step as i32,
+        (steps - step) * CENTS_PER_OCTAVE / divisions as f32,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_a440_reference() {
+        let note = frequency_to_note(440.0, ReferencePitch::default()).unwrap();
+        assert_eq!(note.note_name, "A4");
+        assert_eq!(note.midi_note, 69);
+        assert!(note.cents.abs() < 0.01);
+    }
+
+    #[test]
+    fn test_fast_midi_conversion_error_bound() {
+        let reference = ReferencePitch::default();
+        let mut frequency = 60.0f32;
+        while frequency <= 2000.0 {
+            let exact = reference.frequency_to_midi(frequency);
+            let fast = reference.frequency_to_midi_fast(frequency);
+            // 0.1 cent is a thousandth of a semitone
+            assert!(
+                (fast - exact).abs() < 0.001,
+                "{} Hz: {} vs {}",
+                frequency,
+                fast,
+                exact
+            );
+            frequency *= 1.0007;
+        }
+        assert_eq!(reference.frequency_to_midi_fast(440.0), 69.0);
+        assert!(reference.frequency_to_midi_fast(-1.0).is_nan());
+        assert_eq!(reference.frequency_to_midi_fast(0.0), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_scientific_c256_reference() {
+        // Scientific pitch: MIDI 60 (C4) = 256 Hz puts A4 at 256 * 2^(9/12)
+        let reference = ReferencePitch::new(60, 256.0);
+        let expected_a4 = 256.0 * 2f32.powf(9.0 / 12.0);
+        assert!((reference.midi_to_frequency(69.0) - expected_a4).abs() < 0.01);
+
+        let note = frequency_to_note(expected_a4, reference).unwrap();
+        assert_eq!(note.note_name, "A4");
+        assert!(note.cents.abs() < 0.01);
+        assert!((note.note_frequency - expected_a4).abs() < 0.01);
+
+        // 440 Hz is sharp of A4 under this tuning
+        let note = frequency_to_note(440.0, reference).unwrap();
+        assert_eq!(note.note_name, "A4");
+        assert!(note.cents > 30.0, "got {} cents", note.cents);
+    }
+
+    #[test]
+    fn test_note_naming_conventions() {
+        let c_sharp4 = ReferencePitch::default().midi_to_frequency(61.0);
+        let name = |naming| {
+            let config = NamingConfig {
+                naming,
+                ..NamingConfig::default()
+            };
+            frequency_to_note_with(c_sharp4, &config).unwrap().note_name
+        };
+        assert_eq!(name(NoteNaming::Sharps), "C#4");
+        assert_eq!(name(NoteNaming::Flats), "Db4");
+        assert_eq!(name(NoteNaming::Solfege), "Do#4");
+        assert_eq!(
+            frequency_to_note(c_sharp4, ReferencePitch::default())
+                .unwrap()
+                .note_name,
+            "C#4"
+        );
+    }
+
+    #[test]
+    fn test_transpose_shifts_name_only() {
+        let sharp_a4 = 440.0 * 2f32.powf(10.0 / 1200.0);
+        let config = NamingConfig {
+            transpose_semitones: 2,
+            ..NamingConfig::default()
+        };
+        let note = frequency_to_note_with(sharp_a4, &config).unwrap();
+        assert_eq!(note.note_name, "B4");
+        assert_eq!(note.octave, 4);
+        assert_eq!(note.midi_note, 69);
+        assert!((note.cents - 10.0).abs() < 0.01);
+        assert!((note.note_frequency - 440.0).abs() < 0.01);
+
+        // Crossing the octave boundary downward
+        let config = NamingConfig {
+            transpose_semitones: -10,
+            ..NamingConfig::default()
+        };
+        assert_eq!(
+            frequency_to_note_with(440.0, &config).unwrap().note_name,
+            "B3"
+        );
+    }
+
+    #[test]
+    fn test_note_context_around_a4() {
+        let context = note_context(443.0, 440.0).unwrap();
+        assert_eq!(context.below.note_name, "G#4");
+        assert_eq!(context.current.note_name, "A4");
+        assert_eq!(context.above.note_name, "A#4");
+        assert!((context.cents - 11.8).abs() < 0.1, "{:?}", context);
+        assert_eq!(context.cents, context.current.cents);
+        assert_eq!(context.above.cents, 0.0);
+        assert!((context.above.note_frequency - 466.16).abs() < 0.01);
+
+        // Ends of the MIDI range
+        let reference = ReferencePitch::default();
+        let lowest = note_context(reference.midi_to_frequency(0.0), 440.0).unwrap();
+        assert_eq!(lowest.below.note_name, "B-2");
+        assert_eq!(lowest.current.note_name, "C-1");
+        let highest = note_context(reference.midi_to_frequency(127.0), 440.0).unwrap();
+        assert_eq!(highest.above.note_name, "G#9");
+
+        assert!(note_context(-1.0, 440.0).is_none());
+    }
+
+    #[test]
+    fn test_stretched_target() {
+        let curve = railsback_stretch();
+        assert_eq!(curve.len(), 128);
+        assert_eq!(curve[69], 0.0);
+        assert!((curve[108] - 30.0).abs() < 1e-3 && (curve[21] + 30.0).abs() < 1e-3);
+
+        // An A7 tuned the way a piano technician would leave it
+        let stretched_a7 =
+            ReferencePitch::default().midi_to_frequency(105.0) * cents_to_ratio(curve[105]);
+        let tet = frequency_to_note(stretched_a7, ReferencePitch::default()).unwrap();
+        assert_eq!(tet.note_name, "A7");
+        assert!(tet.cents > 20.0, "{:?}", tet);
+
+        let config = NamingConfig {
+            cents_offsets: Some(curve),
+            ..NamingConfig::default()
+        };
+        let note = frequency_to_note_with(stretched_a7, &config).unwrap();
+        assert_eq!(note.note_name, "A7");
+        assert!(note.cents.abs() < 0.05, "{:?}", note);
+        assert!((note.note_frequency - stretched_a7).abs() < 0.05);
+
+        // The middle of the keyboard is left alone
+        let a4 = frequency_to_note_with(440.0, &config).unwrap();
+        assert!(a4.cents.abs() < 0.01);
+    }
+
+    #[test]
+    fn test_cents_between() {
+        assert!((cents_between(880.0, 440.0) - 1200.0).abs() < 1e-3);
+        assert_eq!(cents_between(440.0, 440.0), 0.0);
+        assert!((cents_between(660.0, 440.0) - 701.955).abs() < 0.01);
+        assert!((cents_between(440.0, 880.0) + 1200.0).abs() < 1e-3);
+        assert!(cents_between(-1.0, 440.0).is_nan());
+        assert!(cents_between(440.0, 0.0).is_nan());
+    }
+
+    #[test]
+    fn test_beat_frequency() {
+        let beat = beat_frequency(440.5, 440.0);
+        assert!((beat - 0.5).abs() < 1e-4, "{}", beat);
+        assert_eq!(beat_frequency(438.0, 440.0), 2.0);
+        assert_eq!(beat_frequency(440.0, 440.0), 0.0);
+        // Same cents error, twice the beat an octave up
+        let cents = cents_to_ratio(5.0);
+        let low = beat_frequency(220.0 * cents, 220.0);
+        assert!((beat_frequency(440.0 * cents, 440.0) / low - 2.0).abs() < 1e-3);
+        assert!(beat_frequency(-1.0, 440.0).is_nan());
+    }
+
+    #[test]
+    fn test_cents_ratio_conversion() {
+        assert!((cents_to_ratio(1200.0) - 2.0).abs() < 1e-6);
+        assert!((cents_to_ratio(-1200.0) - 0.5).abs() < 1e-6);
+        assert_eq!(cents_to_ratio(0.0), 1.0);
+        assert!((ratio_to_cents(2.0) - 1200.0).abs() < 1e-3);
+
+        let ratio = 1.2345f32;
+        assert!((cents_to_ratio(ratio_to_cents(ratio)) - ratio).abs() < 1e-5);
+        assert!(ratio_to_cents(0.0).is_nan());
+    }
+
+    #[test]
+    fn test_wavelength() {
+        assert!((wavelength_meters(440.0, 343.0) - 0.78).abs() < 0.001);
+        assert_eq!(wavelength_in_air(440.0), wavelength_meters(440.0, 343.0));
+        // Sound travels roughly four times faster in water
+        assert!((wavelength_meters(440.0, 1480.0) - 3.364).abs() < 0.001);
+        assert!(wavelength_meters(0.0, 343.0).is_nan());
+        assert!(wavelength_in_air(-1.0).is_nan());
+    }
+
+    #[test]
+    fn test_correction_ratio() {
+        let flat = 440.0 * 2f32.powf(-20.0 / 1200.0);
+        let ratio = correction_ratio(flat, 440.0);
+        assert!((ratio - 2f32.powf(20.0 / 1200.0)).abs() < 1e-5, "{}", ratio);
+        assert!(ratio > 1.0);
+        assert_eq!(correction_ratio(-1.0, 440.0), 1.0);
+    }
+
+    #[test]
+    fn test_snap_to_note() {
+        assert_eq!(snap_to_note(445.0, 440.0), 440.0);
+        assert_eq!(snap_to_note(435.0, 440.0), 440.0);
+        assert_eq!(snap_to_note(445.0, 442.0), 442.0);
+        let c4 = snap_to_note(263.0, 440.0);
+        assert!((c4 - 261.6256).abs() < 1e-3, "{}", c4);
+        assert_eq!(snap_to_note(0.0, 440.0), 0.0);
+        assert_eq!(snap_to_note(-1.0, 440.0), 0.0);
+    }
+
+    #[test]
+    fn test_average_detuning() {
+        let reference = ReferencePitch::default();
+        let sharp = |midi: f32| reference.midi_to_frequency(midi + 0.1);
+        let notes = [sharp(57.0), sharp(64.0), -1.0, sharp(69.0), sharp(76.0)];
+        let detuning = average_detuning(&notes, 440.0);
+        assert!((detuning - 10.0).abs() < 0.05, "{}", detuning);
+        let suggested_a4 = 440.0 * cents_to_ratio(detuning);
+        assert!((suggested_a4 - 442.55).abs() < 0.05, "{}", suggested_a4);
+
+        assert!(average_detuning(&[-1.0], 440.0).is_nan());
+        assert!(average_detuning(&[], 440.0).is_nan());
+    }
+
+    #[test]
+    fn test_suggest_a4() {
+        // An ensemble tuned to A4 = 443
+        let reference = ReferencePitch::a4(443.0);
+        let notes: Vec<f32> = [55.0, 62.0, 69.0, 71.0, 74.0]
+            .iter()
+            .map(|&midi| reference.midi_to_frequency(midi))
+            .collect();
+        let suggested = suggest_a4(&notes, 440.0);
+        assert!(suggested > 440.0);
+        assert!((suggested - 443.0).abs() < 0.01, "{}", suggested);
+        assert_eq!(suggest_a4(&[-1.0], 440.0), 440.0);
+    }
+
+    #[test]
+    fn test_nearest_scale_degree_whole_tone() {
+        let whole_tone = [0.0, 200.0, 400.0, 600.0, 800.0, 1000.0];
+        let tonic = 261.63;
+        let at = |cents: f32| tonic * cents_to_ratio(cents);
+
+        let e = nearest_scale_degree(at(430.0), tonic, &whole_tone);
+        assert_eq!(e.degree, 2);
+        assert!((e.cents - 30.0).abs() < 0.01, "{:?}", e);
+
+        // An octave up, flat of the fourth degree
+        let f_sharp = nearest_scale_degree(at(1200.0 + 560.0), tonic, &whole_tone);
+        assert_eq!(f_sharp.degree, 3);
+        assert!((f_sharp.cents + 40.0).abs() < 0.01, "{:?}", f_sharp);
+
+        // Just below the octave wraps to the tonic
+        let high = nearest_scale_degree(at(1130.0), tonic, &whole_tone);
+        assert_eq!(high.degree, 0);
+        assert!((high.cents + 70.0).abs() < 0.01, "{:?}", high);
+
+        assert!(nearest_scale_degree(-1.0, tonic, &whole_tone)
+            .cents
+            .is_nan());
+        assert!(nearest_scale_degree(440.0, tonic, &[]).cents.is_nan());
+    }
+
+    #[test]
+    fn test_invalid_frequency() {
+        assert!(frequency_to_note(-1.0, ReferencePitch::default()).is_none());
+        assert!(frequency_to_note(0.0, ReferencePitch::default()).is_none());
+    }
+
+    #[test]
+    fn test_24_edo_quarter_tone() {
+        let quarter_sharp = 440.0 * cents_to_ratio(50.0);
+        let matched = frequency_to_edo_degree(quarter_sharp, 440.0, 24);
+        assert_eq!(matched.degree, 1);
+        assert!(matched.cents.abs() < 0.01, "{}", matched.cents);
+        // Halfway between two 12-TET notes, but right on a 24-EDO step
+        let twelve = frequency_to_note(quarter_sharp, ReferencePitch::default()).unwrap();
+        assert!((twelve.cents.abs() - 50.0).abs() < 0.01);
+
+        // Degrees wrap per octave, and steps below A count down from 23
+        assert_eq!(
+            frequency_to_edo_degree(880.0 * cents_to_ratio(50.0), 440.0, 24).degree,
+            1
+        );
+        assert_eq!(
+            frequency_to_edo_degree(440.0 * cents_to_ratio(-50.0), 440.0, 24).degree,
+            23
+        );
+        assert!(frequency_to_edo_degree(440.0, 440.0, 0).cents.is_nan());
+
+        let config = NamingConfig {
+            temperament: Temperament::Edo(24),
+            ..NamingConfig::default()
+        };
+        let note = frequency_to_note_with(quarter_sharp, &config).unwrap();
+        assert_eq!(note.note_name, "A4+1");
+        assert!(note.cents.abs() < 0.01);
+        assert!((note.note_frequency - quarter_sharp).abs() < 0.01);
+        let note = frequency_to_note_with(439.0 * cents_to_ratio(-1200.0), &config).unwrap();
+        assert_eq!(note.note_name, "A3");
+        assert!((note.cents - cents_between(439.0, 440.0)).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_parse_note_name() {
+        assert_eq!(parse_note_name("A4"), Ok(69));
+        assert_eq!(parse_note_name("c4"), Ok(60));
+        assert_eq!(parse_note_name(" F#3 "), Ok(54));
+        assert_eq!(parse_note_name("Bb1"), Ok(34));
+        assert_eq!(parse_note_name("Cb4"), Ok(59));
+        assert_eq!(parse_note_name("E##2"), Ok(42));
+        assert_eq!(parse_note_name("C-1"), Ok(0));
+        assert_eq!(
+            parse_note_name("H2"),
+            Err(ParseError::InvalidLetter("H2".to_string()))
+        );
+        assert_eq!(
+            parse_note_name(""),
+            Err(ParseError::InvalidLetter(String::new()))
+        );
+        assert_eq!(
+            parse_note_name("G#"),
+            Err(ParseError::InvalidOctave("G#".to_string()))
+        );
+        assert!(parse_note_name("D3x").is_err());
+        assert_eq!(parse_note_name("G9"), Ok(127));
+        for name in ["C10", "B-2", "C2147483647", "C-2147483648"] {
+            assert_eq!(
+                parse_note_name(name),
+                Err(ParseError::InvalidOctave(name.to_string()))
+            );
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use wasm_bindgen::prelude::*;
+
+use crate::config::DetectorConfig;
+use crate::yin::{cumulative_mean_normalized_difference, difference_function, result_from_cmndf};
+use crate::{calculate_rms, RMS_THRESHOLD};
+
+/// Scratch buffers shared by many detections
+/// Apps running many detectors lend the difference and CMNDF buffers from one
+/// pool instead of giving each detector its own, so total scratch memory is
+/// bounded by the largest frames in flight rather than growing per detector.
+/// Buffers are returned to the pool after every detection and only grow when
+/// a longer frame than any seen before arrives
+#[wasm_bindgen]
+#[derive(Debug, Default)]
+pub struct ScratchPool {
+    free: Vec<Vec<f32>>,
+    allocations: usize,
+}
+
+#[wasm_bindgen]
+impl ScratchPool {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many times a buffer had to be created or grown
+    pub fn allocations(&self) -> usize {
+        self.allocations
+    }
+
+    /// Buffers currently waiting in the pool
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+
+    /// Drop every pooled buffer, releasing its memory
+    pub fn clear(&mut self) {
+        self.free.clear();
+    }
+}
+
+impl ScratchPool {
+    /// Lend a zeroed buffer of `len` samples
+    /// Prefers the pooled buffer with the most capacity, so growth only
+    /// happens when none is large enough
+    pub fn take(&mut self, len: usize) -> Vec<f32> {
+        let largest = (0..self.free.len()).max_by_key(|&i| self.free[i].capacity());
+        let mut buffer = match largest {
+            Some(i) => self.free.swap_remove(i),
+            None => Vec::new(),
+        };
+        if buffer.capacity() < len {
+            self.allocations += 1;
+        }
+        buffer.clear();
+        buffer.resize(len, 0.0);
+        buffer
+    }
+
+    /// Return a buffer lent by `take`
+    pub fn give(&mut self, buffer: Vec<f32>) {
+        self.free.push(buffer);
+    }
+}
+
+/// YIN pitch detection borrowing its scratch buffers from `pool`
+/// Same result as `detect_pitch`
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_pooled(pool: &mut ScratchPool, samples: &[f32], sample_rate: f32) -> f32 {
+    let buffer_size = samples.len();
+    if buffer_size < 2 || calculate_rms(samples) < RMS_THRESHOLD {
+        return -1.0;
+    }
+
+    let half_buffer_size = buffer_size / 2;
+    let mut difference = pool.take(half_buffer_size);
+    let mut cmndf = pool.take(half_buffer_size);
+    difference_function(samples, &mut difference);
+    cumulative_mean_normalized_difference(&difference, &mut cmndf);
+    let frequency = result_from_cmndf(
+        &cmndf,
+        half_buffer_size,
+        sample_rate,
+        &DetectorConfig::default(),
+    )
+    .frequency;
+    pool.give(difference);
+    pool.give(cmndf);
+    frequency
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect_pitch;
+    use crate::testutil::{generate_tone, Waveform};
+
+    #[test]
+    fn test_pooled_detection_stops_allocating_after_warm_up() {
+        let sample_rate = 44100.0;
+        let mut pool = ScratchPool::new();
+        let frames: Vec<Vec<f32>> = [110.0, 220.0, 440.0, 880.0]
+            .iter()
+            .map(|&f| generate_tone(f, sample_rate, 2048, Waveform::Saw))
+            .collect();
+
+        for frame in &frames {
+            assert_eq!(
+                detect_pitch_pooled(&mut pool, frame, sample_rate),
+                detect_pitch(frame, sample_rate)
+            );
+        }
+        let warm = pool.allocations();
+        assert_eq!(warm, 2);
+        for _ in 0..50 {
+            for frame in &frames {
+                detect_pitch_pooled(&mut pool, frame, sample_rate);
+                // Shorter frames fit in the buffers already pooled
+                detect_pitch_pooled(&mut pool, &frame[..1024], sample_rate);
+            }
+        }
+        assert_eq!(pool.allocations(), warm);
+        assert_eq!(pool.available(), 2);
+
+        assert_eq!(
+            detect_pitch_pooled(&mut pool, &[0.0; 2048], sample_rate),
+            -1.0
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use wasm_bindgen::prelude::*;
+
+use crate::note::cents_between;
+use crate::yin::{
+    absolute_threshold, gated_cmndf, lag_difference, parabola_vertex, parabolic_interpolation,
+};
+use crate::{calculate_rms, MAX_FREQUENCY, MIN_FREQUENCY, RMS_THRESHOLD};
+
+/// Number of thresholds in the pYIN sweep, evenly spaced over (0, 1)
+const THRESHOLD_STEPS: usize = 100;
+
+/// Candidates within this many cents of each other vote for the same pitch
+const VOTE_TOLERANCE_CENTS: f32 = 50.0;
+
+/// Beta(2, 18) prior over thresholds (mean 0.1), as in the pYIN paper
+const PRIOR_ALPHA: f32 = 2.0;
+const PRIOR_BETA: f32 = 18.0;
+
+/// A pitch hypothesis with its probability
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PitchCandidate {
+    /// Frequency in Hz
+    pub frequency: f32,
+    /// Probability in 0.0..=1.0
+    pub probability: f32,
+}
+
+/// Probabilistic YIN (pYIN) pitch candidates
+/// Instead of one absolute threshold, sweeps a Beta-distributed range of
+/// thresholds; each candidate's probability is the prior mass of thresholds
+/// that selected it. Probabilities sum to at most 1.0, the remainder being
+/// the chance the frame is unvoiced.
+/// Returns candidates sorted by descending probability (empty if unvoiced)
+#[wasm_bindgen]
+pub fn detect_pitch_pyin(samples: &[f32], sample_rate: f32) -> Vec<PitchCandidate> {
+    let cmndf = match gated_cmndf(samples) {
+        Some(c) => c,
+        None => return Vec::new(),
+    };
+
+    let weights = threshold_prior();
+    let mut votes: Vec<(usize, f32)> = Vec::new();
+    for (step, weight) in weights.iter().enumerate() {
+        let threshold = (step as f32 + 0.5) / THRESHOLD_STEPS as f32;
+        let tau = match absolute_threshold(&cmndf, threshold) {
+            Some(t) => t,
+            None => continue,
+        };
+        match votes.iter_mut().find(|(t, _)| *t == tau) {
+            Some((_, mass)) => *mass += weight,
+            None => votes.push((tau, *weight)),
+        }
+    }
+
+    let mut candidates: Vec<PitchCandidate> = votes
+        .into_iter()
+        .map(|(tau, probability)| PitchCandidate {
+            frequency: sample_rate / parabolic_interpolation(&cmndf, tau),
+            probability,
+        })
+        .filter(|c| (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&c.frequency))
+        .collect();
+    candidates.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+    candidates
+}
+
+/// Every CMNDF trough dipping below `threshold`, as pitch candidates
+/// A candidate's probability is one minus its interpolated trough depth (its
+/// clarity), so octave errors and subharmonics appear alongside the pitch
+/// YIN would report instead of being discarded. A candidate within
+/// `min_separation_cents` of a more probable one is dropped, so ripples in one
+/// broad trough report once; 0.0 keeps every trough.
+/// Returns candidates sorted by descending probability (empty if unvoiced)
+#[wasm_bindgen]
+pub fn detect_pitch_candidates(
+    samples: &[f32],
+    sample_rate: f32,
+    threshold: f32,
+    min_separation_cents: f32,
+) -> Vec<PitchCandidate> {
+    let mut raw = Vec::new();
+    for_each_candidate(samples, sample_rate, threshold, |c| raw.push(c));
+    let mut kept = raw.clone();
+    let mut count = 0;
+    for candidate in raw {
+        count = keep_candidate(&mut kept, count, candidate, min_separation_cents);
+    }
+    kept.truncate(count);
+    kept
+}
+
+/// [`detect_pitch_candidates`] into a caller-provided array, never allocating
+/// The CMNDF is computed lag by lag and only the last three values are kept.
+/// When there are more candidates than slots the most probable ones are kept.
+/// Returns the number of slots filled, which are sorted by descending probability
+pub fn detect_pitch_candidates_into(
+    samples: &[f32],
+    sample_rate: f32,
+    threshold: f32,
+    min_separation_cents: f32,
+    out: &mut [PitchCandidate],
+) -> usize {
+    let mut count = 0;
+    for_each_candidate(samples, sample_rate, threshold, |candidate| {
+        count = keep_candidate(out, count, candidate, min_separation_cents);
+    });
+    count
+}
+
+/// Offer `candidate` to the first `count` slots of `kept`, which stay sorted
+/// by descending probability with no two within `min_separation_cents`
+/// Returns the new count
+fn keep_candidate(
+    kept: &mut [PitchCandidate],
+    mut count: usize,
+    candidate: PitchCandidate,
+    min_separation_cents: f32,
+) -> usize {
+    let near = |other: &PitchCandidate| {
+        cents_between(other.frequency, candidate.frequency).abs() < min_separation_cents
+    };
+    if kept[..count]
+        .iter()
+        .any(|other| near(other) && other.probability >= candidate.probability)
+    {
+        return count;
+    }
+    // Whatever is left nearby is weaker and gives way to the new candidate
+    let mut retained = 0;
+    for i in 0..count {
+        if !near(&kept[i]) {
+            kept[retained] = kept[i];
+            retained += 1;
+        }
+    }
+    count = retained;
+
+    if count < kept.len() {
+        count += 1;
+    } else if kept
+        .last()
+        .is_none_or(|weakest| weakest.probability >= candidate.probability)
+    {
+        return count;
+    }
+    // Insertion sort into the filled prefix
+    let mut slot = count - 1;
+    while slot > 0 && kept[slot - 1].probability < candidate.probability {
+        kept[slot] = kept[slot - 1];
+        slot -= 1;
+    }
+    kept[slot] = candidate;
+    count
+}
+
+/// Streams the candidates of `detect_pitch_candidates` in lag order
+fn for_each_candidate(
+    samples: &[f32],
+    sample_rate: f32,
+    threshold: f32,
+    mut emit: impl FnMut(PitchCandidate),
+) {
+    if samples.len() < 2 || calculate_rms(samples) < RMS_THRESHOLD {
+        return;
+    }
+    let window = samples.len() / 2;
+    // CMNDF at lags tau - 2, tau - 1, tau
+    let mut recent = [1.0f32; 3];
+    let mut running_sum = 0.0f32;
+    for tau in 1..window {
+        let difference = lag_difference(samples, window, tau);
+        running_sum += difference;
+        recent = [
+            recent[1],
+            recent[2],
+            if running_sum > 0.0 {
+                difference * tau as f32 / running_sum
+            } else {
+                1.0
+            },
+        ];
+        // Judge lag tau - 1 now that both its neighbours are known
+        if tau < 3 || recent[1] >= recent[0] || recent[1] > recent[2] {
+            continue;
+        }
+        let (offset, depth) = parabola_vertex(&recent, 1);
+        if depth >= threshold {
+            continue;
+        }
+        let frequency = sample_rate / ((tau - 1) as f32 + offset);
+        if (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+            emit(PitchCandidate {
+                frequency,
+                probability: (1.0 - depth).clamp(0.0, 1.0),
+            });
+        }
+    }
+}
+
+/// The pitch best supported by candidates from several recent frames
+/// Every candidate gathers the probability of all candidates (from any frame)
+/// within a quarter tone of it; the best-supported group wins and its
+/// probability-weighted median frequency is returned. A single frame's octave
+/// error is outvoted by the frames around it.
+/// Returns -1.0 if there are no candidates
+pub fn weighted_pitch(candidates_over_time: &[Vec<PitchCandidate>]) -> f32 {
+    let pool: Vec<PitchCandidate> = candidates_over_time.iter().flatten().copied().collect();
+    let near = |a: &PitchCandidate, b: &PitchCandidate| {
+        cents_between(a.frequency, b.frequency).abs() <= VOTE_TOLERANCE_CENTS
+    };
+    let support = |center: &PitchCandidate| -> f32 {
+        pool.iter()
+            .filter(|c| near(c, center))
+            .map(|c| c.probability)
+            .sum()
+    };
+
+    let winner = match pool.iter().max_by(|a, b| support(a).total_cmp(&support(b))) {
+        Some(w) => w,
+        None => return -1.0,
+    };
+
+    let mut group: Vec<PitchCandidate> = pool.iter().filter(|c| near(c, winner)).copied().collect();
+    group.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+    let half = group.iter().map(|c| c.probability).sum::<f32>() / 2.0;
+    let mut cumulative = 0.0;
+    for candidate in &group {
+        cumulative += candidate.probability;
+        if cumulative >= half {
+            return candidate.frequency;
+        }
+    }
+    winner.frequency
+}
+
+/// Discretized threshold prior, normalized to sum to 1.0
+fn threshold_prior() -> Vec<f32> {
+    let density: Vec<f32> = (0..THRESHOLD_STEPS)
+        .map(|step| {
+            let x = (step as f32 + 0.5) / THRESHOLD_STEPS as f32;
+            x.powf(PRIOR_ALPHA - 1.0) * (1.0 - x).powf(PRIOR_BETA - 1.0)
+        })
+        .collect();
+    let total: f32 = density.iter().sum();
+    density.into_iter().map(|d| d / total).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect_pitch;
+    use crate::testutil::{add_noise, generate_tone, NoiseKind, Waveform};
+
+    #[test]
+    fn test_clean_tone_single_confident_candidate() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(440.0, sample_rate, 2048, Waveform::Sine);
+        let candidates = detect_pitch_pyin(&samples, sample_rate);
+        assert!(!candidates.is_empty());
+        assert!((candidates[0].frequency - 440.0).abs() < 2.0);
+        assert!(candidates[0].probability > 0.9, "{:?}", candidates);
+        let total: f32 = candidates.iter().map(|c| c.probability).sum();
+        assert!(total <= 1.0 + 1e-4);
+    }
+
+    #[test]
+    fn test_noisy_tone_more_accurate_than_yin() {
+        let sample_rate = 44100.0;
+        let mut samples = generate_tone(440.0, sample_rate, 2048, Waveform::Sine);
+        add_noise(&mut samples, 5.0, NoiseKind::White);
+
+        // Single-threshold YIN gives up on this frame...
+        let yin = detect_pitch(&samples, sample_rate);
+        let yin_error = if yin > 0.0 {
+            (yin - 440.0).abs()
+        } else {
+            f32::INFINITY
+        };
+
+        // ...while the sweep still finds the tone
+        let candidates = detect_pitch_pyin(&samples, sample_rate);
+        assert!(!candidates.is_empty());
+        let pyin_error = (candidates[0].frequency - 440.0).abs();
+        assert!(pyin_error < 3.0, "{:?}", candidates);
+        assert!(pyin_error < yin_error);
+    }
+
+    #[test]
+    fn test_weighted_pitch_outvotes_octave_error() {
+        let candidate = |frequency, probability| PitchCandidate {
+            frequency,
+            probability,
+        };
+        let frames = vec![
+            vec![candidate(110.2, 0.9), candidate(220.0, 0.05)],
+            vec![candidate(109.8, 0.85)],
+            // Errant frame confidently on the octave
+            vec![candidate(220.4, 0.95), candidate(110.0, 0.05)],
+        ];
+        let pitch = weighted_pitch(&frames);
+        assert!((pitch - 110.0).abs() < 0.5, "got {}", pitch);
+
+        assert_eq!(weighted_pitch(&[]), -1.0);
+        assert_eq!(weighted_pitch(&[Vec::new()]), -1.0);
+    }
+
+    #[test]
+    fn test_silence_has_no_candidates() {
+        assert!(detect_pitch_pyin(&[0.0; 2048], 44100.0).is_empty());
+        assert!(detect_pitch_candidates(&[0.0; 2048], 44100.0, 0.5, 0.0).is_empty());
+    }
+
+    #[test]
+    fn test_candidates_include_periodic_troughs() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(440.0, sample_rate, 2048, Waveform::Saw);
+        let candidates = detect_pitch_candidates(&samples, sample_rate, 0.5, 0.0);
+        // The period and its multiples (subharmonics) all dip deeply
+        assert!(candidates.len() >= 3, "{:?}", candidates);
+        assert!(candidates.iter().any(|c| (c.frequency - 440.0).abs() < 2.0));
+        assert!(candidates.iter().any(|c| (c.frequency - 220.0).abs() < 2.0));
+        assert!(candidates
+            .windows(2)
+            .all(|w| w[0].probability >= w[1].probability));
+    }
+
+    #[test]
+    fn test_candidates_into_fixed_capacity() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(440.0, sample_rate, 2048, Waveform::Saw);
+        let all = detect_pitch_candidates(&samples, sample_rate, 0.5, 0.0);
+
+        let empty = PitchCandidate {
+            frequency: 0.0,
+            probability: 0.0,
+        };
+        let mut roomy = [empty; 64];
+        let count = detect_pitch_candidates_into(&samples, sample_rate, 0.5, 0.0, &mut roomy);
+        assert_eq!(count, all.len());
+        assert_eq!(&roomy[..count], &all[..]);
+
+        // Fewer slots than candidates keeps the most probable ones
+        let mut slots = [empty; 2];
+        let count = detect_pitch_candidates_into(&samples, sample_rate, 0.5, 0.0, &mut slots);
+        assert_eq!(count, 2);
+        assert_eq!(&slots[..], &all[..2]);
+
+        assert_eq!(
+            detect_pitch_candidates_into(&samples, sample_rate, 0.5, 0.0, &mut []),
+            0
+        );
+    }
+
+    #[test]
+    fn test_min_separation_merges_broad_trough() {
+        let sample_rate = 44100.0;
+        // A quiet inharmonic partial ripples the broad trough of a low sine
+        let low = generate_tone(100.0, sample_rate, 4096, Waveform::Sine);
+        let ripple = generate_tone(2100.3, sample_rate, 4096, Waveform::Sine);
+        let samples: Vec<f32> = low.iter().zip(&ripple).map(|(l, r)| l + 0.2 * r).collect();
+
+        let all = detect_pitch_candidates(&samples, sample_rate, 0.1, 0.0);
+        assert_eq!(all.len(), 3, "{:?}", all);
+
+        let merged = detect_pitch_candidates(&samples, sample_rate, 0.1, 150.0);
+        assert_eq!(merged.len(), 1, "{:?}", merged);
+        assert_eq!(merged[0], all[0]);
+        assert!((merged[0].frequency - 100.0).abs() < 1.0);
+
+        let empty = PitchCandidate {
+            frequency: 0.0,
+            probability: 0.0,
+        };
+        let mut slots = [empty; 4];
+        let count = detect_pitch_candidates_into(&samples, sample_rate, 0.1, 150.0, &mut slots);
+        assert_eq!(&slots[..count], &merged[..]);
+    }
+}
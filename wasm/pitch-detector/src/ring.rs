@@ -0,0 +1,247 @@
+use wasm_bindgen::prelude::*;
+
+use crate::calculate_rms;
+use crate::config::DetectorConfig;
+use crate::yin::{difference_function, result_from_difference, PitchResult};
+
+/// Incremental updates between full recomputations of the difference
+/// function, bounding the rounding drift of the running sums
+const REFRESH_INTERVAL: usize = 64;
+
+/// Streaming YIN detector over a sliding frame
+/// Each `push` shifts new samples into a fixed-size frame and updates the
+/// difference function in place: when the frame moves by `h` samples, each
+/// lag loses the `h` terms that slid out of the compared window and gains the
+/// `h` that slid in, so a small hop costs `O(h * lags)` instead of the
+/// `O(frame / 2 * lags)` of a full recomputation
+#[wasm_bindgen]
+pub struct RingDetector {
+    sample_rate: f32,
+    config: DetectorConfig,
+    frame: Vec<f32>,
+    /// Samples received so far, saturating at the frame size
+    filled: usize,
+    difference: Vec<f32>,
+    /// Whether `difference` holds the difference function of `frame`
+    difference_valid: bool,
+    updates_since_refresh: usize,
+    last_result: PitchResult,
+}
+
+#[wasm_bindgen]
+impl RingDetector {
+    /// `frame_size` samples are analyzed per estimate, as with `detect_pitch`
+    #[wasm_bindgen(constructor)]
+    pub fn new(frame_size: usize, sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            config: DetectorConfig::default(),
+            frame: vec![0.0; frame_size],
+            filled: 0,
+            difference: vec![0.0; frame_size / 2],
+            difference_valid: false,
+            updates_since_refresh: 0,
+            last_result: PitchResult::UNVOICED,
+        }
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.frame.len()
+    }
+
+    /// Shift `samples` into the frame and detect the pitch of the updated frame
+    /// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+    /// (including until a full frame has been received)
+    pub fn push(&mut self, samples: &[f32]) -> f32 {
+        self.push_samples(samples);
+        self.last_result = self.detect();
+        self.last_result.frequency
+    }
+
+    /// Full result of the most recent `push`
+    pub fn result(&self) -> PitchResult {
+        self.last_result
+    }
+
+    /// Switch to frames of `frame_size` samples, forgetting all received
+    /// samples
+    pub fn resize(&mut self, frame_size: usize) {
+        self.frame = vec![0.0; frame_size];
+        self.difference = vec![0.0; frame_size / 2];
+        self.reset();
+    }
+
+    /// Forget all received samples
+    pub fn reset(&mut self) {
+        self.frame.fill(0.0);
+        self.filled = 0;
+        self.difference_valid = false;
+        self.last_result = PitchResult::UNVOICED;
+    }
+}
+
+impl RingDetector {
+    pub fn with_config(frame_size: usize, sample_rate: f32, config: DetectorConfig) -> Self {
+        Self {
+            config,
+            ..Self::new(frame_size, sample_rate)
+        }
+    }
+
+    fn push_samples(&mut self, samples: &[f32]) {
+        let size = self.frame.len();
+        let hop = samples.len();
+        if hop == 0 || size == 0 {
+            return;
+        }
+        if hop >= size {
+            self.frame.copy_from_slice(&samples[hop - size..]);
+            self.filled = size;
+            self.difference_valid = false;
+            return;
+        }
+
+        // Only a frame that was complete before and after the shift has a
+        // difference function worth updating, and only while the hop stays
+        // inside the compared window; past that no term survives the shift
+        let window = self.difference.len();
+        let incremental = self.difference_valid
+            && hop < window
+            && self.filled == size
+            && self.updates_since_refresh < REFRESH_INTERVAL;
+        if incremental {
+            self.update_terms(0..hop, -1.0);
+        }
+        self.frame.copy_within(hop.., 0);
+        self.frame[size - hop..].copy_from_slice(samples);
+        self.filled = (self.filled + hop).min(size);
+        if incremental {
+            self.update_terms(window.saturating_sub(hop)..window, 1.0);
+            self.updates_since_refresh += 1;
+        } else {
+            self.difference_valid = false;
+        }
+    }
+
+    /// Add `sign` times the terms `i` in `range` of every lag's sum
+    fn update_terms(&mut self, range: std::ops::Range<usize>, sign: f32) {
+        let frame = &self.frame;
+        for (tau, value) in self.difference.iter_mut().enumerate() {
+            let terms: f32 = range
+                .clone()
+                .map(|i| {
+                    let delta = frame[i] - frame[i + tau];
+                    delta * delta
+                })
+                .sum();
+            *value = (*value + sign * terms).max(0.0);
+        }
+    }
+
+    fn detect(&mut self) -> PitchResult {
+        let size = self.frame.len();
+        if size < 2 || self.filled < size || calculate_rms(&self.frame) < self.config.rms_threshold
+        {
+            return PitchResult::UNVOICED;
+        }
+        if !self.difference_valid {
+            difference_function(self.frame.as_slice(), &mut self.difference);
+            self.difference_valid = true;
+            self.updates_since_refresh = 0;
+        }
+        result_from_difference(
+            &self.difference,
+            self.difference.len(),
+            self.sample_rate,
+            &self.config,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+    use crate::{detect_pitch_full, DEFAULT_THRESHOLD};
+
+    #[test]
+    fn test_incremental_matches_full_recomputation() {
+        let sample_rate = 44100.0;
+        let frame_size = 2048;
+        let hop = 128;
+        // A glide, so every hop sees a different pitch
+        let mut signal = generate_tone(196.0, sample_rate, 4096, Waveform::Saw);
+        signal.extend(generate_tone(207.65, sample_rate, 4096, Waveform::Saw));
+
+        let mut ring = RingDetector::new(frame_size, sample_rate);
+        ring.push(&signal[..frame_size]);
+        let mut end = frame_size;
+        while end + hop <= signal.len() {
+            ring.push(&signal[end..end + hop]);
+            end += hop;
+
+            let frame = &signal[end - frame_size..end];
+            let mut full = vec![0.0; frame_size / 2];
+            difference_function(frame, &mut full);
+            // Rounding drift is judged against the scale of the whole function
+            let scale = full.iter().fold(0.0f32, |a, &b| a.max(b));
+            for (incremental, exact) in ring.difference.iter().zip(&full) {
+                assert!(
+                    (incremental - exact).abs() <= 1e-4 * scale,
+                    "{} vs {}",
+                    incremental,
+                    exact
+                );
+            }
+
+            let expected = detect_pitch_full(frame, sample_rate, DEFAULT_THRESHOLD);
+            let result = ring.result();
+            assert_eq!(result.best_tau, expected.best_tau);
+            assert!((result.frequency - expected.frequency).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_hop_beyond_window_recomputes() {
+        let sample_rate = 44100.0;
+        let frame_size = 2048;
+        let signal = generate_tone(246.94, sample_rate, 8192, Waveform::Saw);
+        let mut ring = RingDetector::new(frame_size, sample_rate);
+        ring.push(&signal[..frame_size]);
+        // Between half the frame and the whole frame
+        let mut end = frame_size;
+        for hop in [1500, 1024, 1025, 2047] {
+            ring.push(&signal[end..end + hop]);
+            end += hop;
+            let frame = &signal[end - frame_size..end];
+            assert_eq!(
+                ring.result().frequency,
+                crate::detect_pitch(frame, sample_rate)
+            );
+        }
+    }
+
+    #[test]
+    fn test_unvoiced_until_frame_filled() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(440.0, sample_rate, 2048, Waveform::Sine);
+        let mut ring = RingDetector::new(2048, sample_rate);
+        assert_eq!(ring.push(&samples[..1024]), -1.0);
+        assert!((ring.push(&samples[1024..]) - 440.0).abs() < 2.0);
+
+        ring.reset();
+        assert_eq!(ring.push(&samples[..512]), -1.0);
+        // A push longer than the frame replaces it outright
+        let long = generate_tone(330.0, sample_rate, 5000, Waveform::Sine);
+        assert!((ring.push(&long) - 330.0).abs() < 2.0);
+
+        ring.resize(1024);
+        assert_eq!(ring.frame_size(), 1024);
+        assert_eq!(ring.result(), PitchResult::UNVOICED);
+        assert_eq!(ring.push(&samples[..512]), -1.0);
+        assert!((ring.push(&samples[512..1024]) - 440.0).abs() < 2.0);
+        ring.resize(4096);
+        let low = generate_tone(82.41, sample_rate, 4096, Waveform::Sine);
+        assert!((ring.push(&low) - 82.41).abs() < 0.5);
+    }
+}
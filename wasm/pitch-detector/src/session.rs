@@ -0,0 +1,502 @@
+use wasm_bindgen::prelude::*;
+
+use crate::config::DetectorConfig;
+use crate::consensus::detect_pitch_autocorrelation;
+use crate::yin::{detect_full_source, full_lag_range_cfg, PitchResult};
+
+/// Sample rate a session assumes unless told otherwise
+const DEFAULT_SESSION_SAMPLE_RATE: f32 = 44100.0;
+
+/// Estimator a [`DetectionSession`] runs
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    /// Standard YIN over a `len / 2` window
+    #[default]
+    Yin,
+    /// YIN comparing every available pair at each lag, see
+    /// `detect_pitch_full_lag_range`
+    YinFullLagRange,
+    /// Autocorrelation peak picking; only `frequency` and `tau` of the result
+    /// are filled in, and the frequency range is applied after detection
+    Autocorrelation,
+}
+
+/// Detection settings fixed once and reused for many buffers
+/// Offline tools analyzing many files with the same parameters build one
+/// session instead of threading every parameter through each call
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectionSession {
+    sample_rate: f32,
+    config: DetectorConfig,
+    window: Option<usize>,
+    algorithm: Algorithm,
+}
+
+#[wasm_bindgen]
+impl DetectionSession {
+    /// Build a session from a plain JS object
+    /// `{ sampleRate, minFreq, maxFreq, threshold, algorithm }`, every field
+    /// optional; `algorithm` is one of "yin", "yinFullLagRange" or
+    /// "autocorrelation". Unknown fields and out-of-range values throw an
+    /// error naming the field
+    #[cfg(feature = "serde")]
+    pub fn from_js(config: &JsValue) -> Result<DetectionSession, JsError> {
+        let json = js_sys::JSON::stringify(config)
+            .ok()
+            .and_then(|json| json.as_string())
+            .ok_or_else(|| JsError::new("session config must be a plain object"))?;
+        session_from_json(&json).map_err(|message| JsError::new(&message))
+    }
+
+    /// Detect the pitch of one buffer with the session's settings
+    /// With a window set, only the last `window` samples are analyzed
+    pub fn analyze(&self, samples: &[f32]) -> PitchResult {
+        let samples = match self.window {
+            Some(window) if window < samples.len() => &samples[samples.len() - window..],
+            _ => samples,
+        };
+        match self.algorithm {
+            Algorithm::Yin => detect_full_source(samples, self.sample_rate, &self.config),
+            Algorithm::YinFullLagRange => {
+                full_lag_range_cfg(samples, self.sample_rate, &self.config)
+            }
+            Algorithm::Autocorrelation => {
+                let detected = detect_pitch_autocorrelation(samples, self.sample_rate);
+                let frequency = match self.config.apply_range(detected) {
+                    Some(f) if detected > 0.0 => f,
+                    _ => return PitchResult::UNVOICED,
+                };
+                PitchResult {
+                    frequency,
+                    tau: self.sample_rate / frequency,
+                    ..PitchResult::UNVOICED
+                }
+            }
+        }
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn window(&self) -> Option<usize> {
+        self.window
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+}
+
+impl DetectionSession {
+    pub fn builder() -> DetectionSessionBuilder {
+        DetectionSessionBuilder::default()
+    }
+
+    pub fn config(&self) -> &DetectorConfig {
+        &self.config
+    }
+}
+
+/// Fields accepted by `DetectionSession::from_js`
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SessionConfig {
+    sample_rate: Option<f32>,
+    min_freq: Option<f32>,
+    max_freq: Option<f32>,
+    threshold: Option<f32>,
+    algorithm: Option<String>,
+}
+
+/// Parse and validate a JSON session config
+/// Returns a message naming the offending field on bad input
+#[cfg(feature = "serde")]
+fn session_from_json(json: &str) -> Result<DetectionSession, String> {
+    let fields: SessionConfig =
+        serde_json::from_str(json).map_err(|e| format!("invalid session config: {}", e))?;
+    let mut builder = DetectionSession::builder();
+
+    if let Some(sample_rate) = fields.sample_rate {
+        if !(sample_rate.is_finite() && sample_rate > 0.0) {
+            return Err(format!("sampleRate must be positive, got {}", sample_rate));
+        }
+        builder = builder.sample_rate(sample_rate);
+    }
+    let defaults = DetectorConfig::default();
+    let min_freq = fields.min_freq.unwrap_or(defaults.min_frequency);
+    let max_freq = fields.max_freq.unwrap_or(defaults.max_frequency);
+    if !(min_freq.is_finite() && min_freq > 0.0) {
+        return Err(format!("minFreq must be positive, got {}", min_freq));
+    }
+    if !(max_freq.is_finite() && max_freq > min_freq) {
+        return Err(format!(
+            "maxFreq must be above minFreq ({}), got {}",
+            min_freq, max_freq
+        ));
+    }
+    builder = builder.range(min_freq, max_freq);
+    if let Some(threshold) = fields.threshold {
+        if !(threshold > 0.0 && threshold <= 1.0) {
+            return Err(format!("threshold must be in (0, 1], got {}", threshold));
+        }
+        builder = builder.threshold(threshold);
+    }
+    if let Some(name) = fields.algorithm {
+        let algorithm = match name.as_str() {
+            "yin" => Algorithm::Yin,
+            "yinFullLagRange" => Algorithm::YinFullLagRange,
+            "autocorrelation" => Algorithm::Autocorrelation,
+            _ => {
+                return Err(format!(
+                "algorithm must be \"yin\", \"yinFullLagRange\" or \"autocorrelation\", got {:?}",
+                name
+            ))
+            }
+        };
+        builder = builder.algorithm(algorithm);
+    }
+    Ok(builder.build())
+}
+
+/// Builder for [`DetectionSession`]; unset options keep the crate defaults
+/// (44.1 kHz, the built-in range and threshold, whole buffers, YIN)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectionSessionBuilder {
+    sample_rate: f32,
+    config: DetectorConfig,
+    window: Option<usize>,
+    algorithm: Algorithm,
+}
+
+impl Default for DetectionSessionBuilder {
+    fn default() -> Self {
+        Self {
+            sample_rate: DEFAULT_SESSION_SAMPLE_RATE,
+            config: DetectorConfig::default(),
+            window: None,
+            algorithm: Algorithm::default(),
+        }
+    }
+}
+
+impl DetectionSessionBuilder {
+    pub fn sample_rate(mut self, sample_rate: f32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Frequency range reported, in Hz
+    pub fn range(mut self, min_frequency: f32, max_frequency: f32) -> Self {
+        self.config = self
+            .config
+            .with_frequency_range(min_frequency, max_frequency);
+        self
+    }
+
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.config = self.config.with_threshold(threshold);
+        self
+    }
+
+    pub fn rms_threshold(mut self, rms_threshold: f32) -> Self {
+        self.config = self.config.with_rms_threshold(rms_threshold);
+        self
+    }
+
+    /// Analyze only the last `window` samples of each buffer
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn build(self) -> DetectionSession {
+        DetectionSession {
+            sample_rate: self.sample_rate,
+            config: self.config,
+            window: self.window,
+            algorithm: self.algorithm,
+        }
+    }
+}
+
+/// A named frequency range and estimator, see [`InstrumentRegistry`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstrumentPreset {
+    pub min_frequency: f32,
+    pub max_frequency: f32,
+    pub algorithm: Algorithm,
+}
+
+/// Why [`InstrumentRegistry::register_instrument`] rejected a preset
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryError {
+    /// The instrument name is empty
+    EmptyName,
+    /// The lower bound is not a positive, finite frequency
+    InvalidMinFrequency { name: String, min_freq: f32 },
+    /// The upper bound is not a finite frequency above the lower bound
+    InvalidMaxFrequency {
+        name: String,
+        min_freq: f32,
+        max_freq: f32,
+    },
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EmptyName => write!(f, "instrument name must not be empty"),
+            Self::InvalidMinFrequency { name, min_freq } => {
+                write!(f, "{}: minFreq must be positive, got {}", name, min_freq)
+            }
+            Self::InvalidMaxFrequency {
+                name,
+                min_freq,
+                max_freq,
+            } => write!(
+                f,
+                "{}: maxFreq must be above minFreq ({}), got {}",
+                name, min_freq, max_freq
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+impl From<RegistryError> for JsValue {
+    fn from(error: RegistryError) -> Self {
+        JsError::from(error).into()
+    }
+}
+
+/// Instrument presets an app defines at runtime, looked up by name
+/// Each app or session keeps its own registry; nothing is shared globally,
+/// so two tuners on one page can define the same name differently
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InstrumentRegistry {
+    presets: Vec<(String, InstrumentPreset)>,
+}
+
+#[wasm_bindgen]
+impl InstrumentRegistry {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a preset, replacing any earlier one of the same name
+    /// Fails (throwing in JS) for an empty name or a range that is not
+    /// positive and increasing
+    pub fn register_instrument(
+        &mut self,
+        name: &str,
+        min_freq: f32,
+        max_freq: f32,
+        algorithm: Algorithm,
+    ) -> Result<(), RegistryError> {
+        if name.is_empty() {
+            return Err(RegistryError::EmptyName);
+        }
+        if !(min_freq.is_finite() && min_freq > 0.0) {
+            return Err(RegistryError::InvalidMinFrequency {
+                name: name.to_string(),
+                min_freq,
+            });
+        }
+        if !(max_freq.is_finite() && max_freq > min_freq) {
+            return Err(RegistryError::InvalidMaxFrequency {
+                name: name.to_string(),
+                min_freq,
+                max_freq,
+            });
+        }
+        let preset = InstrumentPreset {
+            min_frequency: min_freq,
+            max_frequency: max_freq,
+            algorithm,
+        };
+        match self.presets.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => *existing = preset,
+            None => self.presets.push((name.to_string(), preset)),
+        }
+        Ok(())
+    }
+}
+
+impl InstrumentRegistry {
+    pub fn preset(&self, name: &str) -> Option<&InstrumentPreset> {
+        self.presets
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, preset)| preset)
+    }
+}
+
+/// Detect a pitch with the range and algorithm registered under `name`
+/// Returns the detected frequency in Hz or -1.0 if no pitch detected, or
+/// None if `registry` has no such preset
+#[wasm_bindgen]
+pub fn detect_pitch_named(
+    samples: &[f32],
+    sample_rate: f32,
+    registry: &InstrumentRegistry,
+    name: &str,
+) -> Option<f32> {
+    let preset = registry.preset(name)?;
+    let session = DetectionSession::builder()
+        .sample_rate(sample_rate)
+        .range(preset.min_frequency, preset.max_frequency)
+        .algorithm(preset.algorithm)
+        .build();
+    Some(session.analyze(samples).frequency)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+    use crate::{detect_pitch_cfg, detect_pitch_full_lag_range, DEFAULT_THRESHOLD};
+
+    #[test]
+    fn test_session_applies_settings_to_every_buffer() {
+        let sample_rate = 48000.0;
+        let session = DetectionSession::builder()
+            .sample_rate(sample_rate)
+            .range(80.0, 1000.0)
+            .window(2048)
+            .algorithm(Algorithm::Yin)
+            .build();
+
+        let low = generate_tone(110.0, sample_rate, 8192, Waveform::Saw);
+        let high = generate_tone(660.0, sample_rate, 4096, Waveform::Saw);
+        let config = DetectorConfig::default().with_frequency_range(80.0, 1000.0);
+        for samples in [&low, &high] {
+            let result = session.analyze(samples);
+            assert_eq!(result.analysis_len, 1024);
+            let tail = &samples[samples.len() - 2048..];
+            assert_eq!(
+                result.frequency,
+                detect_pitch_cfg(tail, sample_rate, &config)
+            );
+        }
+        assert!((session.analyze(&low).frequency - 110.0).abs() < 1.0);
+        assert!((session.analyze(&high).frequency - 660.0).abs() < 2.0);
+
+        // Out of the session's range
+        let above = generate_tone(1500.0, sample_rate, 2048, Waveform::Sine);
+        assert_eq!(session.analyze(&above).frequency, -1.0);
+    }
+
+    #[test]
+    fn test_session_algorithms() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(220.0, sample_rate, 2047, Waveform::Sine);
+        let full = DetectionSession::builder()
+            .algorithm(Algorithm::YinFullLagRange)
+            .build();
+        assert_eq!(
+            full.analyze(&samples),
+            detect_pitch_full_lag_range(&samples, sample_rate, DEFAULT_THRESHOLD)
+        );
+
+        let acf = DetectionSession::builder()
+            .algorithm(Algorithm::Autocorrelation)
+            .build();
+        let result = acf.analyze(&samples);
+        assert!((result.frequency - 220.0).abs() < 2.0, "{:?}", result);
+        assert_eq!(acf.analyze(&[0.0; 2048]), PitchResult::UNVOICED);
+    }
+
+    #[test]
+    fn test_custom_instrument_preset() {
+        let sample_rate = 44100.0;
+        let mut registry = InstrumentRegistry::new();
+        // Re-entrant GCEA tuning: nothing below middle C
+        registry
+            .register_instrument("ukulele", 250.0, 1400.0, Algorithm::Yin)
+            .unwrap();
+
+        let a4 = generate_tone(440.0, sample_rate, 2048, Waveform::Saw);
+        let detected = detect_pitch_named(&a4, sample_rate, &registry, "ukulele").unwrap();
+        assert!((detected - 440.0).abs() < 2.0, "{}", detected);
+        let bass = generate_tone(110.0, sample_rate, 2048, Waveform::Saw);
+        assert_eq!(
+            detect_pitch_named(&bass, sample_rate, &registry, "ukulele"),
+            Some(-1.0)
+        );
+        assert_eq!(
+            detect_pitch_named(&a4, sample_rate, &registry, "banjo"),
+            None
+        );
+
+        // Registering again replaces the preset
+        registry
+            .register_instrument("ukulele", 60.0, 1400.0, Algorithm::Autocorrelation)
+            .unwrap();
+        assert_eq!(
+            registry.preset("ukulele").unwrap().algorithm,
+            Algorithm::Autocorrelation
+        );
+        let detected = detect_pitch_named(&bass, sample_rate, &registry, "ukulele").unwrap();
+        assert!((detected - 110.0).abs() < 2.0, "{}", detected);
+
+        let error = registry
+            .register_instrument("bad", 400.0, 300.0, Algorithm::Yin)
+            .unwrap_err();
+        assert!(
+            matches!(error, RegistryError::InvalidMaxFrequency { .. }),
+            "{:?}",
+            error
+        );
+        assert!(error.to_string().contains("maxFreq"), "{}", error);
+        assert_eq!(
+            registry.register_instrument("", 100.0, 300.0, Algorithm::Yin),
+            Err(RegistryError::EmptyName)
+        );
+        assert!(matches!(
+            registry.register_instrument("bad", f32::NAN, 300.0, Algorithm::Yin),
+            Err(RegistryError::InvalidMinFrequency { .. })
+        ));
+        assert!(registry.preset("bad").is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_session_from_config_object() {
+        let session = session_from_json(
+            r#"{ "sampleRate": 48000, "minFreq": 80, "maxFreq": 1000, "threshold": 0.15, "algorithm": "yinFullLagRange" }"#,
+        )
+        .unwrap();
+        let expected = DetectionSession::builder()
+            .sample_rate(48000.0)
+            .range(80.0, 1000.0)
+            .threshold(0.15)
+            .algorithm(Algorithm::YinFullLagRange)
+            .build();
+        assert_eq!(session, expected);
+        assert_eq!(
+            session_from_json("{}").unwrap(),
+            DetectionSession::builder().build()
+        );
+
+        let error = session_from_json(r#"{ "minFreq": 500, "maxFreq": 400 }"#).unwrap_err();
+        assert!(error.contains("maxFreq"), "{}", error);
+        let error = session_from_json(r#"{ "algorithm": "mpm" }"#).unwrap_err();
+        assert!(error.contains("algorithm"), "{}", error);
+        let error = session_from_json(r#"{ "sampleRate": -1 }"#).unwrap_err();
+        assert!(error.contains("sampleRate"), "{}", error);
+        let error = session_from_json(r#"{ "sample_rate": 44100 }"#).unwrap_err();
+        assert!(error.contains("unknown field"), "{}", error);
+    }
+}
@@ -0,0 +1,8 @@
+use std::f32::consts::PI;
+
+/// Unit-amplitude sine wave starting at phase zero
+pub(crate) fn sine_wave(frequency: f32, sample_rate: f32, num_samples: usize) -> Vec<f32> {
+    (0..num_samples)
+        .map(|i| (2.0 * PI * frequency * (i as f32) / sample_rate).sin())
+        .collect()
+}
@@ -0,0 +1,294 @@
+use wasm_bindgen::prelude::*;
+
+/// Default rejection distance for `robust_mean_frequency`, in MADs
+const DEFAULT_MAX_MADS: f32 = 3.0;
+
+/// Floor on the MAD in cents, so that near-identical estimates do not make
+/// every tiny deviation count as an outlier
+const MIN_MAD_CENTS: f32 = 1.0;
+
+/// Fuse the estimates of two adjacent frames into one per-hop value
+/// Each estimate is `(frequency, clarity)`; the result is the
+/// clarity-weighted mean in log-frequency (cents) space, so an octave pair
+/// blends to the geometric middle rather than being skewed toward the higher
+/// note. Unvoiced entries (non-positive frequency) are ignored, and two
+/// voiced entries with zero clarity are weighted equally.
+/// Returns -1.0 if neither is voiced
+pub fn fuse_estimates(prev: (f32, f32), curr: (f32, f32)) -> f32 {
+    let ((f1, c1), (f2, c2)) = (prev, curr);
+    match (f1 > 0.0, f2 > 0.0) {
+        (false, false) => -1.0,
+        (true, false) => f1,
+        (false, true) => f2,
+        (true, true) => {
+            let (w1, w2) = (c1.max(0.0), c2.max(0.0));
+            let (w1, w2) = if w1 + w2 > 0.0 { (w1, w2) } else { (1.0, 1.0) };
+            let log_mean = (w1 * f1.log2() + w2 * f2.log2()) / (w1 + w2);
+            2f32.powf(log_mean)
+        }
+    }
+}
+
+/// Pitch at fraction `t` of the way from one analysis frame to the next
+/// For drawing a needle at a display rate above the analysis rate: the two
+/// frame frequencies are blended in cents, so halfway between an octave pair
+/// is the geometric mean and equal steps of `t` are equal musical steps.
+/// `t` is clamped to 0.0-1.0. If only one frame is voiced (positive
+/// frequency) it is returned as is, whatever `t`.
+/// Returns -1.0 if neither is voiced
+#[wasm_bindgen]
+pub fn interpolate_pitch(prev: f32, next: f32, t: f32) -> f32 {
+    match (prev > 0.0, next > 0.0) {
+        (false, false) => -1.0,
+        (true, false) => prev,
+        (false, true) => next,
+        (true, true) => {
+            let t = t.clamp(0.0, 1.0);
+            2f32.powf((1.0 - t) * prev.log2() + t * next.log2())
+        }
+    }
+}
+
+/// Scale a pitch smoother averages on
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SmoothSpace {
+    /// Average frequencies directly; cheap, but a fixed smoothing amount
+    /// lags more on falling than on rising pitch and weighs high notes more
+    #[default]
+    Hz,
+    /// Average in cents and convert back, so the response is the same for
+    /// any interval in either direction, anywhere in the range
+    Cents,
+}
+
+impl SmoothSpace {
+    fn to_space(self, frequency: f32) -> f32 {
+        match self {
+            SmoothSpace::Hz => frequency,
+            SmoothSpace::Cents => 1200.0 * frequency.log2(),
+        }
+    }
+
+    fn to_frequency(self, value: f32) -> f32 {
+        match self {
+            SmoothSpace::Hz => value,
+            SmoothSpace::Cents => 2f32.powf(value / 1200.0),
+        }
+    }
+}
+
+/// Exponential moving average of a pitch reading, for a steadier display
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmaSmoother {
+    alpha: f32,
+    space: SmoothSpace,
+    /// Smoothed value in `space`, None until the first voiced frame
+    value: Option<f32>,
+}
+
+#[wasm_bindgen]
+impl EmaSmoother {
+    /// `alpha` is the weight of each new frame, from just above 0.0 (very
+    /// smooth) to 1.0 (no smoothing)
+    #[wasm_bindgen(constructor)]
+    pub fn new(alpha: f32, space: SmoothSpace) -> Self {
+        Self {
+            alpha: alpha.clamp(f32::EPSILON, 1.0),
+            space,
+            value: None,
+        }
+    }
+
+    /// Feed one frame's frequency and return the smoothed frequency
+    /// The first voiced frame is taken as is. Unvoiced frames (non-positive
+    /// frequency) return -1.0 and leave the average alone, so a brief dropout
+    /// does not restart it
+    pub fn process(&mut self, frequency: f32) -> f32 {
+        if frequency <= 0.0 {
+            return -1.0;
+        }
+        let input = self.space.to_space(frequency);
+        let value = match self.value {
+            Some(value) => value + self.alpha * (input - value),
+            None => input,
+        };
+        self.value = Some(value);
+        self.space.to_frequency(value)
+    }
+
+    /// Current smoothed frequency, or -1.0 before any voiced frame
+    pub fn value(&self) -> f32 {
+        self.value
+            .map_or(-1.0, |value| self.space.to_frequency(value))
+    }
+
+    pub fn space(&self) -> SmoothSpace {
+        self.space
+    }
+
+    pub fn reset(&mut self) {
+        self.value = None;
+    }
+}
+
+/// Combine several frames' estimates into one, ignoring octave errors
+/// Same as [`robust_mean_frequency_with`] dropping estimates beyond 3 MADs
+#[wasm_bindgen]
+pub fn robust_mean_frequency(freqs: &[f32]) -> f32 {
+    robust_mean_frequency_with(freqs, DEFAULT_MAX_MADS)
+}
+
+/// Mean of the voiced estimates after dropping outliers, in cents space
+/// Estimates further than `max_mads` median absolute deviations (at least
+/// one cent) from the median are dropped and the rest averaged
+/// geometrically, so an octave error among consistent frames has no say.
+/// Unvoiced entries (non-positive frequency) are ignored.
+/// Returns -1.0 if none are voiced, or none survive (a negative or NaN
+/// `max_mads`)
+#[wasm_bindgen]
+pub fn robust_mean_frequency_with(freqs: &[f32], max_mads: f32) -> f32 {
+    let cents: Vec<f32> = freqs
+        .iter()
+        .filter(|&&f| f > 0.0 && f.is_finite())
+        .map(|&f| 1200.0 * f.log2())
+        .collect();
+    if cents.is_empty() {
+        return -1.0;
+    }
+    let center = median(cents.clone());
+    let mad = median(cents.iter().map(|c| (c - center).abs()).collect()).max(MIN_MAD_CENTS);
+
+    let kept: Vec<f32> = cents
+        .into_iter()
+        .filter(|c| (c - center).abs() <= max_mads * mad)
+        .collect();
+    if kept.is_empty() {
+        return -1.0;
+    }
+    let mean = kept.iter().sum::<f32>() / kept.len() as f32;
+    2f32.powf(mean / 1200.0)
+}
+
+/// Median of a non-empty list, the mean of the middle pair for even lengths
+pub(crate) fn median(mut values: Vec<f32>) -> f32 {
+    values.sort_by(f32::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::cents_between;
+
+    #[test]
+    fn test_fuse_both_voiced() {
+        // Equal clarity lands halfway in cents
+        let fused = fuse_estimates((440.0, 0.9), (880.0, 0.9));
+        assert!(
+            (cents_between(fused, 440.0) - 600.0).abs() < 0.1,
+            "{}",
+            fused
+        );
+
+        // Higher clarity pulls the blend toward its estimate
+        let fused = fuse_estimates((440.0, 0.9), (444.0, 0.3));
+        assert!(fused > 440.0 && fused < 442.0, "{}", fused);
+
+        let fused = fuse_estimates((440.0, 0.0), (450.0, 0.0));
+        assert!((cents_between(fused, 440.0) - cents_between(450.0, 440.0) / 2.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_fuse_one_voiced() {
+        assert_eq!(fuse_estimates((-1.0, 0.0), (220.0, 0.8)), 220.0);
+        assert_eq!(fuse_estimates((330.0, 0.5), (-1.0, 0.9)), 330.0);
+    }
+
+    #[test]
+    fn test_fuse_neither_voiced() {
+        assert_eq!(fuse_estimates((-1.0, 0.0), (-1.0, 0.0)), -1.0);
+    }
+
+    #[test]
+    fn test_interpolate_pitch() {
+        let halfway = interpolate_pitch(220.0, 440.0, 0.5);
+        assert!((halfway - 220.0 * 2f32.sqrt()).abs() < 0.01, "{}", halfway);
+        assert!((interpolate_pitch(220.0, 440.0, 0.0) - 220.0).abs() < 1e-3);
+        assert!((interpolate_pitch(220.0, 440.0, 2.0) - 440.0).abs() < 1e-3);
+        // A quarter of the way is a quarter of the 1200 cents
+        let quarter = interpolate_pitch(440.0, 220.0, 0.25);
+        assert!((cents_between(quarter, 440.0) + 300.0).abs() < 0.01);
+
+        assert_eq!(interpolate_pitch(-1.0, 330.0, 0.1), 330.0);
+        assert_eq!(interpolate_pitch(330.0, -1.0, 0.9), 330.0);
+        assert_eq!(interpolate_pitch(-1.0, -1.0, 0.5), -1.0);
+    }
+
+    #[test]
+    fn test_robust_mean_rejects_octave_error() {
+        let frames = [440.0, 441.0, -1.0, 439.5, 880.0, 440.5, 440.2];
+        let mean = robust_mean_frequency(&frames);
+        assert!((mean - 440.24).abs() < 0.05, "{}", mean);
+
+        // A plain mean would be dragged far sharp
+        let voiced: Vec<f32> = frames.iter().copied().filter(|&f| f > 0.0).collect();
+        let plain = voiced.iter().sum::<f32>() / voiced.len() as f32;
+        assert!(plain > 500.0);
+
+        // Identical frames survive the MAD floor
+        assert!((robust_mean_frequency(&[330.0; 4]) - 330.0).abs() < 1e-3);
+        assert_eq!(robust_mean_frequency(&[-1.0, -1.0]), -1.0);
+        assert_eq!(robust_mean_frequency_with(&frames, -1.0), -1.0);
+        assert_eq!(robust_mean_frequency_with(&frames, f32::NAN), -1.0);
+    }
+
+    #[test]
+    fn test_cents_space_smoothing_is_symmetric() {
+        // Glide up an octave and back down at 100 cents per frame; report
+        // how far each smoother trails the input at the top and bottom
+        let lag = |space: SmoothSpace| {
+            let mut smoother = EmaSmoother::new(0.5, space);
+            let mut trail = Vec::new();
+            for (frame, step) in (0..=12).chain((0..12).rev()).enumerate() {
+                let input = 220.0 * 2f32.powf(step as f32 / 12.0);
+                let output = smoother.process(input);
+                if frame == 12 || frame == 24 {
+                    trail.push(1200.0 * (output / input).log2());
+                }
+            }
+            (trail[0], trail[1])
+        };
+
+        // Cents space trails by the same 100 cents rising and falling
+        let (rising, falling) = lag(SmoothSpace::Cents);
+        assert!((rising + 100.0).abs() < 0.5, "{}", rising);
+        assert!((falling - 100.0).abs() < 0.5, "{}", falling);
+        // Hz space trails less on the way up than on the way down
+        let (rising, falling) = lag(SmoothSpace::Hz);
+        assert!(falling - (-rising) > 5.0, "{} {}", rising, falling);
+    }
+
+    #[test]
+    fn test_ema_smoother_skips_unvoiced() {
+        let mut smoother = EmaSmoother::new(0.25, SmoothSpace::Cents);
+        assert_eq!(smoother.value(), -1.0);
+        assert_eq!(smoother.process(440.0), 440.0);
+        assert_eq!(smoother.process(-1.0), -1.0);
+        assert!((smoother.value() - 440.0).abs() < 1e-3);
+        let next = smoother.process(880.0);
+        assert!(
+            (1200.0 * (next / 440.0).log2() - 300.0).abs() < 0.1,
+            "{}",
+            next
+        );
+        smoother.reset();
+        assert_eq!(smoother.value(), -1.0);
+    }
+}
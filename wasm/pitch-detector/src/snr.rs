@@ -0,0 +1,65 @@
+use wasm_bindgen::prelude::*;
+
+use crate::detect_pitch;
+use crate::filter::fitted_sinusoid;
+
+/// Harmonic-to-residual energy ratio of the frame, in dB
+/// Fits a sinusoid at each harmonic of the detected pitch below Nyquist and
+/// compares the energy they explain with what is left over, so
+/// unlike the RMS level it measures how clean the note is, not how loud.
+/// Returns negative infinity if no pitch is detected
+#[wasm_bindgen]
+pub fn estimate_snr_db(samples: &[f32], sample_rate: f32) -> f32 {
+    let frequency = detect_pitch(samples, sample_rate);
+    if frequency <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+
+    let mut residual = samples.to_vec();
+    let harmonics = (sample_rate / 2.0 / frequency).ceil() as usize;
+    for harmonic in 1..harmonics {
+        let partial = frequency * harmonic as f32;
+        let fitted = fitted_sinusoid(&residual, partial, sample_rate);
+        for (n, sample) in residual.iter_mut().enumerate() {
+            *sample -= fitted(n as f32);
+        }
+    }
+
+    let noise: f32 = residual.iter().map(|r| r * r).sum();
+    let harmonic: f32 = samples
+        .iter()
+        .zip(&residual)
+        .map(|(x, r)| (x - r) * (x - r))
+        .sum();
+    10.0 * (harmonic / noise.max(f32::MIN_POSITIVE)).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{add_noise, generate_tone, NoiseKind, Waveform};
+
+    #[test]
+    fn test_snr_tracks_added_noise() {
+        let sample_rate = 44100.0;
+        let clean = generate_tone(440.0, sample_rate, 4096, Waveform::Sine);
+        let clean_snr = estimate_snr_db(&clean, sample_rate);
+        assert!(clean_snr > 25.0, "clean {}", clean_snr);
+
+        let mut noisy = clean.clone();
+        add_noise(&mut noisy, 10.0, NoiseKind::White);
+        let noisy_snr = estimate_snr_db(&noisy, sample_rate);
+        assert!(
+            noisy_snr < clean_snr - 10.0,
+            "{} vs {}",
+            noisy_snr,
+            clean_snr
+        );
+        assert!((noisy_snr - 10.0).abs() < 3.0, "noisy {}", noisy_snr);
+
+        assert_eq!(
+            estimate_snr_db(&[0.0; 4096], sample_rate),
+            f32::NEG_INFINITY
+        );
+    }
+}
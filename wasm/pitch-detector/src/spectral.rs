@@ -0,0 +1,570 @@
+use wasm_bindgen::prelude::*;
+
+use std::f32::consts::PI;
+
+use crate::fft::{complex_spectrum, magnitude_spectrum};
+use crate::note::{cents_between, ReferencePitch};
+use crate::yin::{detect_pitch_full, lag_difference, parabola_vertex};
+use crate::{calculate_rms, detect_pitch, MAX_FREQUENCY, MIN_FREQUENCY, RMS_THRESHOLD};
+
+/// Spectral flatness above which a frame is treated as broadband noise
+/// (white noise sits around 0.56, tonal sound far below)
+const PRESENCE_MAX_FLATNESS: f32 = 0.3;
+
+/// CMNDF threshold the frame's periodicity must pass for presence
+const PRESENCE_THRESHOLD: f32 = 0.2;
+
+/// Spectral flatness below which a frame is treated as a single sinusoid
+/// (a pure tone sits near 1e-11, a sawtooth at 1e-5 and above)
+const SINUSOID_MAX_FLATNESS: f32 = 1e-6;
+
+/// Lags either side of the zero-crossing period that the refinement evaluates
+const SINUSOID_SEARCH_RADIUS: usize = 2;
+
+/// Mains hum frequencies, in Hz
+const MAINS_HZ: [f32; 2] = [50.0, 60.0];
+
+/// A fundamental this close to a mains frequency is taken for hum
+const HUM_TOLERANCE_CENTS: f32 = 30.0;
+
+/// Bins on each side of a peak counted as part of it (the Hann main lobe)
+const PEAK_HALF_WIDTH: usize = 2;
+
+/// Bins above DC that a Hann-windowed offset leaks into; kept out of peak
+/// searches along with DC itself
+const DC_LEAKAGE_BINS: usize = 1;
+
+/// How dominant the strongest spectral peak is, from 0.0 to 1.0
+/// The share of spectral energy (DC and its leakage excluded) within the main lobe of the
+/// strongest peak in the detection range. Near 1.0 for pure tones such as
+/// flute or whistling, low for broadband noise; a harmonic-rich tone scores
+/// in between since its energy is split over several peaks.
+/// Returns 0.0 for silent or empty input
+#[wasm_bindgen]
+pub fn spectral_peak_prominence(samples: &[f32], sample_rate: f32) -> f32 {
+    let (magnitudes, fft_size) = magnitude_spectrum(samples);
+    let power: Vec<f32> = magnitudes.iter().map(|m| m * m).collect();
+    let total: f32 = power.iter().skip(DC_LEAKAGE_BINS + 1).sum();
+    if total <= f32::EPSILON {
+        return 0.0;
+    }
+
+    let peak = match range_bins(power.len(), sample_rate / fft_size as f32)
+        .max_by(|&a, &b| power[a].total_cmp(&power[b]))
+    {
+        Some(p) => p,
+        None => return 0.0,
+    };
+
+    let lobe = peak
+        .saturating_sub(PEAK_HALF_WIDTH)
+        .max(DC_LEAKAGE_BINS + 1)..=(peak + PEAK_HALF_WIDTH).min(power.len() - 1);
+    let peak_energy: f32 = power[lobe].iter().sum();
+    (peak_energy / total).clamp(0.0, 1.0)
+}
+
+/// Frequency of the strongest spectral peak in the detection range
+/// A plain alternative to YIN: Hann window, FFT, largest bin, then a parabola
+/// through the log magnitudes of that bin and its neighbours for sub-bin
+/// accuracy. Unlike YIN it has no notion of period, so any tone whose
+/// fundamental is weaker than an overtone (low strings, many voices, phone
+/// audio missing the fundamental) is reported at that overtone, an octave
+/// or more too high.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_spectral_peak(samples: &[f32], sample_rate: f32) -> f32 {
+    if samples.len() < 2 || ac_rms(samples) < RMS_THRESHOLD {
+        return -1.0;
+    }
+    let (magnitudes, fft_size) = magnitude_spectrum(samples);
+    let bin_hz = sample_rate / fft_size as f32;
+    let peak = match range_bins(magnitudes.len(), bin_hz)
+        .max_by(|&a, &b| magnitudes[a].total_cmp(&magnitudes[b]))
+    {
+        Some(p) => p,
+        None => return -1.0,
+    };
+
+    let mut offset = 0.0;
+    if peak + 1 < magnitudes.len() {
+        let [left, centre, right] =
+            [peak - 1, peak, peak + 1].map(|bin| magnitudes[bin].max(f32::MIN_POSITIVE).ln());
+        let denominator = left - 2.0 * centre + right;
+        if denominator.abs() > f32::EPSILON {
+            offset = (0.5 * (left - right) / denominator).clamp(-0.5, 0.5);
+        }
+    }
+    let frequency = (peak as f32 + offset) * bin_hz;
+    if (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+        frequency
+    } else {
+        -1.0
+    }
+}
+
+/// Frequency of the strongest spectral peak from its phase advance over a hop
+/// The phase vocoder estimate: `curr_frame` starts `hop` samples after
+/// `prev_frame` (same length), and how far the peak bin's phase moved beyond
+/// what its centre frequency predicts pins the frequency down far more
+/// finely than the bin spacing. Best on sustained tones; a `hop` of at most a
+/// quarter of the frame keeps the phase advance unambiguous.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_instantaneous(
+    prev_frame: &[f32],
+    curr_frame: &[f32],
+    hop: usize,
+    sample_rate: f32,
+) -> f32 {
+    if hop == 0
+        || prev_frame.len() != curr_frame.len()
+        || curr_frame.len() < 2
+        || ac_rms(curr_frame) < RMS_THRESHOLD
+    {
+        return -1.0;
+    }
+    let (prev_re, prev_im) = complex_spectrum(prev_frame);
+    let (curr_re, curr_im) = complex_spectrum(curr_frame);
+    let fft_size = curr_re.len();
+    let bin_hz = sample_rate / fft_size as f32;
+    let power = |bin: usize| curr_re[bin] * curr_re[bin] + curr_im[bin] * curr_im[bin];
+    let peak =
+        match range_bins(fft_size / 2 + 1, bin_hz).max_by(|&a, &b| power(a).total_cmp(&power(b))) {
+            Some(p) => p,
+            None => return -1.0,
+        };
+
+    let advance = curr_im[peak].atan2(curr_re[peak]) - prev_im[peak].atan2(prev_re[peak]);
+    let expected = 2.0 * PI * peak as f32 * hop as f32 / fft_size as f32;
+    let deviation = (advance - expected + PI).rem_euclid(2.0 * PI) - PI;
+    let frequency = (peak as f32 + deviation * fft_size as f32 / (2.0 * PI * hop as f32)) * bin_hz;
+    if (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+        frequency
+    } else {
+        -1.0
+    }
+}
+
+/// Frequency of the strongest spectral peak from a precomputed spectrum
+/// For callers that already run their own STFT: `magnitudes` and `phases`
+/// hold bins 0 to at least `fft_size / 2` of a Hann-windowed frame, phase
+/// as `atan2(im, re)`. Rebuilding the complex bins lets the peak and its two
+/// neighbours be interpolated with their phases as well as magnitudes, which
+/// for the Hann window places a single sinusoid exactly rather than the
+/// approximately a parabola through magnitudes gives.
+/// Returns the detected frequency in Hz, or -1.0 if the lengths differ, the
+/// spectrum is silent or no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_from_stft(
+    magnitudes: &[f32],
+    phases: &[f32],
+    sample_rate: f32,
+    fft_size: usize,
+) -> f32 {
+    if magnitudes.len() != phases.len() || fft_size < 4 || magnitudes.len() <= fft_size / 2 {
+        return -1.0;
+    }
+    let bin_hz = sample_rate / fft_size as f32;
+    let peak = match range_bins(fft_size / 2 + 1, bin_hz)
+        .max_by(|&a, &b| magnitudes[a].total_cmp(&magnitudes[b]))
+    {
+        Some(p) if magnitudes[p] > f32::EPSILON => p,
+        _ => return -1.0,
+    };
+
+    let bin = |k: usize| {
+        (
+            magnitudes[k] * phases[k].cos(),
+            magnitudes[k] * phases[k].sin(),
+        )
+    };
+    let [(lr, li), (cr, ci), (rr, ri)] = [bin(peak - 1), bin(peak), bin(peak + 1)];
+    // Offset = 2 Re[(X[k-1] - X[k+1]) / (2X[k] - X[k-1] - X[k+1])], exact for
+    // a Hann-windowed sinusoid
+    let (nr, ni) = (lr - rr, li - ri);
+    let (dr, di) = (2.0 * cr - lr - rr, 2.0 * ci - li - ri);
+    let denominator = dr * dr + di * di;
+    let offset = if denominator > f32::EPSILON {
+        (2.0 * (nr * dr + ni * di) / denominator).clamp(-1.0, 1.0)
+    } else {
+        0.0
+    };
+    let frequency = (peak as f32 + offset) * bin_hz;
+    if (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+        frequency
+    } else {
+        -1.0
+    }
+}
+
+/// Pitch detection for (near-)pure tones such as whistling or tuning forks
+/// The spacing of rising zero crossings gives the period to within a lag,
+/// and the difference function at just the few lags around it refines that
+/// to a sub-sample estimate, instead of the full lag search YIN makes. Only a
+/// single sinusoid crosses zero once per period, so frames whose spectral
+/// flatness shows more than one partial fall back to `detect_pitch`.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_sinusoid(samples: &[f32], sample_rate: f32) -> f32 {
+    if samples.len() < 2 || calculate_rms(samples) < RMS_THRESHOLD {
+        return -1.0;
+    }
+    if spectral_flatness(samples) > SINUSOID_MAX_FLATNESS {
+        return detect_pitch(samples, sample_rate);
+    }
+    match sinusoid_period(samples) {
+        Some((tau, _)) => {
+            let frequency = sample_rate / tau;
+            if (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+                frequency
+            } else {
+                -1.0
+            }
+        }
+        None => detect_pitch(samples, sample_rate),
+    }
+}
+
+/// Period of a sinusoid in samples, with the number of lags compared
+/// None when the frame holds fewer than two rising zero crossings or the
+/// period leaves no room to compare
+fn sinusoid_period(samples: &[f32]) -> Option<(f32, usize)> {
+    let crossings: Vec<f32> = samples
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0] < 0.0 && pair[1] >= 0.0)
+        .map(|(i, pair)| i as f32 + pair[0] / (pair[0] - pair[1]))
+        .collect();
+    let (first, last) = match crossings[..] {
+        [first, .., last] => (first, last),
+        _ => return None,
+    };
+    let rough = ((last - first) / (crossings.len() - 1) as f32).round() as usize;
+
+    let lo = rough.checked_sub(SINUSOID_SEARCH_RADIUS)?.max(1);
+    let hi = rough + SINUSOID_SEARCH_RADIUS;
+    if hi >= samples.len() {
+        return None;
+    }
+    let window = samples.len() - hi;
+    let difference: Vec<f32> = (lo..=hi)
+        .map(|tau| lag_difference(samples, window, tau))
+        .collect();
+    let trough = (0..difference.len()).min_by(|&a, &b| difference[a].total_cmp(&difference[b]))?;
+    let (offset, _) = parabola_vertex(&difference, trough);
+    Some(((lo + trough) as f32 + offset, difference.len()))
+}
+
+/// Bins covering the detection range for a spectrum of `bins` bins spaced
+/// `bin_hz` apart
+/// DC, the bins its leakage reaches and the Nyquist bin are always left out,
+/// since any offset or aliasing piles spurious energy there; with short
+/// frames the detection range would otherwise reach into them
+fn range_bins(bins: usize, bin_hz: f32) -> std::ops::RangeInclusive<usize> {
+    let first = ((MIN_FREQUENCY / bin_hz).floor() as usize).max(DC_LEAKAGE_BINS + 1);
+    let last = ((MAX_FREQUENCY / bin_hz).ceil() as usize).min(bins.saturating_sub(2));
+    first..=last
+}
+
+/// RMS of the signal about its mean, so that a DC offset alone does not
+/// pass the silence gate
+fn ac_rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+    let rms = calculate_rms(samples);
+    (rms * rms - mean * mean).max(0.0).sqrt()
+}
+
+/// Magnitude-weighted mean frequency of the spectrum, in Hz
+/// A brightness measure independent of pitch: harmonic-rich tones sit well
+/// above their fundamental, pure tones at it. DC is excluded.
+/// Returns 0.0 for silent or empty input
+#[wasm_bindgen]
+pub fn spectral_centroid(samples: &[f32], sample_rate: f32) -> f32 {
+    let (magnitudes, fft_size) = magnitude_spectrum(samples);
+    let bin_hz = sample_rate / fft_size as f32;
+    let (weighted, total) = magnitudes
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold((0.0f32, 0.0f32), |(weighted, total), (bin, &m)| {
+            (weighted + m * bin as f32 * bin_hz, total + m)
+        });
+    if total <= f32::EPSILON {
+        return 0.0;
+    }
+    weighted / total
+}
+
+/// Spectral energy folded into the 12 pitch classes, C (0) through B (11)
+/// Each bin in the detection range adds its power to the pitch class nearest
+/// its centre frequency (A4 = 440 Hz), whatever the octave; normalized to sum
+/// to 1.0. For key or chord estimation downstream.
+/// Returns all zeros for silent or empty input
+pub fn chroma_vector(samples: &[f32], sample_rate: f32) -> [f32; 12] {
+    let mut chroma = [0.0f32; 12];
+    if samples.is_empty() {
+        return chroma;
+    }
+    let (magnitudes, fft_size) = magnitude_spectrum(samples);
+    let bin_hz = sample_rate / fft_size as f32;
+    let reference = ReferencePitch::default();
+    for bin in range_bins(magnitudes.len(), bin_hz) {
+        let midi = reference.frequency_to_midi(bin as f32 * bin_hz).round() as i32;
+        chroma[midi.rem_euclid(12) as usize] += magnitudes[bin] * magnitudes[bin];
+    }
+
+    let total: f32 = chroma.iter().sum();
+    if total > f32::EPSILON {
+        chroma.iter_mut().for_each(|c| *c /= total);
+    }
+    chroma
+}
+
+/// Whether the frame holds an instrument note rather than silence or noise
+/// A voice-activity style gate combining three checks: the usual RMS gate,
+/// a low spectral flatness (tonal rather than broadband), and a YIN trough
+/// deep enough to mark a clear period. Steady 50/60 Hz mains hum is tonal
+/// and periodic too, so a fundamental at a mains frequency is rejected
+#[wasm_bindgen]
+pub fn is_instrument_present(samples: &[f32], sample_rate: f32) -> bool {
+    if calculate_rms(samples) < RMS_THRESHOLD {
+        return false;
+    }
+    if spectral_flatness(samples) > PRESENCE_MAX_FLATNESS {
+        return false;
+    }
+    let frequency = detect_pitch_full(samples, sample_rate, PRESENCE_THRESHOLD).frequency;
+    frequency > 0.0
+        && MAINS_HZ
+            .iter()
+            .all(|&mains| cents_between(frequency, mains).abs() > HUM_TOLERANCE_CENTS)
+}
+
+/// Geometric over arithmetic mean of the power spectrum (DC excluded)
+/// Near 1.0 for white noise, near 0.0 for a few strong peaks
+fn spectral_flatness(samples: &[f32]) -> f32 {
+    let (magnitudes, _) = magnitude_spectrum(samples);
+    let power: Vec<f32> = magnitudes.iter().skip(1).map(|m| m * m + 1e-12).collect();
+    if power.is_empty() {
+        return 1.0;
+    }
+    let count = power.len() as f32;
+    let log_mean = power.iter().map(|p| p.ln()).sum::<f32>() / count;
+    let mean = power.iter().sum::<f32>() / count;
+    log_mean.exp() / mean
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{add_noise, generate_tone, mix_partials, NoiseKind, Waveform};
+
+    #[test]
+    fn test_spectral_peak_interpolates_between_bins() {
+        let sample_rate = 44100.0;
+        // 2048 samples give 21.5 Hz bins; 443.7 Hz sits between bins 20 and 21
+        let samples = generate_tone(443.7, sample_rate, 2048, Waveform::Sine);
+        let detected = detect_pitch_spectral_peak(&samples, sample_rate);
+        assert!((detected - 443.7).abs() < 1.0, "got {}", detected);
+        assert_eq!(detect_pitch_spectral_peak(&[0.0; 2048], sample_rate), -1.0);
+    }
+
+    #[test]
+    fn test_spectral_detectors_ignore_dc() {
+        let sample_rate = 44100.0;
+        // 1024 samples give 43 Hz bins, so the detection range starts at
+        // bin 1, right where the offset leaks
+        let offset =
+            |samples: &[f32]| -> Vec<f32> { samples.iter().map(|x| 0.5 + 0.2 * x).collect() };
+        let tone = generate_tone(440.0, sample_rate, 1280, Waveform::Sine);
+        let (prev, curr) = (offset(&tone[..1024]), offset(&tone[256..]));
+
+        let detected = detect_pitch_spectral_peak(&curr, sample_rate);
+        assert!((detected - 440.0).abs() < 5.0, "got {}", detected);
+        let detected = detect_pitch_instantaneous(&prev, &curr, 256, sample_rate);
+        assert!((detected - 440.0).abs() < 1.0, "got {}", detected);
+        assert!(spectral_peak_prominence(&curr, sample_rate) > 0.9);
+
+        // An offset with no tone is not a pitch
+        let dc = [0.5; 1024];
+        assert_eq!(detect_pitch_spectral_peak(&dc, sample_rate), -1.0);
+        assert_eq!(detect_pitch_instantaneous(&dc, &dc, 256, sample_rate), -1.0);
+
+        // At 4 kHz the range would reach the Nyquist bin
+        assert_eq!(range_bins(513, 4000.0 / 1024.0), 15..=511);
+    }
+
+    #[test]
+    fn test_spectral_peak_follows_strongest_overtone() {
+        let sample_rate = 44100.0;
+        let samples = mix_partials(
+            &[(110.0, 0.3), (220.0, 1.0), (330.0, 0.5)],
+            sample_rate,
+            4096,
+        );
+
+        // The octave error YIN avoids
+        assert!((detect_pitch_spectral_peak(&samples, sample_rate) - 220.0).abs() < 2.0);
+        assert!((crate::detect_pitch(&samples, sample_rate) - 110.0).abs() < 2.0);
+    }
+
+    #[test]
+    fn test_sinusoid_fast_path() {
+        let sample_rate = 44100.0;
+        for frequency in [82.41, 440.0, 1318.5] {
+            let sine = generate_tone(frequency, sample_rate, 2048, Waveform::Sine);
+            let fast = detect_pitch_sinusoid(&sine, sample_rate);
+            let yin = crate::detect_pitch(&sine, sample_rate);
+            assert!(cents_between(fast, yin).abs() < 1.0, "{} vs {}", fast, yin);
+            // A handful of lags against YIN's 1024
+            let (_, lags) = sinusoid_period(&sine).unwrap();
+            assert!(lags <= 2 * SINUSOID_SEARCH_RADIUS + 1);
+        }
+
+        // Several partials: zero crossings no longer mark periods
+        let saw = generate_tone(220.0, sample_rate, 2048, Waveform::Saw);
+        assert_eq!(
+            detect_pitch_sinusoid(&saw, sample_rate),
+            crate::detect_pitch(&saw, sample_rate)
+        );
+        assert_eq!(detect_pitch_sinusoid(&[0.0; 2048], sample_rate), -1.0);
+    }
+
+    #[test]
+    fn test_instantaneous_frequency_beats_yin() {
+        let sample_rate = 44100.0;
+        let frequency = 443.37;
+        let hop = 256;
+        let signal = generate_tone(frequency, sample_rate, 2048 + hop, Waveform::Sine);
+        let (prev, curr) = (&signal[..2048], &signal[hop..]);
+
+        let instantaneous = detect_pitch_instantaneous(prev, curr, hop, sample_rate);
+        let yin = crate::detect_pitch(curr, sample_rate);
+        let error = cents_between(instantaneous, frequency).abs();
+        assert!(error < 0.05, "{} Hz, {} cents off", instantaneous, error);
+        assert!(error < cents_between(yin, frequency).abs(), "YIN {}", yin);
+
+        assert_eq!(
+            detect_pitch_instantaneous(prev, &curr[1..], hop, sample_rate),
+            -1.0
+        );
+        assert_eq!(detect_pitch_instantaneous(prev, curr, 0, sample_rate), -1.0);
+        assert_eq!(
+            detect_pitch_instantaneous(&[0.0; 2048], &[0.0; 2048], hop, sample_rate),
+            -1.0
+        );
+    }
+
+    #[test]
+    fn test_stft_phase_interpolation() {
+        let sample_rate = 44100.0;
+        let frequency = 443.37;
+        let samples = generate_tone(frequency, sample_rate, 2048, Waveform::Sine);
+        let (re, im) = complex_spectrum(&samples);
+        let half = re.len() / 2 + 1;
+        let magnitudes: Vec<f32> = (0..half).map(|k| re[k].hypot(im[k])).collect();
+        let phases: Vec<f32> = (0..half).map(|k| im[k].atan2(re[k])).collect();
+
+        // Well inside a bin (21.5 Hz), and closer than the magnitude parabola
+        let stft = detect_pitch_from_stft(&magnitudes, &phases, sample_rate, re.len());
+        let error = (stft - frequency).abs();
+        assert!(error < 0.05, "{} Hz", stft);
+        let parabola = detect_pitch_spectral_peak(&samples, sample_rate);
+        assert!(
+            error < (parabola - frequency).abs(),
+            "parabola {}",
+            parabola
+        );
+
+        assert_eq!(
+            detect_pitch_from_stft(&magnitudes, &phases[1..], sample_rate, re.len()),
+            -1.0
+        );
+        assert_eq!(
+            detect_pitch_from_stft(&vec![0.0; half], &vec![0.0; half], sample_rate, re.len()),
+            -1.0
+        );
+    }
+
+    #[test]
+    fn test_chroma_of_c_major_triad() {
+        let sample_rate = 44100.0;
+        let third = 1.0 / 3.0;
+        let triad = mix_partials(
+            &[(261.63, third), (329.63, third), (392.0, third)],
+            sample_rate,
+            8192,
+        );
+        let chroma = chroma_vector(&triad, sample_rate);
+        assert!((chroma.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+        assert!(chroma[0] + chroma[4] + chroma[7] > 0.95, "{:?}", chroma);
+        for class in [0, 4, 7] {
+            assert!(chroma[class] > 0.25, "{:?}", chroma);
+        }
+
+        assert_eq!(chroma_vector(&[0.0; 2048], sample_rate), [0.0; 12]);
+    }
+
+    #[test]
+    fn test_sine_prominent_noise_not() {
+        let sample_rate = 44100.0;
+        let sine = generate_tone(440.0, sample_rate, 2048, Waveform::Sine);
+        let prominence = spectral_peak_prominence(&sine, sample_rate);
+        assert!(prominence > 0.9, "sine {}", prominence);
+
+        // Practically pure noise
+        let mut noise = generate_tone(440.0, sample_rate, 2048, Waveform::Sine);
+        add_noise(&mut noise, -30.0, NoiseKind::White);
+        let prominence = spectral_peak_prominence(&noise, sample_rate);
+        assert!(prominence < 0.1, "noise {}", prominence);
+
+        assert_eq!(spectral_peak_prominence(&[0.0; 2048], sample_rate), 0.0);
+        assert_eq!(spectral_peak_prominence(&[], sample_rate), 0.0);
+    }
+
+    #[test]
+    fn test_instrument_presence() {
+        let sample_rate = 44100.0;
+        let with_harmonics = |f: f32| {
+            mix_partials(
+                &[(f, 0.5), (2.0 * f, 0.25), (3.0 * f, 0.15)],
+                sample_rate,
+                4096,
+            )
+        };
+        assert!(!is_instrument_present(&with_harmonics(50.0), sample_rate));
+        assert!(!is_instrument_present(&with_harmonics(60.0), sample_rate));
+
+        let mut noise = generate_tone(440.0, sample_rate, 4096, Waveform::Sine);
+        add_noise(&mut noise, -30.0, NoiseKind::White);
+        assert!(!is_instrument_present(&noise, sample_rate));
+        assert!(!is_instrument_present(&[0.0; 4096], sample_rate));
+
+        // A plucked G3
+        let pluck: Vec<f32> = generate_tone(196.0, sample_rate, 4096, Waveform::Saw)
+            .iter()
+            .enumerate()
+            .map(|(i, x)| 0.5 * x * (-(i as f32) / sample_rate / 0.3).exp())
+            .collect();
+        assert!(is_instrument_present(&pluck, sample_rate));
+    }
+
+    #[test]
+    fn test_saw_brighter_than_sine() {
+        let sample_rate = 44100.0;
+        let sine = generate_tone(220.0, sample_rate, 4096, Waveform::Sine);
+        let saw = generate_tone(220.0, sample_rate, 4096, Waveform::Saw);
+        let sine_centroid = spectral_centroid(&sine, sample_rate);
+        let saw_centroid = spectral_centroid(&saw, sample_rate);
+        assert!(
+            (sine_centroid - 220.0).abs() < 20.0,
+            "sine {}",
+            sine_centroid
+        );
+        assert!(saw_centroid > 4.0 * sine_centroid, "saw {}", saw_centroid);
+        assert_eq!(spectral_centroid(&[0.0; 1024], sample_rate), 0.0);
+    }
+}
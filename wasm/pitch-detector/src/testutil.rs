@@ -0,0 +1,204 @@
+//! Test-signal generators for exercising the detector
+//!
+//! Available with the `testutil` feature.
+
+use crate::signal::sine_wave;
+
+/// Waveform shape for [`generate_tone`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Saw,
+    Triangle,
+}
+
+/// Generate a unit-amplitude tone starting at phase zero
+/// The non-sine shapes are not band-limited, so they carry the full harmonic
+/// series (and some aliasing), which makes them good octave-error stress tests
+pub fn generate_tone(
+    frequency: f32,
+    sample_rate: f32,
+    num_samples: usize,
+    waveform: Waveform,
+) -> Vec<f32> {
+    let shape: fn(f32) -> f32 = match waveform {
+        Waveform::Sine => return sine_wave(frequency, sample_rate, num_samples),
+        Waveform::Square => |phase| if phase < 0.5 { 1.0 } else { -1.0 },
+        Waveform::Saw => |phase| 2.0 * phase - 1.0,
+        Waveform::Triangle => |phase| 1.0 - 4.0 * (phase - 0.5).abs(),
+    };
+
+    (0..num_samples)
+        .map(|i| shape((frequency * i as f32 / sample_rate).fract()))
+        .collect()
+}
+
+/// Sum of sines starting at phase zero, one per `(frequency, amplitude)`
+/// partial, for chords, hum and tones with a chosen harmonic balance
+pub fn mix_partials(partials: &[(f32, f32)], sample_rate: f32, num_samples: usize) -> Vec<f32> {
+    let mut mix = vec![0.0; num_samples];
+    for &(frequency, amplitude) in partials {
+        let tone = sine_wave(frequency, sample_rate, num_samples);
+        for (sample, x) in mix.iter_mut().zip(tone) {
+            *sample += amplitude * x;
+        }
+    }
+    mix
+}
+
+/// Spectral color of the noise added by [`add_noise`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Flat spectrum
+    White,
+    /// -3 dB per octave, closer to real room noise
+    Pink,
+}
+
+const DEFAULT_NOISE_SEED: u64 = 0x5EED;
+
+/// Add noise to `samples` at the given signal-to-noise ratio
+/// Uses a fixed seed, so repeated calls on the same input give the same output
+pub fn add_noise(samples: &mut [f32], snr_db: f32, kind: NoiseKind) {
+    add_noise_seeded(samples, snr_db, kind, DEFAULT_NOISE_SEED);
+}
+
+/// Add noise to `samples` at the given signal-to-noise ratio using `seed`
+/// Silent input is left untouched since its SNR is undefined
+pub fn add_noise_seeded(samples: &mut [f32], snr_db: f32, kind: NoiseKind, seed: u64) {
+    let signal_power = mean_power(samples);
+    if signal_power <= 0.0 {
+        return;
+    }
+
+    let mut rng = SplitMix64(seed);
+    let mut noise: Vec<f32> = (0..samples.len()).map(|_| rng.next_f32()).collect();
+    if kind == NoiseKind::Pink {
+        pink_filter(&mut noise);
+    }
+
+    let noise_power = mean_power(&noise);
+    if noise_power <= 0.0 {
+        return;
+    }
+    let target_power = signal_power / 10f32.powf(snr_db / 10.0);
+    let gain = (target_power / noise_power).sqrt();
+    for (sample, n) in samples.iter_mut().zip(&noise) {
+        *sample += n * gain;
+    }
+}
+
+fn mean_power(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32
+}
+
+/// Paul Kellett's economy pink filter, applied in place to white noise
+fn pink_filter(noise: &mut [f32]) {
+    let (mut b0, mut b1, mut b2) = (0.0f32, 0.0f32, 0.0f32);
+    for n in noise.iter_mut() {
+        let white = *n;
+        b0 = 0.99765 * b0 + white * 0.0990460;
+        b1 = 0.96300 * b1 + white * 0.2965164;
+        b2 = 0.57000 * b2 + white * 1.0526913;
+        *n = b0 + b1 + b2 + white * 0.1848;
+    }
+}
+
+/// Small deterministic PRNG, kept local so no generator state is shared
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [-1.0, 1.0)
+    fn next_f32(&mut self) -> f32 {
+        let unit = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        2.0 * unit - 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect_pitch;
+
+    #[test]
+    fn test_saw_110hz_no_octave_error() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(110.0, sample_rate, 2048, Waveform::Saw);
+        let detected = detect_pitch(&samples, sample_rate);
+        assert!(
+            (detected - 110.0).abs() < 2.0,
+            "Expected ~110Hz, got {}",
+            detected
+        );
+    }
+
+    #[test]
+    fn test_noise_is_deterministic() {
+        let clean = generate_tone(440.0, 44100.0, 1024, Waveform::Sine);
+        let mut a = clean.clone();
+        let mut b = clean.clone();
+        add_noise(&mut a, 10.0, NoiseKind::Pink);
+        add_noise(&mut b, 10.0, NoiseKind::Pink);
+        assert_eq!(a, b);
+        assert_ne!(a, clean);
+
+        let mut c = clean.clone();
+        add_noise_seeded(&mut c, 10.0, NoiseKind::Pink, 7);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_noise_tolerance_of_default_threshold() {
+        // With the default threshold, a 440 Hz sine survives 10 dB SNR but is
+        // lost once the noise is as loud as the tone
+        let sample_rate = 44100.0;
+        for kind in [NoiseKind::White, NoiseKind::Pink] {
+            let mut samples = generate_tone(440.0, sample_rate, 2048, Waveform::Sine);
+            add_noise(&mut samples, 10.0, kind);
+            let detected = detect_pitch(&samples, sample_rate);
+            assert!(
+                (detected - 440.0).abs() < 2.0,
+                "{:?}: got {}",
+                kind,
+                detected
+            );
+
+            let mut samples = generate_tone(440.0, sample_rate, 2048, Waveform::Sine);
+            add_noise(&mut samples, 0.0, kind);
+            assert_eq!(detect_pitch(&samples, sample_rate), -1.0, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn test_all_waveforms_detect_fundamental() {
+        let sample_rate = 44100.0;
+        for waveform in [
+            Waveform::Sine,
+            Waveform::Square,
+            Waveform::Saw,
+            Waveform::Triangle,
+        ] {
+            let samples = generate_tone(220.0, sample_rate, 2048, waveform);
+            assert!(samples.iter().all(|s| s.abs() <= 1.0));
+            let detected = detect_pitch(&samples, sample_rate);
+            assert!(
+                (detected - 220.0).abs() < 3.0,
+                "{:?}: expected ~220Hz, got {}",
+                waveform,
+                detected
+            );
+        }
+    }
+}
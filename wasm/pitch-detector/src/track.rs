@@ -0,0 +1,899 @@
+use wasm_bindgen::prelude::*;
+
+use crate::algorithm::{PitchAlgorithm, Yin};
+use crate::grade::{segment_notes, slack_frames};
+use crate::note::{cents_to_ratio, frequency_to_note, NoteInfo, ReferencePitch};
+use crate::yin::detect_pitch_full;
+use crate::{detect_pitch, DEFAULT_THRESHOLD, MAX_FREQUENCY, MIN_FREQUENCY};
+
+/// Pitch spread, in cents, at which `pitch_stability` falls to 1/e
+const STABILITY_SCALE_CENTS: f32 = 10.0;
+
+/// Pitch of each frame of a longer recording
+/// Frames of `frame_size` samples start every `hop_size` samples; a trailing
+/// partial frame is dropped. Each entry is in Hz, or -1.0 if no pitch was
+/// detected. Returns an empty track if either size is zero
+#[wasm_bindgen]
+pub fn detect_pitch_track(
+    samples: &[f32],
+    sample_rate: f32,
+    frame_size: usize,
+    hop_size: usize,
+) -> Vec<f32> {
+    let mut track = Vec::new();
+    detect_pitch_track_into(samples, sample_rate, frame_size, hop_size, &mut track);
+    track
+}
+
+/// [`detect_pitch_track`] with each frame's centre time
+/// Entries are `(time_seconds, frequency)`, the time measured from the start
+/// of `samples` to the middle of the frame
+pub fn detect_pitch_track_timed(
+    samples: &[f32],
+    sample_rate: f32,
+    frame_size: usize,
+    hop_size: usize,
+) -> Vec<(f32, f32)> {
+    detect_pitch_track(samples, sample_rate, frame_size, hop_size)
+        .into_iter()
+        .enumerate()
+        .map(|(i, frequency)| {
+            let centre = i * hop_size + frame_size / 2;
+            (centre as f32 / sample_rate, frequency)
+        })
+        .collect()
+}
+
+/// [`detect_pitch_track`] with the frame spacing given as an overlap
+/// `overlap_percent` must be in `0.0..100.0`; the hop is
+/// `frame_size * (1 - overlap / 100)` rounded to the nearest sample, at least
+/// one. Returns an empty track for an invalid overlap
+#[wasm_bindgen]
+pub fn detect_pitch_track_overlap(
+    samples: &[f32],
+    sample_rate: f32,
+    frame_size: usize,
+    overlap_percent: f32,
+) -> Vec<f32> {
+    match overlap_hop(frame_size, overlap_percent) {
+        Some(hop_size) => detect_pitch_track(samples, sample_rate, frame_size, hop_size),
+        None => Vec::new(),
+    }
+}
+
+fn overlap_hop(frame_size: usize, overlap_percent: f32) -> Option<usize> {
+    if !(0.0..100.0).contains(&overlap_percent) {
+        return None;
+    }
+    let hop = (frame_size as f32 * (1.0 - overlap_percent / 100.0)).round() as usize;
+    Some(hop.max(1))
+}
+
+/// Pitch of each of a list of arbitrary segments, e.g. notes split at onsets
+/// Each `(start, end)` is a half-open sample range of `samples`. Returns one
+/// entry per segment, in Hz, or -1.0 if no pitch was detected or the range
+/// is empty or out of bounds
+pub fn detect_pitch_segments(
+    samples: &[f32],
+    sample_rate: f32,
+    segments: &[(usize, usize)],
+) -> Vec<f32> {
+    segments
+        .iter()
+        .map(|&(start, end)| match samples.get(start..end) {
+            Some(segment) if start < end => detect_pitch(segment, sample_rate),
+            _ => -1.0,
+        })
+        .collect()
+}
+
+/// Interval between a detected pitch and a reference melody, from
+/// [`detect_relative`]
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelativeResult {
+    /// Detected frequency in Hz, or -1.0 if no pitch detected
+    pub frequency: f32,
+    /// The reference melody's frequency at the frame, -1.0 if it is unvoiced
+    /// there or the frame is past its end
+    pub reference: f32,
+    /// Interval above the reference rounded to whole semitones (negative
+    /// below it), 0 unless both are voiced
+    pub semitones: i32,
+    /// Deviation from that interval in cents, NaN unless both are voiced
+    pub cents: f32,
+}
+
+/// Pitch of one frame relative to a moving reference, for harmony and
+/// interval training
+/// `reference_track` holds the melody's pitch per frame (Hz, -1.0 where
+/// silent), as from [`detect_pitch_track`]; the frame `frame_index` is
+/// compared with `samples`. A player a major third above the reference
+/// scores 4 semitones, with `cents` showing how far off the interval is.
+/// Unlike the note-naming functions it takes no `a4`: an interval between
+/// two pitches is the same whatever the concert pitch, so a reference tuning
+/// would have nothing to change
+#[wasm_bindgen]
+pub fn detect_relative(
+    samples: &[f32],
+    sample_rate: f32,
+    reference_track: &[f32],
+    frame_index: usize,
+) -> RelativeResult {
+    let frequency = detect_pitch(samples, sample_rate);
+    let reference = match reference_track.get(frame_index) {
+        Some(&f) if f > 0.0 => f,
+        _ => -1.0,
+    };
+    let mut result = RelativeResult {
+        frequency,
+        reference,
+        semitones: 0,
+        cents: f32::NAN,
+    };
+    if frequency > 0.0 && reference > 0.0 {
+        let interval = 1200.0 * (frequency / reference).log2();
+        let semitones = (interval / 100.0).round();
+        result.semitones = semitones as i32;
+        result.cents = interval - 100.0 * semitones;
+    }
+    result
+}
+
+/// [`detect_pitch_track`] into a caller-owned buffer, which is cleared first
+/// Lets repeated offline runs reuse one allocation
+pub fn detect_pitch_track_into(
+    samples: &[f32],
+    sample_rate: f32,
+    frame_size: usize,
+    hop_size: usize,
+    out: &mut Vec<f32>,
+) {
+    track_into(
+        &Yin::default(),
+        samples,
+        sample_rate,
+        frame_size,
+        hop_size,
+        out,
+    );
+}
+
+/// [`detect_pitch_track`] with any [`PitchAlgorithm`] in place of YIN
+pub fn detect_pitch_track_with<A: PitchAlgorithm + ?Sized>(
+    algorithm: &A,
+    samples: &[f32],
+    sample_rate: f32,
+    frame_size: usize,
+    hop_size: usize,
+) -> Vec<f32> {
+    let mut track = Vec::new();
+    track_into(
+        algorithm,
+        samples,
+        sample_rate,
+        frame_size,
+        hop_size,
+        &mut track,
+    );
+    track
+}
+
+fn track_into<A: PitchAlgorithm + ?Sized>(
+    algorithm: &A,
+    samples: &[f32],
+    sample_rate: f32,
+    frame_size: usize,
+    hop_size: usize,
+    out: &mut Vec<f32>,
+) {
+    out.clear();
+    if frame_size == 0 || hop_size == 0 || samples.len() < frame_size {
+        return;
+    }
+    let frames = (samples.len() - frame_size) / hop_size + 1;
+    out.extend((0..frames).map(|i| {
+        let start = i * hop_size;
+        algorithm.detect(&samples[start..start + frame_size], sample_rate)
+    }));
+}
+
+/// The note held for most of a phrase, with its average deviation
+/// Every voiced frame (framed as in [`detect_pitch_track`]) votes for its
+/// nearest note at `a4` with its clarity, so brief glitches and unclear
+/// frames count for little. The returned `cents` is the clarity-weighted
+/// mean deviation of the winning note's frames.
+/// Returns None if no frame is voiced
+pub fn dominant_note(
+    samples: &[f32],
+    sample_rate: f32,
+    frame_size: usize,
+    hop_size: usize,
+    a4: f32,
+) -> Option<NoteInfo> {
+    if frame_size == 0 || hop_size == 0 || samples.len() < frame_size {
+        return None;
+    }
+    let reference = ReferencePitch::a4(a4);
+    // (midi note, total clarity, clarity-weighted cents)
+    let mut votes: Vec<(i32, f32, f32)> = Vec::new();
+    for start in (0..=samples.len() - frame_size).step_by(hop_size) {
+        let result = detect_pitch_full(
+            &samples[start..start + frame_size],
+            sample_rate,
+            DEFAULT_THRESHOLD,
+        );
+        let note = match frequency_to_note(result.frequency, reference) {
+            Some(n) => n,
+            None => continue,
+        };
+        let clarity = 1.0 - result.cmndf;
+        match votes
+            .iter_mut()
+            .find(|(midi, _, _)| *midi == note.midi_note)
+        {
+            Some((_, weight, cents)) => {
+                *weight += clarity;
+                *cents += clarity * note.cents;
+            }
+            None => votes.push((note.midi_note, clarity, clarity * note.cents)),
+        }
+    }
+
+    let (midi, weight, cents) = votes.into_iter().max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let average_cents = if weight > 0.0 { cents / weight } else { 0.0 };
+    let frequency = reference.midi_to_frequency(midi as f32) * cents_to_ratio(average_cents);
+    frequency_to_note(frequency, reference)
+}
+
+/// Number of notes played in a recording, for transcription statistics
+/// The pitch track (framed as in [`detect_pitch_track`]) is split into notes
+/// as for grading. Consecutive notes only count separately when their
+/// nearest notes at `a4` differ or an unvoiced gap separates them, so a
+/// repeated note counts twice but a slow drift within one note does not.
+/// Notes and gaps shorter than 0.1 s are ignored as glitches
+#[wasm_bindgen]
+pub fn count_distinct_notes(
+    samples: &[f32],
+    sample_rate: f32,
+    frame_size: usize,
+    hop_size: usize,
+    a4: f32,
+) -> usize {
+    let track = detect_pitch_track(samples, sample_rate, frame_size, hop_size);
+    let reference = ReferencePitch::a4(a4);
+    let mut previous: Option<i32> = None;
+    let mut count = 0;
+    for note in segment_notes(&track, slack_frames(hop_size, sample_rate)) {
+        let midi = reference.frequency_to_midi(note.frequency).round() as i32;
+        if note.after_gap || previous != Some(midi) {
+            count += 1;
+        }
+        previous = Some(midi);
+    }
+    count
+}
+
+/// A pitch track as CSV text for spreadsheets and plotting tools
+/// One header line `time_seconds,frequency_hz,note,cents`, then one line per
+/// frame with its start time (`i * hop_size / sample_rate`) and nearest note
+/// at `a4`. The track does not record its frame size, so unlike
+/// [`detect_pitch_track_timed`] and `analyze_full` this is the start of
+/// the frame, not its centre; add half the frame's duration to line the two
+/// up. Unvoiced frames keep their -1.0 frequency and leave note and cents
+/// empty. Lines end in `\n`
+#[wasm_bindgen]
+pub fn pitch_track_to_csv(track: &[f32], hop_size: usize, sample_rate: f32, a4: f32) -> String {
+    let reference = ReferencePitch::a4(a4);
+    let mut csv = String::from("time_seconds,frequency_hz,note,cents\n");
+    for (i, &frequency) in track.iter().enumerate() {
+        let time = (i * hop_size) as f32 / sample_rate;
+        let line = match frequency_to_note(frequency, reference) {
+            Some(note) => format!(
+                "{:.4},{:.2},{},{:.1}\n",
+                time, frequency, note.note_name, note.cents
+            ),
+            None => format!("{:.4},{:.2},,\n", time, frequency),
+        };
+        csv.push_str(&line);
+    }
+    csv
+}
+
+/// One frame of [`analyze_full`]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct FrameReport {
+    time: f32,
+    frequency: f32,
+    note: Option<String>,
+    cents: Option<f32>,
+    clarity: f32,
+    voiced: bool,
+}
+
+/// Every frame of a recording analyzed in one call, for offline tools
+/// feeding a web frontend
+/// Returns an array of `{ time, frequency, note, cents, clarity, voiced }`
+/// objects, framed as in [`detect_pitch_track`]: `time` is the frame centre
+/// in seconds, as in [`detect_pitch_track_timed`], `frequency` is -1.0 and
+/// `note` and `cents` are null for unvoiced frames, and `clarity` is 1.0
+/// minus the YIN trough depth
+#[cfg(feature = "serde")]
+#[wasm_bindgen]
+pub fn analyze_full(
+    samples: &[f32],
+    sample_rate: f32,
+    frame_size: usize,
+    hop_size: usize,
+    a4: f32,
+) -> JsValue {
+    let json = analyze_full_json(samples, sample_rate, frame_size, hop_size, a4);
+    // Our own JSON always parses
+    js_sys::JSON::parse(&json).unwrap_or(JsValue::NULL)
+}
+
+/// [`analyze_full`] as JSON text
+#[cfg(feature = "serde")]
+fn analyze_full_json(
+    samples: &[f32],
+    sample_rate: f32,
+    frame_size: usize,
+    hop_size: usize,
+    a4: f32,
+) -> String {
+    let reference = ReferencePitch::a4(a4);
+    let mut frames = Vec::new();
+    if frame_size > 0 && hop_size > 0 && samples.len() >= frame_size {
+        for start in (0..=samples.len() - frame_size).step_by(hop_size) {
+            let result = detect_pitch_full(
+                &samples[start..start + frame_size],
+                sample_rate,
+                DEFAULT_THRESHOLD,
+            );
+            let note = frequency_to_note(result.frequency, reference);
+            frames.push(FrameReport {
+                time: (start + frame_size / 2) as f32 / sample_rate,
+                frequency: result.frequency,
+                cents: note.as_ref().map(|n| n.cents),
+                note: note.map(|n| n.note_name),
+                clarity: (1.0 - result.cmndf).clamp(0.0, 1.0),
+                voiced: result.frequency > 0.0,
+            });
+        }
+    }
+    serde_json::to_string(&frames).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Steadiness of a sustained note, from 0.0 to 1.0
+/// `exp(-sd / 10)` where `sd` is the standard deviation of the voiced frames
+/// in cents: 1.0 for a dead-steady note, about 0.6 for a 5 cent wobble and
+/// near 0.0 for wide vibrato. Unvoiced (-1.0) frames are skipped; NaN if none
+/// are voiced
+#[wasm_bindgen]
+pub fn pitch_stability(track: &[f32]) -> f32 {
+    let cents: Vec<f32> = track
+        .iter()
+        .filter(|&&f| f > 0.0)
+        .map(|&f| 1200.0 * f.log2())
+        .collect();
+    if cents.is_empty() {
+        return f32::NAN;
+    }
+    let count = cents.len() as f32;
+    let mean = cents.iter().sum::<f32>() / count;
+    let variance = cents.iter().map(|c| (c - mean).powi(2)).sum::<f32>() / count;
+    (-variance.sqrt() / STABILITY_SCALE_CENTS).exp()
+}
+
+/// One note's share of a practice session, see [`session_report`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteSummary {
+    /// The note, with `cents` the mean deviation of its frames
+    pub note: NoteInfo,
+    /// Number of frames nearest to this note
+    pub frames: usize,
+    /// [`pitch_stability`] of this note's frames alone
+    pub stability: f32,
+}
+
+/// Summary of a practice session's pitch track
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionReport {
+    /// Every note played, the most held first
+    pub notes: Vec<NoteSummary>,
+    /// Frame-weighted mean of the per-note stabilities, so moving between
+    /// notes is not counted as wobble; NaN if no frame is voiced
+    pub stability: f32,
+    /// Frames with a detected pitch; multiply by the hop duration for the
+    /// voiced time
+    pub voiced_frames: usize,
+    pub total_frames: usize,
+}
+
+/// Aggregate a pitch track into a per-note practice summary
+/// Each voiced frame is assigned to its nearest note at `a4`; every note
+/// reports its frame count, mean cents deviation and stability.
+/// Returns a report with no notes if no frame is voiced
+pub fn session_report(track: &[f32], a4: f32) -> SessionReport {
+    let reference = ReferencePitch::a4(a4);
+    // (midi note, frequencies, summed cents)
+    let mut groups: Vec<(i32, Vec<f32>, f32)> = Vec::new();
+    for &frequency in track {
+        let note = match frequency_to_note(frequency, reference) {
+            Some(n) => n,
+            None => continue,
+        };
+        match groups
+            .iter_mut()
+            .find(|(midi, _, _)| *midi == note.midi_note)
+        {
+            Some((_, frequencies, cents)) => {
+                frequencies.push(frequency);
+                *cents += note.cents;
+            }
+            None => groups.push((note.midi_note, vec![frequency], note.cents)),
+        }
+    }
+
+    let mut notes: Vec<NoteSummary> = groups
+        .into_iter()
+        .filter_map(|(midi, frequencies, cents)| {
+            let average_cents = cents / frequencies.len() as f32;
+            let frequency =
+                reference.midi_to_frequency(midi as f32) * cents_to_ratio(average_cents);
+            Some(NoteSummary {
+                note: frequency_to_note(frequency, reference)?,
+                frames: frequencies.len(),
+                stability: pitch_stability(&frequencies),
+            })
+        })
+        .collect();
+    notes.sort_by(|a, b| {
+        b.frames
+            .cmp(&a.frames)
+            .then(a.note.midi_note.cmp(&b.note.midi_note))
+    });
+
+    let voiced_frames: usize = notes.iter().map(|n| n.frames).sum();
+    let stability = if voiced_frames == 0 {
+        f32::NAN
+    } else {
+        notes
+            .iter()
+            .map(|n| n.stability * n.frames as f32)
+            .sum::<f32>()
+            / voiced_frames as f32
+    };
+    SessionReport {
+        notes,
+        stability,
+        voiced_frames,
+        total_frames: track.len(),
+    }
+}
+
+/// Gradual pitch drift over a sustained note, from [`drift_analysis`]
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriftInfo {
+    /// Slope of the fitted line, positive when drifting sharp
+    pub slope_cents_per_second: f32,
+    /// How far the fitted line moves from the first voiced frame to the
+    /// last, e.g. +15.0 for "went 15 cents sharp over the note"
+    pub total_drift_cents: f32,
+}
+
+/// Least-squares line through the voiced frames of a track, in cents
+/// Frames are `hop_size` samples apart, as from [`detect_pitch_track`];
+/// unvoiced (-1.0) frames are skipped without breaking the timeline. A
+/// straight line ignores vibrato and picks out the slow trend under it.
+/// Returns NaN fields if fewer than two frames are voiced
+#[wasm_bindgen]
+pub fn drift_analysis(track: &[f32], hop_size: usize, sample_rate: f32) -> DriftInfo {
+    let frame_seconds = hop_size as f32 / sample_rate;
+    let points: Vec<(f32, f32)> = track
+        .iter()
+        .enumerate()
+        .filter(|(_, &f)| f > 0.0)
+        .map(|(i, &f)| (i as f32 * frame_seconds, 1200.0 * f.log2()))
+        .collect();
+    let unknown = DriftInfo {
+        slope_cents_per_second: f32::NAN,
+        total_drift_cents: f32::NAN,
+    };
+    if points.len() < 2 {
+        return unknown;
+    }
+    let count = points.len() as f32;
+    let mean_time = points.iter().map(|p| p.0).sum::<f32>() / count;
+    let mean_cents = points.iter().map(|p| p.1).sum::<f32>() / count;
+    let (covariance, variance) = points.iter().fold((0.0f32, 0.0f32), |(c, v), &(t, y)| {
+        let dt = t - mean_time;
+        (c + dt * (y - mean_cents), v + dt * dt)
+    });
+    if variance <= 0.0 {
+        return unknown;
+    }
+
+    let slope = covariance / variance;
+    let span = points[points.len() - 1].0 - points[0].0;
+    DriftInfo {
+        slope_cents_per_second: slope,
+        total_drift_cents: slope * span,
+    }
+}
+
+/// Histogram of the pitches detected over a long recording
+/// Bin `i` counts frames whose pitch lies in
+/// `MIN_FREQUENCY * 2^(i / bins_per_octave)` up to the next bin edge, covering
+/// the detection range; unvoiced frames are skipped. Framing is as in
+/// [`detect_pitch_track`]. Returns an empty histogram if `bins_per_octave`
+/// is zero
+#[wasm_bindgen]
+pub fn pitch_histogram(
+    samples: &[f32],
+    sample_rate: f32,
+    frame_size: usize,
+    hop_size: usize,
+    bins_per_octave: usize,
+) -> Vec<u32> {
+    if bins_per_octave == 0 {
+        return Vec::new();
+    }
+    let per_octave = bins_per_octave as f32;
+    let bins = (per_octave * (MAX_FREQUENCY / MIN_FREQUENCY).log2()).ceil() as usize;
+    let mut histogram = vec![0; bins];
+    for frequency in detect_pitch_track(samples, sample_rate, frame_size, hop_size) {
+        if frequency <= 0.0 {
+            continue;
+        }
+        let bin = (per_octave * (frequency / MIN_FREQUENCY).log2()).floor() as usize;
+        histogram[bin.min(bins - 1)] += 1;
+    }
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+
+    #[test]
+    fn test_track_follows_note_change() {
+        let sample_rate = 44100.0;
+        let mut samples = generate_tone(220.0, sample_rate, 8192, Waveform::Sine);
+        samples.extend(generate_tone(330.0, sample_rate, 8192, Waveform::Sine));
+
+        let track = detect_pitch_track(&samples, sample_rate, 2048, 1024);
+        assert_eq!(track.len(), 15);
+        assert!((track[0] - 220.0).abs() < 2.0, "{:?}", track);
+        assert!((track[14] - 330.0).abs() < 2.0, "{:?}", track);
+        assert!(detect_pitch_track(&samples, sample_rate, 2048, 0).is_empty());
+    }
+
+    #[test]
+    fn test_timed_track() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(330.0, sample_rate, 8192, Waveform::Sine);
+        let timed = detect_pitch_track_timed(&samples, sample_rate, 2048, 512);
+        let track = detect_pitch_track(&samples, sample_rate, 2048, 512);
+        assert_eq!(timed.len(), track.len());
+
+        assert!((timed[0].0 - 1024.0 / sample_rate).abs() < 1e-7);
+        for pair in timed.windows(2) {
+            assert!((pair[1].0 - pair[0].0 - 512.0 / sample_rate).abs() < 1e-6);
+        }
+        assert!(timed.iter().zip(&track).all(|(t, &f)| t.1 == f));
+    }
+
+    #[test]
+    fn test_track_overlap() {
+        assert_eq!(overlap_hop(1024, 50.0), Some(512));
+        assert_eq!(overlap_hop(1024, 0.0), Some(1024));
+        assert_eq!(overlap_hop(1024, 99.99), Some(1));
+        assert_eq!(overlap_hop(1024, 100.0), None);
+        assert_eq!(overlap_hop(1024, -5.0), None);
+
+        let sample_rate = 44100.0;
+        let samples = generate_tone(440.0, sample_rate, 8192, Waveform::Sine);
+        let track = detect_pitch_track_overlap(&samples, sample_rate, 1024, 50.0);
+        assert_eq!(track.len(), 15);
+        assert_eq!(track, detect_pitch_track(&samples, sample_rate, 1024, 512));
+        assert!(detect_pitch_track_overlap(&samples, sample_rate, 1024, f32::NAN).is_empty());
+    }
+
+    #[test]
+    fn test_segments_of_two_notes() {
+        let sample_rate = 44100.0;
+        let mut samples = generate_tone(196.0, sample_rate, 5000, Waveform::Saw);
+        samples.extend(generate_tone(293.66, sample_rate, 3000, Waveform::Saw));
+
+        let segments = [(0, 5000), (5000, 8000), (4000, 4000), (7000, 9000)];
+        let pitches = detect_pitch_segments(&samples, sample_rate, &segments);
+        assert_eq!(pitches.len(), 4);
+        assert!((pitches[0] - 196.0).abs() < 1.0, "{:?}", pitches);
+        assert!((pitches[1] - 293.66).abs() < 1.0, "{:?}", pitches);
+        assert_eq!(pitches[2..], [-1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_track_into_reuses_buffer() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(440.0, sample_rate, 8192, Waveform::Saw);
+        let expected = detect_pitch_track(&samples, sample_rate, 2048, 512);
+
+        let mut out = vec![0.0; 3];
+        detect_pitch_track_into(&samples, sample_rate, 2048, 512, &mut out);
+        assert_eq!(out, expected);
+        let capacity = out.capacity();
+        detect_pitch_track_into(&samples, sample_rate, 2048, 512, &mut out);
+        assert_eq!(out, expected);
+        assert_eq!(out.capacity(), capacity);
+    }
+
+    /// Rising zero crossings per second, fine for a pure tone
+    struct ZeroCrossings;
+
+    impl PitchAlgorithm for ZeroCrossings {
+        fn detect(&self, samples: &[f32], sample_rate: f32) -> f32 {
+            let rising = samples
+                .windows(2)
+                .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+                .count();
+            if rising == 0 {
+                return -1.0;
+            }
+            rising as f32 * sample_rate / samples.len() as f32
+        }
+    }
+
+    #[test]
+    fn test_track_with_custom_algorithm() {
+        let sample_rate = 44100.0;
+        let mut samples = generate_tone(220.0, sample_rate, 8192, Waveform::Sine);
+        samples.extend(generate_tone(441.0, sample_rate, 8192, Waveform::Sine));
+
+        let custom = detect_pitch_track_with(&ZeroCrossings, &samples, sample_rate, 2048, 1024);
+        let yin = detect_pitch_track(&samples, sample_rate, 2048, 1024);
+        assert_eq!(custom.len(), yin.len());
+        // Whole crossings per frame only resolve about 21.5 Hz
+        assert!((custom[0] - 220.0).abs() < 22.0, "{:?}", custom);
+        assert!((custom[14] - 441.0).abs() < 22.0, "{:?}", custom);
+
+        // Built-ins go through the same generic path
+        assert_eq!(
+            detect_pitch_track_with(&Yin::default(), &samples, sample_rate, 2048, 1024),
+            yin
+        );
+        let algorithms: [&dyn PitchAlgorithm; 3] = [
+            &Yin::default(),
+            &crate::Autocorrelation,
+            &crate::Algorithm::Yin,
+        ];
+        for algorithm in algorithms {
+            let track = detect_pitch_track_with(algorithm, &samples, sample_rate, 2048, 1024);
+            assert!((track[0] - 220.0).abs() < 2.0, "{:?}", track);
+        }
+    }
+
+    #[test]
+    fn test_dominant_note_ignores_glitch() {
+        let sample_rate = 44100.0;
+        // Slightly sharp A4 with a brief jump to E5 in the middle
+        let sharp_a4 = 440.0 * 2f32.powf(6.0 / 1200.0);
+        let mut samples = generate_tone(sharp_a4, sample_rate, 16384, Waveform::Saw);
+        samples.extend(generate_tone(659.26, sample_rate, 3072, Waveform::Saw));
+        samples.extend(generate_tone(sharp_a4, sample_rate, 16384, Waveform::Saw));
+
+        let note = dominant_note(&samples, sample_rate, 2048, 1024, 440.0).unwrap();
+        assert_eq!(note.note_name, "A4");
+        assert!((note.cents - 6.0).abs() < 1.0, "{:?}", note);
+
+        assert_eq!(
+            dominant_note(&[0.0; 8192], sample_rate, 2048, 1024, 440.0),
+            None
+        );
+        assert_eq!(dominant_note(&samples, sample_rate, 2048, 0, 440.0), None);
+    }
+
+    #[test]
+    fn test_track_csv() {
+        let track = [440.0, -1.0, 330.0 * 2f32.powf(10.0 / 1200.0)];
+        let csv = pitch_track_to_csv(&track, 512, 44100.0, 440.0);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "time_seconds,frequency_hz,note,cents");
+        assert_eq!(lines[1], "0.0000,440.00,A4,0.0");
+        assert_eq!(lines[2], "0.0116,-1.00,,");
+        assert_eq!(lines[3], "0.0232,331.91,E4,12.0");
+        assert!(csv.ends_with('\n'));
+
+        assert_eq!(
+            pitch_track_to_csv(&[], 512, 44100.0, 440.0),
+            "time_seconds,frequency_hz,note,cents\n"
+        );
+    }
+
+    #[test]
+    fn test_pitch_stability() {
+        let steady = [440.0, 440.1, -1.0, 439.9, 440.0];
+        assert!(pitch_stability(&steady) > 0.95);
+
+        // ±20 cents of vibrato
+        let wobbling: Vec<f32> = (0..40)
+            .map(|i| 440.0 * 2f32.powf(20.0 * (i as f32 * 0.5).sin() / 1200.0))
+            .collect();
+        let score = pitch_stability(&wobbling);
+        assert!(score < 0.5, "{}", score);
+
+        assert!(pitch_stability(&[-1.0, -1.0]).is_nan());
+    }
+
+    #[test]
+    fn test_histogram_peaks_at_played_note() {
+        let sample_rate = 44100.0;
+        let samples = generate_tone(440.0, sample_rate, 44100, Waveform::Saw);
+        let histogram = pitch_histogram(&samples, sample_rate, 2048, 1024, 12);
+
+        // 440 Hz is 34.5 semitones above 60 Hz
+        let peak = (0..histogram.len()).max_by_key(|&i| histogram[i]).unwrap();
+        assert_eq!(peak, 34);
+        let frames = detect_pitch_track(&samples, sample_rate, 2048, 1024).len() as u32;
+        assert_eq!(histogram[peak], frames);
+        assert_eq!(histogram.iter().sum::<u32>(), frames);
+
+        assert!(pitch_histogram(&[0.0; 8192], sample_rate, 2048, 1024, 12)
+            .iter()
+            .all(|&count| count == 0));
+        assert!(pitch_histogram(&samples, sample_rate, 2048, 1024, 0).is_empty());
+    }
+
+    #[test]
+    fn test_session_report_two_notes() {
+        // A steady, slightly sharp A3 held longer than a wobbly, flat E4
+        let mut track: Vec<f32> = (0..30).map(|_| 220.0 * cents_to_ratio(5.0)).collect();
+        track.extend([-1.0; 4]);
+        track.extend((0..20).map(|i| {
+            let wobble = if i % 2 == 0 { 8.0 } else { -8.0 };
+            329.63 * cents_to_ratio(-10.0 + wobble)
+        }));
+
+        let report = session_report(&track, 440.0);
+        assert_eq!(report.total_frames, 54);
+        assert_eq!(report.voiced_frames, 50);
+        assert_eq!(report.notes.len(), 2);
+
+        let (a3, e4) = (&report.notes[0], &report.notes[1]);
+        assert_eq!((a3.note.note_name.as_str(), a3.frames), ("A3", 30));
+        assert_eq!((e4.note.note_name.as_str(), e4.frames), ("E4", 20));
+        assert!((a3.note.cents - 5.0).abs() < 0.1, "{:?}", a3);
+        assert!((e4.note.cents + 10.0).abs() < 0.1, "{:?}", e4);
+        assert!(a3.stability > 0.99 && e4.stability < 0.5);
+        // Weighted between the two, and unaffected by the note change
+        assert!(report.stability > e4.stability && report.stability < a3.stability);
+        assert!(report.stability > pitch_stability(&track));
+
+        let silent = session_report(&[-1.0; 3], 440.0);
+        assert!(silent.notes.is_empty() && silent.stability.is_nan());
+    }
+
+    #[test]
+    fn test_count_distinct_notes() {
+        let sample_rate = 44100.0;
+        // A3, C4, then A3 again after a rest
+        let mut samples = generate_tone(220.0, sample_rate, 22050, Waveform::Saw);
+        samples.extend(generate_tone(261.63, sample_rate, 22050, Waveform::Saw));
+        samples.extend(vec![0.0; 11025]);
+        samples.extend(generate_tone(220.0, sample_rate, 22050, Waveform::Saw));
+        assert_eq!(
+            count_distinct_notes(&samples, sample_rate, 2048, 512, 440.0),
+            3
+        );
+
+        // The same note played twice with a rest between
+        let mut repeated = generate_tone(330.0, sample_rate, 22050, Waveform::Saw);
+        repeated.extend(vec![0.0; 11025]);
+        repeated.extend(generate_tone(330.0, sample_rate, 22050, Waveform::Saw));
+        assert_eq!(
+            count_distinct_notes(&repeated, sample_rate, 2048, 512, 440.0),
+            2
+        );
+
+        assert_eq!(
+            count_distinct_notes(&[0.0; 8192], sample_rate, 2048, 512, 440.0),
+            0
+        );
+    }
+
+    #[test]
+    fn test_drift_analysis_rising_note() {
+        // 2 s of 10 ms frames drifting from 440 Hz to 15 cents sharp, with
+        // 5 cent vibrato and a dropout on top
+        let (hop_size, sample_rate) = (441, 44100.0);
+        let mut track: Vec<f32> = (0..=200)
+            .map(|i| {
+                let vibrato = 5.0 * (i as f32 * 0.6).sin();
+                440.0 * cents_to_ratio(15.0 * i as f32 / 200.0 + vibrato)
+            })
+            .collect();
+        track[50..60].fill(-1.0);
+
+        let drift = drift_analysis(&track, hop_size, sample_rate);
+        assert!(
+            (drift.slope_cents_per_second - 7.5).abs() < 0.5,
+            "{:?}",
+            drift
+        );
+        assert!((drift.total_drift_cents - 15.0).abs() < 1.0, "{:?}", drift);
+
+        let falling: Vec<f32> = track.iter().rev().copied().collect();
+        assert!(drift_analysis(&falling, hop_size, sample_rate).slope_cents_per_second < -7.0);
+        let single = drift_analysis(&[-1.0, 440.0, -1.0], hop_size, sample_rate);
+        assert!(single.slope_cents_per_second.is_nan() && single.total_drift_cents.is_nan());
+    }
+
+    #[test]
+    fn test_relative_to_reference_melody() {
+        let sample_rate = 44100.0;
+        let melody = [-1.0, 220.0, 246.94];
+        // A major third above A3, a little sharp
+        let third = generate_tone(
+            277.18 * cents_to_ratio(6.0),
+            sample_rate,
+            2048,
+            Waveform::Saw,
+        );
+
+        let result = detect_relative(&third, sample_rate, &melody, 1);
+        assert_eq!(result.semitones, 4);
+        assert!((result.cents - 6.0).abs() < 3.0, "{:?}", result);
+        // Against the next note of the melody it is a whole tone up
+        let result = detect_relative(&third, sample_rate, &melody, 2);
+        assert_eq!(result.semitones, 2);
+
+        for (samples, frame) in [(&third[..], 0), (&third[..], 3), (&[0.0; 2048][..], 1)] {
+            let result = detect_relative(samples, sample_rate, &melody, frame);
+            assert_eq!(result.semitones, 0);
+            assert!(result.cents.is_nan(), "{:?}", result);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_analyze_full_frames() {
+        let sample_rate = 44100.0;
+        let mut samples = generate_tone(440.0, sample_rate, 8192, Waveform::Saw);
+        samples.extend(vec![0.0; 4096]);
+        let json = analyze_full_json(&samples, sample_rate, 2048, 1024, 440.0);
+        let frames: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            frames.len(),
+            detect_pitch_track(&samples, sample_rate, 2048, 1024).len()
+        );
+
+        let first = &frames[0];
+        let timed = detect_pitch_track_timed(&samples, sample_rate, 2048, 1024);
+        for (frame, (time, _)) in frames.iter().zip(&timed) {
+            assert!((frame["time"].as_f64().unwrap() - *time as f64).abs() < 1e-6);
+        }
+        assert!(
+            (first["frequency"].as_f64().unwrap() - 440.0).abs() < 2.0,
+            "{}",
+            first
+        );
+        assert_eq!(first["note"], "A4");
+        assert!(first["cents"].as_f64().unwrap().abs() < 8.0, "{}", first);
+        assert!(first["clarity"].as_f64().unwrap() > 0.9, "{}", first);
+        assert_eq!(first["voiced"], true);
+
+        let last = &frames[frames.len() - 1];
+        assert_eq!(last["frequency"], -1.0);
+        assert!(last["note"].is_null() && last["cents"].is_null());
+        assert_eq!(last["voiced"], false);
+        assert_eq!(
+            analyze_full_json(&samples, sample_rate, 0, 1024, 440.0),
+            "[]"
+        );
+    }
+}
@@ -0,0 +1,1435 @@
+use wasm_bindgen::prelude::*;
+
+use crate::config::DetectorConfig;
+use crate::filter::Biquad;
+use crate::{calculate_rms, DEFAULT_THRESHOLD, MAX_FREQUENCY, MIN_FREQUENCY, RMS_THRESHOLD};
+
+/// Fraction of the lag range beyond which the best tau is considered to be
+/// pressing against the buffer-size ceiling
+const PERIOD_CEILING_FRACTION: f32 = 0.95;
+
+/// Shortest period (in samples) for which three-point parabolic interpolation
+/// still gives a usable estimate
+const MIN_PRACTICAL_TAU: f32 = 4.0;
+
+/// Relative tolerance for treating one period as an integer multiple of another
+const SUBHARMONIC_TOLERANCE: f32 = 0.03;
+
+/// A shorter period at most this much shallower than a multiple of itself is
+/// considered the same periodicity
+const SUBHARMONIC_DEPTH_EPSILON: f32 = 0.01;
+
+/// `detect_pitch_low` searches lags up to this fraction of the decimated
+/// buffer, leaving at least a third of it to compare at the longest lag
+const LOW_MAX_LAG_FRACTION: f32 = 2.0 / 3.0;
+
+/// Anti-alias cutoff as a fraction of the decimated sample rate
+const DECIMATION_CUTOFF_FRACTION: f32 = 0.4;
+
+/// Largest sub-sample correction parabolic interpolation may apply, in lags
+const MAX_INTERPOLATION_OFFSET: f32 = 0.5;
+
+/// Periods the comparison window must hold for the full-period trough to
+/// stand out clearly from the half-period one
+const OCTAVE_CONFUSION_PERIODS: f32 = 2.0;
+
+/// Full YIN detection result with diagnostics
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PitchResult {
+    /// Detected frequency in Hz, or -1.0 if no pitch detected
+    pub frequency: f32,
+    /// Interpolated period (in samples) of the best candidate, 0.0 if none
+    pub tau: f32,
+    /// CMNDF value at the best candidate (lower means more periodic)
+    pub cmndf: f32,
+    /// The best candidate lies at the far end of the lag range, so the true
+    /// fundamental is likely below what this buffer can resolve
+    pub period_exceeds_buffer: bool,
+    /// Number of samples compared at each lag (0 if detection did not run)
+    pub analysis_len: usize,
+    /// Integer lag of the best candidate, 0 if none
+    pub best_tau: usize,
+    /// Error bar on `frequency` in Hz, 0.0 if no pitch detected
+    /// Derived from the curvature of the CMNDF trough: a sharp, deep trough
+    /// pins the period down, a shallow or flat one leaves it loose
+    pub frequency_uncertainty_hz: f32,
+    /// Depth (1.0 minus the CMNDF) of the second-deepest trough over that of
+    /// the deepest, an octave-ambiguity risk score
+    /// Troughs that merely repeat a shorter, equally deep period are not
+    /// counted, so a clean tone with no rival trough scores 0.0 while two
+    /// nearly equal candidates score close to 1.0. Only [`detect_pitch_full`]
+    /// and the session's YIN analysis compute it; other paths leave 0.0
+    pub ambiguity_ratio: f32,
+}
+
+impl PitchResult {
+    pub(crate) const UNVOICED: Self = Self {
+        frequency: -1.0,
+        tau: 0.0,
+        cmndf: 1.0,
+        period_exceeds_buffer: false,
+        analysis_len: 0,
+        best_tau: 0,
+        frequency_uncertainty_hz: 0.0,
+        ambiguity_ratio: 0.0,
+    };
+
+    /// `(analysis_len, best_tau)`: samples `0..analysis_len` were compared
+    /// against the same span shifted by `best_tau`, so the UI can draw exactly
+    /// one detected period starting at any sample in that span
+    pub fn used_range(&self) -> (usize, usize) {
+        (self.analysis_len, self.best_tau)
+    }
+}
+
+/// Read-only view of a mono signal, so detection can run over layouts other
+/// than a contiguous slice without copying
+pub(crate) trait SampleSource {
+    fn sample_count(&self) -> usize;
+    fn sample(&self, index: usize) -> f32;
+}
+
+impl SampleSource for [f32] {
+    fn sample_count(&self) -> usize {
+        self.len()
+    }
+
+    fn sample(&self, index: usize) -> f32 {
+        self[index]
+    }
+}
+
+/// One channel of an interleaved multi-channel buffer
+pub(crate) struct Strided<'a> {
+    data: &'a [f32],
+    stride: usize,
+    offset: usize,
+}
+
+impl<'a> Strided<'a> {
+    /// Caller guarantees `offset < stride`
+    pub(crate) fn new(data: &'a [f32], stride: usize, offset: usize) -> Self {
+        Self {
+            data,
+            stride,
+            offset,
+        }
+    }
+}
+
+impl SampleSource for Strided<'_> {
+    fn sample_count(&self) -> usize {
+        (self.data.len() + self.stride - 1 - self.offset) / self.stride
+    }
+
+    fn sample(&self, index: usize) -> f32 {
+        self.data[index * self.stride + self.offset]
+    }
+}
+
+fn source_rms<S: SampleSource + ?Sized>(samples: &S) -> f32 {
+    let len = samples.sample_count();
+    if len == 0 {
+        return 0.0;
+    }
+    let sum: f32 = (0..len).map(|i| samples.sample(i).powi(2)).sum();
+    (sum / len as f32).sqrt()
+}
+
+/// YIN pitch detection returning the full result with diagnostics
+#[wasm_bindgen]
+pub fn detect_pitch_full(samples: &[f32], sample_rate: f32, threshold: f32) -> PitchResult {
+    let config = DetectorConfig::default().with_threshold(threshold);
+    detect_full_source(samples, sample_rate, &config)
+}
+
+pub(crate) fn detect_full_source<S: SampleSource + ?Sized>(
+    samples: &S,
+    sample_rate: f32,
+    config: &DetectorConfig,
+) -> PitchResult {
+    let buffer_size = samples.sample_count();
+    if buffer_size < 2 {
+        return PitchResult::UNVOICED;
+    }
+
+    let half_buffer_size = buffer_size / 2;
+
+    // Check if signal has enough energy
+    let rms = source_rms(samples);
+    if rms < config.rms_threshold {
+        return PitchResult::UNVOICED;
+    }
+
+    let mut difference = vec![0.0f32; half_buffer_size];
+    difference_function(samples, &mut difference);
+    let mut cmndf = vec![0.0f32; half_buffer_size];
+    cumulative_mean_normalized_difference(&difference, &mut cmndf);
+
+    // Only the full result pays for the trough census; the frequency-only
+    // paths sharing `result_from_cmndf` leave the ratio at 0.0
+    let mut result = result_from_cmndf(&cmndf, half_buffer_size, sample_rate, config);
+    result.ambiguity_ratio = ambiguity_ratio(&cmndf);
+    result
+}
+
+/// YIN pitch detection that reads every sample at every lag
+/// The standard path compares a fixed window of `len / 2` samples, which
+/// leaves the last sample of an odd-length buffer unused. Here lags run up to
+/// `ceil(len / 2)` and each lag compares all `len - tau` available pairs,
+/// normalized to a per-pair mean so lags stay comparable
+#[wasm_bindgen]
+pub fn detect_pitch_full_lag_range(
+    samples: &[f32],
+    sample_rate: f32,
+    threshold: f32,
+) -> PitchResult {
+    let config = DetectorConfig::default().with_threshold(threshold);
+    full_lag_range_cfg(samples, sample_rate, &config)
+}
+
+pub(crate) fn full_lag_range_cfg(
+    samples: &[f32],
+    sample_rate: f32,
+    config: &DetectorConfig,
+) -> PitchResult {
+    let buffer_size = samples.len();
+    if buffer_size < 2 || calculate_rms(samples) < config.rms_threshold {
+        return PitchResult::UNVOICED;
+    }
+
+    let lag_count = buffer_size.div_ceil(2);
+    let mut difference = vec![0.0f32; lag_count];
+    for (tau, value) in difference.iter_mut().enumerate() {
+        let pairs = buffer_size - tau;
+        let sum: f32 = (0..pairs)
+            .map(|i| {
+                let delta = samples[i] - samples[i + tau];
+                delta * delta
+            })
+            .sum();
+        *value = sum / pairs as f32;
+    }
+
+    let mut result = result_from_difference(&difference, lag_count, sample_rate, config);
+    if result.analysis_len > 0 {
+        result.analysis_len = buffer_size - result.best_tau;
+    }
+    result
+}
+
+/// YIN pitch detection with a chosen share of the buffer spent on lags
+/// The standard split compares `len / 2` samples at lags below `len / 2`.
+/// Here lags run below `fraction * len` and each compares the remaining
+/// `len - max_lag` samples, so `i + tau` stays inside the buffer: a larger
+/// fraction reaches lower notes from the same buffer at the price of fewer
+/// samples per comparison, a smaller one the reverse. `fraction` must be in
+/// `(0, 1)`.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_with_lag_fraction(
+    samples: &[f32],
+    sample_rate: f32,
+    threshold: f32,
+    fraction: f32,
+) -> f32 {
+    let buffer_size = samples.len();
+    if !(fraction > 0.0 && fraction < 1.0)
+        || buffer_size < 2
+        || calculate_rms(samples) < RMS_THRESHOLD
+    {
+        return -1.0;
+    }
+    let lag_count = ((buffer_size as f32 * fraction) as usize).min(buffer_size - 1);
+    let window = buffer_size - lag_count;
+    let difference: Vec<f32> = (0..lag_count)
+        .map(|tau| lag_difference(samples, window, tau))
+        .collect();
+
+    let config = DetectorConfig::default().with_threshold(threshold);
+    result_from_difference(&difference, window, sample_rate, &config).frequency
+}
+
+/// Detected frequency at each of several thresholds, from one CMNDF
+/// Shows how sensitive a frame's result is to the threshold: typically -1.0
+/// below some threshold, then the pitch, and possibly an octave error at
+/// very lax thresholds.
+/// Returns one entry per threshold, in Hz or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn threshold_sweep(samples: &[f32], sample_rate: f32, thresholds: &[f32]) -> Vec<f32> {
+    let cmndf = match gated_cmndf(samples) {
+        Some(c) => c,
+        None => return vec![-1.0; thresholds.len()],
+    };
+    thresholds
+        .iter()
+        .map(|&threshold| match absolute_threshold(&cmndf, threshold) {
+            Some(tau) => {
+                let frequency = sample_rate / parabolic_interpolation(&cmndf, tau);
+                if (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+                    frequency
+                } else {
+                    -1.0
+                }
+            }
+            None => -1.0,
+        })
+        .collect()
+}
+
+/// YIN pitch detection treating the buffer as one period of a loop
+/// `samples[i + tau]` wraps around the end, so every lag compares all `len`
+/// samples and lags run up to `len / 2`, against `len / 2` samples and lags
+/// below that on the linear path. Only correct when the buffer is exactly a
+/// whole number of periods (e.g. a seamless loop); the seam of any other
+/// buffer shows up as a discontinuity at every lag.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_circular(samples: &[f32], sample_rate: f32) -> f32 {
+    let buffer_size = samples.len();
+    if buffer_size < 2 || calculate_rms(samples) < RMS_THRESHOLD {
+        return -1.0;
+    }
+
+    let mut difference = vec![0.0f32; buffer_size / 2 + 1];
+    for (tau, value) in difference.iter_mut().enumerate() {
+        *value = (0..buffer_size)
+            .map(|i| {
+                let delta = samples[i] - samples[(i + tau) % buffer_size];
+                delta * delta
+            })
+            .sum();
+    }
+
+    result_from_difference(
+        &difference,
+        buffer_size,
+        sample_rate,
+        &DetectorConfig::default(),
+    )
+    .frequency
+}
+
+/// YIN pitch detection searching lags up to `max_tau` only
+/// Work is proportional to the number of lags searched, so a cap bounds the
+/// worst-case cost, at the price of a hard floor of `sample_rate / max_tau` Hz
+/// on detectable pitches. `max_tau` is clamped to the usual `len / 2` lags.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_capped(
+    samples: &[f32],
+    sample_rate: f32,
+    threshold: f32,
+    max_tau: usize,
+) -> f32 {
+    let buffer_size = samples.len();
+    if buffer_size < 2 || calculate_rms(samples) < RMS_THRESHOLD {
+        return -1.0;
+    }
+
+    let window = buffer_size / 2;
+    let lag_count = max_tau.saturating_add(1).min(window);
+    let mut difference = vec![0.0f32; lag_count];
+    for (tau, value) in difference.iter_mut().enumerate() {
+        *value = (0..window)
+            .map(|i| {
+                let delta = samples[i] - samples[i + tau];
+                delta * delta
+            })
+            .sum();
+    }
+
+    let config = DetectorConfig::default().with_threshold(threshold);
+    result_from_difference(&difference, window, sample_rate, &config).frequency
+}
+
+/// YIN pitch detection for notes too low for the standard lag range
+/// The standard path only searches periods up to half the buffer. Here the
+/// signal is low-passed and decimated by `decimation`, which makes it cheap
+/// to search lags up to two thirds of the buffer (normalized per compared
+/// pair, as in `detect_pitch_full_lag_range`), and the range floor is the
+/// lowest frequency whose period fits those lags. Decimation itself does not
+/// lengthen the periods that fit; it trades high-frequency resolution for the
+/// cost of the longer search. A `decimation` of 0 is treated as 1.
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_low(samples: &[f32], sample_rate: f32, decimation: usize) -> f32 {
+    let decimation = decimation.max(1);
+    if samples.len() < 2 || calculate_rms(samples) < RMS_THRESHOLD {
+        return -1.0;
+    }
+
+    let decimated_rate = sample_rate / decimation as f32;
+    let mut filtered = samples.to_vec();
+    if decimation > 1 {
+        // Two cascaded stages for a steeper anti-alias slope
+        for _ in 0..2 {
+            Biquad::low_pass(decimated_rate * DECIMATION_CUTOFF_FRACTION, sample_rate)
+                .process_in_place(&mut filtered);
+        }
+    }
+    let decimated: Vec<f32> = filtered.iter().step_by(decimation).copied().collect();
+
+    let buffer_size = decimated.len();
+    let lag_count = ((buffer_size as f32 * LOW_MAX_LAG_FRACTION) as usize).max(1);
+    let mut difference = vec![0.0f32; lag_count];
+    for (tau, value) in difference.iter_mut().enumerate() {
+        let pairs = buffer_size - tau;
+        let sum: f32 = (0..pairs)
+            .map(|i| {
+                let delta = decimated[i] - decimated[i + tau];
+                delta * delta
+            })
+            .sum();
+        *value = sum / pairs as f32;
+    }
+
+    let config = DetectorConfig::default().with_frequency_range(
+        decimated_rate / lag_count as f32,
+        MAX_FREQUENCY.min(decimated_rate * DECIMATION_CUTOFF_FRACTION),
+    );
+    result_from_difference(&difference, buffer_size, decimated_rate, &config).frequency
+}
+
+/// YIN detection biased toward the fundamental
+/// Plain YIN takes the first trough under the threshold, which on low notes
+/// can be a harmonic's basin. Here every trough within `margin` of the
+/// deepest one is a candidate and the longest period wins, except troughs that
+/// merely repeat a shorter, equally deep period (those are subharmonics).
+/// Returns the detected frequency in Hz, or -1.0 if no pitch detected
+#[wasm_bindgen]
+pub fn detect_pitch_prefer_fundamental(
+    samples: &[f32],
+    sample_rate: f32,
+    threshold: f32,
+    margin: f32,
+) -> f32 {
+    let cmndf = match gated_cmndf(samples) {
+        Some(c) => c,
+        None => return -1.0,
+    };
+
+    let deepest = match deepest_trough(&cmndf) {
+        Some(t) if cmndf[t] < threshold => cmndf[t],
+        _ => return -1.0,
+    };
+
+    let candidates: Vec<usize> = local_minima(&cmndf)
+        .filter(|&tau| cmndf[tau] <= deepest + margin)
+        .collect();
+
+    let tau = match candidates
+        .iter()
+        .rev()
+        .find(|&&tau| !is_subharmonic_repeat(&cmndf, &candidates, tau))
+    {
+        Some(&t) => t,
+        None => return -1.0,
+    };
+
+    let frequency = sample_rate / parabolic_interpolation(&cmndf, tau);
+    if (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+        frequency
+    } else {
+        -1.0
+    }
+}
+
+/// YIN detection that stops computing lags as soon as the answer is known
+/// Lags are evaluated one at a time, through the CMNDF, in increasing order.
+/// A voiced frame stops at the bottom of the first trough under the
+/// threshold; an unvoiced one stops at the longest lag that could still map
+/// above the minimum frequency, since a later crossing would be rejected
+/// anyway. Returns the same result as `detect_pitch_with_threshold`
+#[wasm_bindgen]
+pub fn detect_pitch_fast_reject(samples: &[f32], sample_rate: f32, threshold: f32) -> f32 {
+    fast_reject_source(samples, sample_rate, threshold)
+}
+
+fn fast_reject_source<S: SampleSource + ?Sized>(
+    samples: &S,
+    sample_rate: f32,
+    threshold: f32,
+) -> f32 {
+    let buffer_size = samples.sample_count();
+    if buffer_size < 2 || source_rms(samples) < RMS_THRESHOLD {
+        return -1.0;
+    }
+
+    let window = buffer_size / 2;
+    let last_useful_tau = (sample_rate / MIN_FREQUENCY + MAX_INTERPOLATION_OFFSET).ceil() as usize;
+    let mut cmndf = Vec::with_capacity(window);
+    cmndf.push(1.0);
+    let mut running_sum = 0.0f32;
+
+    let mut crossing = None;
+    while cmndf.len() < window {
+        let tau = cmndf.len();
+        push_cmndf_lag(samples, window, &mut cmndf, &mut running_sum);
+        // Same order as `absolute_threshold`: the previous lag's trough
+        // can only be judged once this lag is known
+        if is_interpolated_crossing(&cmndf, tau - 1, threshold) {
+            crossing = Some(tau - 1);
+            break;
+        }
+        if tau >= 2 && cmndf[tau] < threshold {
+            crossing = Some(tau);
+            break;
+        }
+        if tau >= last_useful_tau {
+            break;
+        }
+    }
+
+    let mut tau = match crossing {
+        Some(t) => t,
+        None => return -1.0,
+    };
+
+    // Walk to the bottom of the trough, computing lags on demand
+    while tau + 1 < window {
+        if cmndf.len() == tau + 1 {
+            push_cmndf_lag(samples, window, &mut cmndf, &mut running_sum);
+        }
+        if cmndf[tau + 1] < cmndf[tau] {
+            tau += 1;
+        } else {
+            break;
+        }
+    }
+
+    let frequency = sample_rate / parabolic_interpolation(&cmndf, tau);
+    if (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+        frequency
+    } else {
+        -1.0
+    }
+}
+
+/// Steps 1-2 for the next lag `cmndf.len()`, appended to `cmndf`
+fn push_cmndf_lag<S: SampleSource + ?Sized>(
+    samples: &S,
+    window: usize,
+    cmndf: &mut Vec<f32>,
+    running_sum: &mut f32,
+) {
+    let tau = cmndf.len();
+    let difference = lag_difference(samples, window, tau);
+    *running_sum += difference;
+    cmndf.push(if *running_sum > 0.0 {
+        difference * tau as f32 / *running_sum
+    } else {
+        1.0
+    });
+}
+
+/// Step 1 for a single lag: squared difference over the first `window` samples
+pub(crate) fn lag_difference<S: SampleSource + ?Sized>(
+    samples: &S,
+    window: usize,
+    tau: usize,
+) -> f32 {
+    (0..window)
+        .map(|i| {
+            let delta = samples.sample(i) - samples.sample(i + tau);
+            delta * delta
+        })
+        .sum()
+}
+
+/// Steps 1-2 with the usual gating: None if the buffer is too short or quiet
+pub(crate) fn gated_cmndf(samples: &[f32]) -> Option<Vec<f32>> {
+    let buffer_size = samples.len();
+    if buffer_size < 2 || calculate_rms(samples) < RMS_THRESHOLD {
+        return None;
+    }
+
+    let half_buffer_size = buffer_size / 2;
+    let mut difference = vec![0.0f32; half_buffer_size];
+    difference_function(samples, &mut difference);
+    let mut cmndf = vec![0.0f32; half_buffer_size];
+    cumulative_mean_normalized_difference(&difference, &mut cmndf);
+    Some(cmndf)
+}
+
+/// Steps 2-4 shared by the detection variants, given a difference function
+/// whose lags `0..difference.len()` were computed over `analysis_len` samples
+pub(crate) fn result_from_difference(
+    difference: &[f32],
+    analysis_len: usize,
+    sample_rate: f32,
+    config: &DetectorConfig,
+) -> PitchResult {
+    let mut cmndf = vec![0.0f32; difference.len()];
+    cumulative_mean_normalized_difference(difference, &mut cmndf);
+    result_from_cmndf(&cmndf, analysis_len, sample_rate, config)
+}
+
+/// Steps 3-4 given the CMNDF of a difference function computed over
+/// `analysis_len` samples
+pub(crate) fn result_from_cmndf(
+    cmndf: &[f32],
+    analysis_len: usize,
+    sample_rate: f32,
+    config: &DetectorConfig,
+) -> PitchResult {
+    let lag_count = cmndf.len();
+    let threshold_tau = absolute_threshold(cmndf, config.threshold);
+
+    // Without a threshold crossing, the deepest trough still tells us where the
+    // period would be, which is what the buffer-ceiling diagnostic needs
+    let best_tau = match threshold_tau.or_else(|| deepest_trough(cmndf)) {
+        Some(t) => t,
+        None => return PitchResult::UNVOICED,
+    };
+
+    let better_tau = parabolic_interpolation(cmndf, best_tau);
+    let period_exceeds_buffer =
+        best_tau as f32 >= (lag_count as f32 * PERIOD_CEILING_FRACTION).floor();
+
+    let mut result = PitchResult {
+        frequency: -1.0,
+        tau: better_tau,
+        cmndf: cmndf[best_tau],
+        period_exceeds_buffer,
+        analysis_len,
+        best_tau,
+        frequency_uncertainty_hz: 0.0,
+        ambiguity_ratio: 0.0,
+    };
+
+    if threshold_tau.is_some() {
+        // Calculate frequency
+        let frequency = sample_rate / better_tau;
+
+        // Validate frequency range
+        if let Some(frequency) = config.apply_range(frequency) {
+            result.frequency = frequency;
+            // f = sr / tau, so df = sr / tau^2 * dtau
+            result.frequency_uncertainty_hz =
+                sample_rate / (better_tau * better_tau) * tau_uncertainty(cmndf, best_tau);
+        }
+    }
+
+    result
+}
+
+/// Every local minimum of the CMNDF as `(tau, cmndf)` pairs, for showing how
+/// YIN chooses its period
+/// Taus are parabolically interpolated and ordered by lag, limited to the
+/// periods of the 60-2000 Hz range. A periodic signal shows its period and
+/// every multiple of it; the first one below the threshold is the pick.
+/// Returns an empty list for a buffer that is too short or quiet
+pub fn cmndf_minima(samples: &[f32], sample_rate: f32) -> Vec<(f32, f32)> {
+    let cmndf = match gated_cmndf(samples) {
+        Some(cmndf) => cmndf,
+        None => return Vec::new(),
+    };
+    let shortest = sample_rate / MAX_FREQUENCY;
+    let longest = sample_rate / MIN_FREQUENCY;
+    local_minima(&cmndf)
+        .filter(|&tau| (shortest..=longest).contains(&(tau as f32)))
+        .map(|tau| {
+            let (offset, value) = parabola_vertex(&cmndf, tau);
+            (tau as f32 + offset, value)
+        })
+        .collect()
+}
+
+/// Step 1 divided by twice the window energy, so its scale no longer
+/// depends on the input level
+/// Twice the energy is the level the difference settles at for uncorrelated
+/// samples, so values near 0.0 mark a repeat of the waveform and values near
+/// 1.0 no relation. Lags are those of the standard `len / 2` window.
+/// Returns an empty list when the buffer is too short or silent
+#[wasm_bindgen]
+pub fn normalized_difference(samples: &[f32]) -> Vec<f32> {
+    let window = samples.len() / 2;
+    let energy: f32 = samples[..window].iter().map(|x| x * x).sum();
+    if window == 0 || energy == 0.0 {
+        return Vec::new();
+    }
+    let mut difference = vec![0.0f32; window];
+    difference_function(samples, &mut difference);
+    let scale = 1.0 / (2.0 * energy);
+    difference.iter_mut().for_each(|d| *d *= scale);
+    difference
+}
+
+/// Depth of the difference function's global minimum, a cheap tonality proxy
+/// Read from `normalized_difference`, since `difference[0]` is identically
+/// zero and gives no scale of its own. The search starts after the first
+/// local maximum, skipping the descent to lag 0 that every smooth signal has.
+/// Skips the CMNDF and the RMS gate entirely.
+/// Returns near 0.0 for a periodic signal, near 1.0 for noise, and 1.0 when
+/// the buffer is too short or silent
+#[wasm_bindgen]
+pub fn difference_min_depth(samples: &[f32]) -> f32 {
+    let difference = normalized_difference(samples);
+    let window = difference.len();
+    if window < 3 {
+        return 1.0;
+    }
+
+    let first_peak = (1..window - 1)
+        .find(|&tau| difference[tau] >= difference[tau + 1])
+        .unwrap_or(window - 1);
+    difference[first_peak..]
+        .iter()
+        .copied()
+        .fold(f32::INFINITY, f32::min)
+        .min(1.0)
+}
+
+/// Practical (min, max) detectable frequency in Hz for a buffer
+/// The minimum comes from the longest lag YIN searches (half the buffer), the
+/// maximum from the shortest period that interpolates reliably. This ignores
+/// the fixed 60-2000 Hz range the detection functions also enforce.
+/// Returns (0.0, 0.0) when the buffer is too short to resolve any period
+pub fn effective_range(buffer_size: usize, sample_rate: f32) -> (f32, f32) {
+    let longest_tau = (buffer_size / 2).saturating_sub(1) as f32;
+    if longest_tau < MIN_PRACTICAL_TAU {
+        return (0.0, 0.0);
+    }
+    (sample_rate / longest_tau, sample_rate / MIN_PRACTICAL_TAU)
+}
+
+/// Frequency step corresponding to one lag of period near `freq`, in Hz
+/// `freq - sample_rate / (tau + 1)` for the period `tau = sample_rate / freq`,
+/// about `freq^2 / sample_rate`: the integer lag grid is coarse for high
+/// notes and fine for low ones. Parabolic interpolation resolves well
+/// within one step on clean input, so this is the grid the estimate is
+/// refined on rather than a hard limit.
+/// Returns NaN if either argument is not positive
+#[wasm_bindgen]
+pub fn frequency_resolution_hz(freq: f32, sample_rate: f32) -> f32 {
+    if freq <= 0.0 || sample_rate <= 0.0 {
+        return f32::NAN;
+    }
+    let tau = sample_rate / freq;
+    freq - sample_rate / (tau + 1.0)
+}
+
+/// Frequency below which octave errors become likely for a buffer size
+/// YIN compares a window of half the buffer; once that window holds fewer
+/// than two periods, the trough at the true period is averaged over too
+/// little signal to reliably beat the one at half the period. The result
+/// scales inversely with `buffer_size`.
+/// Returns infinity when the buffer is too short to hold any window
+#[wasm_bindgen]
+pub fn octave_confusion_threshold(buffer_size: usize, sample_rate: f32) -> f32 {
+    let window = (buffer_size / 2) as f32;
+    if window == 0.0 {
+        return f32::INFINITY;
+    }
+    OCTAVE_CONFUSION_PERIODS * sample_rate / window
+}
+
+/// Dominant period of any signal in seconds, with no musical assumptions
+/// The bare YIN core for non-musical uses such as heart rate or engine
+/// speed: no 60-2000 Hz range and no silence gate, so periods up to half the
+/// buffer are found at any level. The first lag whose CMNDF dips under the
+/// default threshold is taken, falling back to the deepest trough when none
+/// does (YIN's own fallback), then refined by parabolic interpolation.
+/// Choose the sample rate and buffer so that the buffer holds at least two
+/// of the longest periods of interest.
+/// Returns the period in seconds, or -1.0 if the signal has no trough at all
+/// (too short, constant or silent)
+#[wasm_bindgen]
+pub fn detect_period_unbounded(samples: &[f32], sample_rate: f32) -> f32 {
+    let window = samples.len() / 2;
+    if window < 3 {
+        return -1.0;
+    }
+    let mut difference = vec![0.0f32; window];
+    let mut cmndf = vec![0.0f32; window];
+    difference_function(samples, &mut difference);
+    cumulative_mean_normalized_difference(&difference, &mut cmndf);
+
+    match absolute_threshold(&cmndf, DEFAULT_THRESHOLD).or_else(|| deepest_trough(&cmndf)) {
+        Some(tau) => parabolic_interpolation(&cmndf, tau) / sample_rate,
+        None => -1.0,
+    }
+}
+
+/// Step 1: Difference function
+/// Compares the first `difference.len()` samples against each lag `tau`,
+/// so `samples` must hold at least twice that many samples. With
+/// `difference.len() == len / 2` the last sample of an odd-length buffer is
+/// never read; see `detect_pitch_full_lag_range` for a variant that uses it
+pub(crate) fn difference_function<S: SampleSource + ?Sized>(samples: &S, difference: &mut [f32]) {
+    let window = difference.len();
+    for (tau, value) in difference.iter_mut().enumerate() {
+        let mut sum = 0.0f32;
+        for i in 0..window {
+            let delta = samples.sample(i) - samples.sample(i + tau);
+            sum += delta * delta;
+        }
+        *value = sum;
+    }
+}
+
+/// Step 2: Cumulative mean normalized difference function (CMNDF)
+pub(crate) fn cumulative_mean_normalized_difference(difference: &[f32], cmndf: &mut [f32]) {
+    if cmndf.is_empty() {
+        return;
+    }
+    cmndf[0] = 1.0;
+    let mut running_sum = 0.0f32;
+
+    for tau in 1..cmndf.len() {
+        running_sum += difference[tau];
+        if running_sum > 0.0 {
+            cmndf[tau] = difference[tau] * (tau as f32) / running_sum;
+        } else {
+            cmndf[tau] = 1.0;
+        }
+    }
+}
+
+/// Step 3: Absolute threshold - find first tau where CMNDF < threshold,
+/// then walk forward to the bottom of that trough
+/// A trough also qualifies when the parabola through its lowest lag dips
+/// under the threshold: at small tau (high notes) the true period can fall
+/// half a lag away from any integer lag, and the sampled values then
+/// overstate the depth enough to skip the fundamental for its octave below.
+/// The search deliberately starts below the lag of MAX_FREQUENCY so that
+/// tones above the range are rejected rather than read as a subharmonic
+pub(crate) fn absolute_threshold(cmndf: &[f32], threshold: f32) -> Option<usize> {
+    let len = cmndf.len();
+    for tau in 2..len {
+        if is_interpolated_crossing(cmndf, tau - 1, threshold) {
+            return Some(tau - 1);
+        }
+        if cmndf[tau] < threshold {
+            // Find the local minimum
+            let mut min_tau = tau;
+            while min_tau + 1 < len && cmndf[min_tau + 1] < cmndf[min_tau] {
+                min_tau += 1;
+            }
+            return Some(min_tau);
+        }
+    }
+    None
+}
+
+/// Whether `tau` is a local minimum whose interpolated vertex lies below
+/// `threshold` (lags from 2 up, needing both neighbours)
+fn is_interpolated_crossing(cmndf: &[f32], tau: usize, threshold: f32) -> bool {
+    tau >= 2
+        && tau + 1 < cmndf.len()
+        && cmndf[tau] < cmndf[tau - 1]
+        && cmndf[tau] <= cmndf[tau + 1]
+        && parabola_vertex(cmndf, tau).1 < threshold
+}
+
+/// Whether `tau` is an integer multiple of a shorter trough in `troughs`
+/// that is at least as deep, i.e. the same periodicity seen again
+fn is_subharmonic_repeat(cmndf: &[f32], troughs: &[usize], tau: usize) -> bool {
+    troughs.iter().take_while(|&&t| t < tau).any(|&shorter| {
+        let ratio = tau as f32 / shorter as f32;
+        let error = (ratio - ratio.round()).abs() / ratio;
+        ratio.round() >= 2.0
+            && error < SUBHARMONIC_TOLERANCE
+            && cmndf[shorter] <= cmndf[tau] + SUBHARMONIC_DEPTH_EPSILON
+    })
+}
+
+/// Second-deepest over deepest trough depth, ignoring subharmonic repeats
+/// 0.0 when there is at most one distinct trough
+fn ambiguity_ratio(cmndf: &[f32]) -> f32 {
+    let troughs: Vec<usize> = local_minima(cmndf).collect();
+    let mut depths: Vec<f32> = troughs
+        .iter()
+        .filter(|&&tau| !is_subharmonic_repeat(cmndf, &troughs, tau))
+        .map(|&tau| (1.0 - cmndf[tau]).max(0.0))
+        .collect();
+    depths.sort_by(|a, b| b.total_cmp(a));
+    match depths[..] {
+        [best, second, ..] if best > 0.0 => second / best,
+        _ => 0.0,
+    }
+}
+
+/// Interior local minima of the CMNDF over the searchable lag range
+pub(crate) fn local_minima(cmndf: &[f32]) -> impl Iterator<Item = usize> + '_ {
+    (2..cmndf.len().saturating_sub(1))
+        .filter(|&tau| cmndf[tau] < cmndf[tau - 1] && cmndf[tau] <= cmndf[tau + 1])
+}
+
+/// Deepest local minimum over the searchable lag range
+/// The last lag counts as a trough when the CMNDF is still descending into it,
+/// since the real minimum then lies beyond the buffer
+fn deepest_trough(cmndf: &[f32]) -> Option<usize> {
+    let len = cmndf.len();
+    (2..len)
+        .filter(|&tau| {
+            cmndf[tau] < cmndf[tau - 1] && (tau + 1 == len || cmndf[tau] <= cmndf[tau + 1])
+        })
+        .min_by(|&a, &b| cmndf[a].total_cmp(&cmndf[b]))
+}
+
+/// Step 4: Parabolic interpolation for better precision
+/// The offset is clamped to half a lag: a true local minimum always lands
+/// within that, and a nearly flat or non-minimal neighbourhood would
+/// otherwise extrapolate the vertex far from `tau`. At the first lag the
+/// parabola is fitted forward, see `parabola_vertex`; at the last lag `tau`
+/// is returned unrefined, since the real minimum may lie beyond the lags
+/// searched (which `period_exceeds_buffer` reports)
+pub(crate) fn parabolic_interpolation(cmndf: &[f32], tau: usize) -> f32 {
+    tau as f32 + parabola_vertex(cmndf, tau).0
+}
+
+/// Uncertainty of the interpolated period, in lags
+/// How far either side of the vertex the fitted parabola rises by the trough's
+/// own depth: zero for a perfect trough, wide for a shallow or flat one.
+/// Half a lag where no parabola can be fitted
+fn tau_uncertainty(cmndf: &[f32], tau: usize) -> f32 {
+    if tau > 0 && tau < cmndf.len() - 1 {
+        let curvature = cmndf[tau - 1] + cmndf[tau + 1] - 2.0 * cmndf[tau];
+        if curvature > f32::EPSILON {
+            let depth = parabola_vertex(cmndf, tau).1.max(0.0);
+            return (2.0 * depth / curvature).sqrt();
+        }
+    }
+    MAX_INTERPOLATION_OFFSET
+}
+
+/// `(offset, value)` of the parabola through `tau` and its neighbours,
+/// with the offset clamped as described for `parabolic_interpolation`
+/// At the first lag, which has no left neighbour, the parabola goes through
+/// `tau` and the two lags after it instead, so a trough sitting on the lower
+/// edge is still refined (towards or past the edge by up to half a lag).
+/// Falls back to `(0.0, cmndf[tau])` at the last lag or on a flat curve
+pub(crate) fn parabola_vertex(cmndf: &[f32], tau: usize) -> (f32, f32) {
+    if tau == 0 && cmndf.len() >= 3 {
+        return forward_parabola_vertex(cmndf[0], cmndf[1], cmndf[2]);
+    }
+    if tau > 0 && tau < cmndf.len() - 1 {
+        let s0 = cmndf[tau - 1];
+        let s1 = cmndf[tau];
+        let s2 = cmndf[tau + 1];
+        let denominator = 2.0 * s1 - s2 - s0;
+        if denominator.abs() > f32::EPSILON {
+            let offset = ((s2 - s0) / (2.0 * denominator))
+                .clamp(-MAX_INTERPOLATION_OFFSET, MAX_INTERPOLATION_OFFSET);
+            let value = s1 + 0.5 * (s2 - s0) * offset - 0.5 * denominator * offset * offset;
+            return (offset, value);
+        }
+    }
+    (0.0, cmndf[tau])
+}
+
+/// `(offset, value)` of the vertex of the parabola through `(0, s0)`,
+/// `(1, s1)` and `(2, s2)`, clamped to half a lag either side of 0
+/// Falls back to `(0.0, s0)` unless the parabola opens upwards
+fn forward_parabola_vertex(s0: f32, s1: f32, s2: f32) -> (f32, f32) {
+    // s(x) = a x^2 + b x + s0
+    let a = 0.5 * (s2 - 2.0 * s1 + s0);
+    if a <= f32::EPSILON {
+        return (0.0, s0);
+    }
+    let b = s1 - s0 - a;
+    let offset = (-b / (2.0 * a)).clamp(-MAX_INTERPOLATION_OFFSET, MAX_INTERPOLATION_OFFSET);
+    (offset, a * offset * offset + b * offset + s0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{generate_tone, Waveform};
+    use crate::{detect_pitch, detect_pitch_with_threshold, DEFAULT_THRESHOLD};
+
+    fn generate_sine_wave(frequency: f32, sample_rate: f32, duration_samples: usize) -> Vec<f32> {
+        generate_tone(frequency, sample_rate, duration_samples, Waveform::Sine)
+    }
+
+    #[test]
+    fn test_parabolic_interpolation_at_lower_edge() {
+        // A period of 99.55 lags searched from lag 100 up: the trough sits on
+        // the first lag searched, with the true minimum just before it
+        let sample_rate = 44100.0;
+        let frequency = sample_rate / 99.55;
+        let samples = generate_sine_wave(frequency, sample_rate, 2048);
+        let lags: Vec<f32> = (100..105)
+            .map(|tau| lag_difference(&samples[..], 1024, tau))
+            .collect();
+        assert!(lags[0] < lags[1]);
+
+        let refined = 100.0 + parabolic_interpolation(&lags, 0);
+        assert!((refined - 99.55).abs() < 0.05, "{}", refined);
+        assert!((refined - 99.55).abs() < (100.0f32 - 99.55).abs() / 4.0);
+
+        // The last lag is left as it is
+        let rising: Vec<f32> = lags.iter().rev().copied().collect();
+        assert_eq!(parabolic_interpolation(&rising, 4), 4.0);
+        // An exact parabola is recovered, clamped to half a lag
+        assert!((parabola_vertex(&[0.09, 1.69, 5.29], 0).0 + 0.3).abs() < 1e-5);
+        assert_eq!(parabola_vertex(&[0.0, 4.0, 16.0], 0).0, 0.0);
+        assert_eq!(parabola_vertex(&[4.0, 9.0, 16.0], 0).0, -0.5);
+    }
+
+    #[test]
+    fn test_parabolic_interpolation_clamps_flat_neighbourhood() {
+        // tau = 2 sits on a nearly flat slope, not a minimum; the unclamped
+        // vertex would land 1.5 lags away
+        let cmndf = [1.0, 0.3002, 0.3, 0.2999, 1.0];
+        let refined = parabolic_interpolation(&cmndf, 2);
+        assert!(
+            (refined - 2.0).abs() <= MAX_INTERPOLATION_OFFSET,
+            "{}",
+            refined
+        );
+
+        // A genuine minimum is refined as before
+        let cmndf = [1.0, 0.4, 0.1, 0.2, 1.0];
+        let refined = parabolic_interpolation(&cmndf, 2);
+        assert!(refined > 2.0 && refined < 2.5, "{}", refined);
+    }
+
+    /// Counts every sample read, as a proxy for work done
+    struct TapCounter<'a> {
+        samples: &'a [f32],
+        taps: std::cell::Cell<usize>,
+    }
+
+    impl SampleSource for TapCounter<'_> {
+        fn sample_count(&self) -> usize {
+            self.samples.len()
+        }
+
+        fn sample(&self, index: usize) -> f32 {
+            self.taps.set(self.taps.get() + 1);
+            self.samples[index]
+        }
+    }
+
+    #[test]
+    fn test_fast_reject_matches_and_saves_work() {
+        use crate::testutil::{add_noise, NoiseKind};
+
+        let sample_rate = 44100.0;
+        for frequency in [82.41, 220.0, 440.0, 1000.0] {
+            let samples = generate_tone(frequency, sample_rate, 2048, Waveform::Saw);
+            assert_eq!(
+                detect_pitch_fast_reject(&samples, sample_rate, 0.1),
+                detect_pitch_full(&samples, sample_rate, 0.1).frequency
+            );
+        }
+
+        let mut noise = generate_tone(440.0, sample_rate, 2048, Waveform::Sine);
+        add_noise(&mut noise, -20.0, NoiseKind::White);
+        let full = TapCounter {
+            samples: &noise,
+            taps: 0.into(),
+        };
+        let fast = TapCounter {
+            samples: &noise,
+            taps: 0.into(),
+        };
+        assert_eq!(
+            detect_full_source(&full, sample_rate, &DetectorConfig::default()).frequency,
+            -1.0
+        );
+        assert_eq!(fast_reject_source(&fast, sample_rate, 0.1), -1.0);
+        assert!(
+            fast.taps.get() * 4 < full.taps.get() * 3,
+            "{} vs {} taps",
+            fast.taps.get(),
+            full.taps.get()
+        );
+    }
+
+    #[test]
+    fn test_high_note_small_tau() {
+        // 1800 Hz has a period of 24.5 samples at 44.1 kHz, exactly between
+        // two lags, which used to push the sampled trough over the threshold
+        // and report the octave below
+        for (waveform, sample_rate) in [
+            (Waveform::Saw, 44100.0),
+            (Waveform::Sine, 44100.0),
+            (Waveform::Square, 48000.0),
+        ] {
+            let samples = generate_tone(1800.0, sample_rate, 2048, waveform);
+            let result = detect_pitch_full(&samples, sample_rate, 0.1);
+            assert!(
+                (result.frequency - 1800.0).abs() < 10.0,
+                "{:?} at {}: {:?}",
+                waveform,
+                sample_rate,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_circular_loop_of_two_periods() {
+        let sample_rate = 44100.0;
+        // Exactly two 441-sample periods of 100 Hz
+        let samples = generate_sine_wave(100.0, sample_rate, 882);
+
+        let circular = detect_pitch_circular(&samples, sample_rate);
+        assert!((circular - 100.0).abs() < 0.1, "circular {}", circular);
+
+        // The linear path cannot reach a lag of a full period here
+        let linear = detect_pitch_full(&samples, sample_rate, DEFAULT_THRESHOLD).frequency;
+        let linear_error = if linear > 0.0 {
+            (linear - 100.0).abs()
+        } else {
+            f32::INFINITY
+        };
+        assert!(linear_error > (circular - 100.0).abs());
+    }
+
+    #[test]
+    fn test_capped_search_sets_frequency_floor() {
+        let sample_rate = 44100.0;
+        // Lags up to 200 put the floor at 220.5 Hz
+        let max_tau = 200;
+        let low = generate_sine_wave(110.0, sample_rate, 2048);
+        assert!(detect_pitch(&low, sample_rate) > 0.0);
+        assert_eq!(detect_pitch_capped(&low, sample_rate, 0.1, max_tau), -1.0);
+
+        let high = generate_sine_wave(330.0, sample_rate, 2048);
+        assert_eq!(
+            detect_pitch_capped(&high, sample_rate, 0.1, max_tau),
+            detect_pitch(&high, sample_rate)
+        );
+
+        // A cap past the buffer is the plain search
+        assert_eq!(
+            detect_pitch_capped(&low, sample_rate, 0.1, usize::MAX),
+            detect_pitch(&low, sample_rate)
+        );
+    }
+
+    #[test]
+    fn test_uncertainty_grows_with_noise() {
+        use crate::testutil::{add_noise, NoiseKind};
+
+        let sample_rate = 44100.0;
+        let clean = generate_sine_wave(220.0, sample_rate, 2048);
+        let mut noisy = clean.clone();
+        add_noise(&mut noisy, 12.0, NoiseKind::White);
+
+        let clean = detect_pitch_full(&clean, sample_rate, 0.1);
+        let noisy = detect_pitch_full(&noisy, sample_rate, 0.1);
+        assert!((clean.frequency - 220.0).abs() < 2.0, "{:?}", clean);
+        assert!((noisy.frequency - 220.0).abs() < 2.0, "{:?}", noisy);
+        assert!(clean.frequency_uncertainty_hz < 0.1, "{:?}", clean);
+        assert!(
+            noisy.frequency_uncertainty_hz > 2.0 * clean.frequency_uncertainty_hz,
+            "{:?} vs {:?}",
+            noisy,
+            clean
+        );
+        // The error bar covers the actual error
+        assert!((noisy.frequency - 220.0).abs() < noisy.frequency_uncertainty_hz);
+    }
+
+    #[test]
+    fn test_lag_fraction_reaches_lower_notes() {
+        let sample_rate = 44100.0;
+        // A 70 Hz period (630 samples) is beyond the 512 lags of a 1024 buffer
+        let samples = generate_sine_wave(70.0, sample_rate, 1024);
+        assert_eq!(detect_pitch(&samples, sample_rate), -1.0);
+        let detected = detect_pitch_with_lag_fraction(&samples, sample_rate, 0.1, 0.75);
+        assert!((detected - 70.0).abs() < 1.0, "got {}", detected);
+
+        // One half reproduces the standard split
+        let tone = generate_sine_wave(330.0, sample_rate, 2048);
+        assert_eq!(
+            detect_pitch_with_lag_fraction(&tone, sample_rate, 0.1, 0.5),
+            detect_pitch_with_threshold(&tone, sample_rate, 0.1)
+        );
+        for fraction in [0.0, 1.0, f32::NAN] {
+            assert_eq!(
+                detect_pitch_with_lag_fraction(&tone, sample_rate, 0.1, fraction),
+                -1.0
+            );
+        }
+    }
+
+    #[test]
+    fn test_threshold_sweep_steps_from_miss_to_detection() {
+        use crate::testutil::{add_noise, NoiseKind};
+
+        let sample_rate = 44100.0;
+        let mut samples = generate_sine_wave(330.0, sample_rate, 2048);
+        add_noise(&mut samples, 15.0, NoiseKind::White);
+
+        let thresholds = [0.01, 0.05, 0.2, 0.3];
+        let curve = threshold_sweep(&samples, sample_rate, &thresholds);
+        assert_eq!(curve.len(), 4);
+        assert_eq!(curve[0], -1.0, "{:?}", curve);
+        assert!((curve[2] - 330.0).abs() < 3.0, "{:?}", curve);
+        assert!((curve[3] - 330.0).abs() < 3.0, "{:?}", curve);
+        for (&threshold, &frequency) in thresholds.iter().zip(&curve) {
+            assert_eq!(
+                frequency,
+                detect_pitch_with_threshold(&samples, sample_rate, threshold)
+            );
+        }
+
+        assert_eq!(
+            threshold_sweep(&[0.0; 2048], sample_rate, &[0.1, 0.2]),
+            [-1.0, -1.0]
+        );
+    }
+
+    #[test]
+    fn test_cmndf_minima_lists_period_multiples() {
+        let sample_rate = 44100.0;
+        // A 100-sample period
+        let samples = generate_sine_wave(441.0, sample_rate, 2048);
+        let minima = cmndf_minima(&samples, sample_rate);
+        // Multiples up to the 735-sample period of 60 Hz
+        assert_eq!(minima.len(), 7, "{:?}", minima);
+        for (k, &(tau, value)) in minima.iter().enumerate() {
+            assert!((tau - 100.0 * (k + 1) as f32).abs() < 0.5, "{:?}", minima);
+            assert!(value < 0.01, "{:?}", minima);
+        }
+        let result = detect_pitch_full(&samples, sample_rate, DEFAULT_THRESHOLD);
+        assert!((minima[0].0 - result.tau).abs() < 1e-4);
+
+        assert!(cmndf_minima(&[0.0; 2048], sample_rate).is_empty());
+    }
+
+    #[test]
+    fn test_difference_min_depth_separates_tone_from_noise() {
+        use crate::testutil::{add_noise, NoiseKind};
+
+        let sample_rate = 44100.0;
+        let sine = generate_sine_wave(220.0, sample_rate, 2048);
+        let tonal = difference_min_depth(&sine);
+        assert!(tonal < 0.01, "{}", tonal);
+
+        let mut noise = generate_sine_wave(220.0, sample_rate, 2048);
+        add_noise(&mut noise, -30.0, NoiseKind::White);
+        let noisy = difference_min_depth(&noise);
+        assert!(noisy > 0.5, "{}", noisy);
+
+        assert_eq!(difference_min_depth(&[0.0; 2048]), 1.0);
+        assert_eq!(difference_min_depth(&[0.5; 4]), 1.0);
+    }
+
+    #[test]
+    fn test_normalized_difference_is_level_invariant() {
+        use crate::testutil::{add_noise_seeded, NoiseKind};
+
+        let sample_rate = 44100.0;
+        let mut loud = generate_tone(196.0, sample_rate, 2048, Waveform::Saw);
+        add_noise_seeded(&mut loud, 20.0, NoiseKind::Pink, 7);
+        let quiet: Vec<f32> = loud.iter().map(|x| 0.5 * x).collect();
+
+        let (mut raw_loud, mut raw_quiet) = (vec![0.0; 1024], vec![0.0; 1024]);
+        difference_function(loud.as_slice(), &mut raw_loud);
+        difference_function(quiet.as_slice(), &mut raw_quiet);
+        assert!((raw_quiet[300] / raw_loud[300] - 0.25).abs() < 1e-4);
+
+        let normalized_loud = normalized_difference(&loud);
+        let normalized_quiet = normalized_difference(&quiet);
+        assert_eq!(normalized_loud.len(), 1024);
+        for (a, b) in normalized_loud.iter().zip(&normalized_quiet) {
+            assert!((a - b).abs() < 1e-5, "{} vs {}", a, b);
+        }
+        let depth = difference_min_depth(&loud);
+        assert!((difference_min_depth(&quiet) - depth).abs() < 1e-5);
+
+        assert!(normalized_difference(&[0.0; 2048]).is_empty());
+    }
+
+    #[test]
+    fn test_low_note_via_decimation() {
+        let sample_rate = 44100.0;
+        // A 35 Hz period (1260 samples) is longer than half the buffer
+        let samples = generate_sine_wave(35.0, sample_rate, 2048);
+        assert_eq!(detect_pitch(&samples, sample_rate), -1.0);
+
+        let detected = detect_pitch_low(&samples, sample_rate, 4);
+        assert!((detected - 35.0).abs() < 0.5, "got {}", detected);
+
+        // The floor follows the searched lags, not the decimation: 1365 lags
+        // at full rate reach down to 32.3 Hz, while a 1024-sample buffer
+        // (682 lags, 64.7 Hz) cannot hold the period
+        let detected = detect_pitch_low(&samples, sample_rate, 1);
+        assert!((detected - 35.0).abs() < 0.5, "got {}", detected);
+        assert_eq!(detect_pitch_low(&samples[..1024], sample_rate, 1), -1.0);
+
+        // Ordinary notes still come out right
+        let samples = generate_sine_wave(220.0, sample_rate, 2048);
+        let detected = detect_pitch_low(&samples, sample_rate, 4);
+        assert!((detected - 220.0).abs() < 2.0, "got {}", detected);
+    }
+
+    #[test]
+    fn test_full_matches_frequency() {
+        let sample_rate = 44100.0;
+        let samples = generate_sine_wave(440.0, sample_rate, 2048);
+        let result = detect_pitch_full(&samples, sample_rate, 0.1);
+        assert!((result.frequency - 440.0).abs() < 5.0);
+        assert!((sample_rate / result.tau - result.frequency).abs() < 0.01);
+        assert!(!result.period_exceeds_buffer);
+    }
+
+    #[test]
+    fn test_used_range() {
+        let sample_rate = 44100.0;
+        let samples = generate_sine_wave(441.0, sample_rate, 2048);
+        let result = detect_pitch_full(&samples, sample_rate, 0.1);
+        let (analysis_len, best_tau) = result.used_range();
+        assert_eq!(analysis_len, samples.len() / 2);
+        assert_eq!(best_tau, 100);
+        assert!(best_tau + analysis_len <= samples.len());
+    }
+
+    #[test]
+    fn test_period_exceeds_buffer() {
+        // A 50 Hz period is ~882 samples, but a 1024 buffer only searches 512 lags
+        let sample_rate = 44100.0;
+        let samples = generate_sine_wave(50.0, sample_rate, 1024);
+        let result = detect_pitch_full(&samples, sample_rate, 0.1);
+        assert!(result.period_exceeds_buffer, "{:?}", result);
+        assert_eq!(result.frequency, -1.0);
+    }
+
+    #[test]
+    fn test_effective_range() {
+        let (min, max) = effective_range(2048, 44100.0);
+        assert!((min - 43.1).abs() < 0.5, "min {}", min);
+        assert!(max > MAX_FREQUENCY);
+
+        // Halving the buffer doubles the lowest resolvable frequency
+        let (half_min, _) = effective_range(1024, 44100.0);
+        assert!((half_min / min - 2.0).abs() < 0.01);
+
+        assert_eq!(effective_range(4, 44100.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_frequency_resolution() {
+        let sample_rate = 44100.0;
+        let low = frequency_resolution_hz(82.41, sample_rate);
+        let high = frequency_resolution_hz(1318.5, sample_rate);
+        assert!(high > low * 100.0, "{} {}", low, high);
+        // 441 Hz is a period of exactly 100 lags; 101 lags is 436.6 Hz
+        let step = frequency_resolution_hz(441.0, sample_rate);
+        assert!((step - 4.366).abs() < 0.01, "{}", step);
+        assert!(frequency_resolution_hz(441.0, 2.0 * sample_rate) < step);
+        assert!(frequency_resolution_hz(0.0, sample_rate).is_nan());
+    }
+
+    #[test]
+    fn test_period_unbounded_below_musical_range() {
+        // 5 Hz (a 200-lag period at 1 kHz), faint enough to be gated as silence
+        let sample_rate = 1000.0;
+        let samples: Vec<f32> = (0..1000)
+            .map(|i| 0.001 * (2.0 * std::f32::consts::PI * 5.0 * i as f32 / sample_rate).sin())
+            .collect();
+        let period = detect_period_unbounded(&samples, sample_rate);
+        assert!((period - 0.2).abs() < 1e-3, "{}", period);
+        assert_eq!(crate::detect_pitch(&samples, sample_rate), -1.0);
+
+        assert_eq!(detect_period_unbounded(&[0.0; 1000], sample_rate), -1.0);
+        assert_eq!(detect_period_unbounded(&samples[..4], sample_rate), -1.0);
+    }
+
+    #[test]
+    fn test_octave_confusion_threshold() {
+        let threshold = octave_confusion_threshold(2048, 44100.0);
+        assert!((threshold - 86.1).abs() < 0.1, "{}", threshold);
+        assert!(threshold > effective_range(2048, 44100.0).0);
+
+        // Inversely proportional to the buffer size, proportional to the rate
+        for size in [512, 1024, 4096, 8192] {
+            let scaled = octave_confusion_threshold(size, 44100.0) * size as f32;
+            assert!((scaled / (threshold * 2048.0) - 1.0).abs() < 1e-6);
+        }
+        assert_eq!(octave_confusion_threshold(2048, 88200.0), 2.0 * threshold);
+        assert_eq!(octave_confusion_threshold(1, 44100.0), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_odd_length_buffers() {
+        let sample_rate = 44100.0;
+        for len in [2049, 2047, 1025] {
+            let samples = generate_sine_wave(330.0, sample_rate, len);
+            let standard = detect_pitch_full(&samples, sample_rate, 0.1);
+            let full = detect_pitch_full_lag_range(&samples, sample_rate, 0.1);
+            assert!(
+                (standard.frequency - 330.0).abs() < 2.0,
+                "{}: {:?}",
+                len,
+                standard
+            );
+            assert!((full.frequency - 330.0).abs() < 2.0, "{}: {:?}", len, full);
+            assert_eq!(standard.analysis_len, len / 2);
+            assert_eq!(full.analysis_len, len - full.best_tau);
+        }
+
+        // Tiny odd buffers must not panic
+        for len in [1, 3, 5, 7] {
+            let samples = vec![0.5f32; len];
+            detect_pitch_full(&samples, sample_rate, 0.1);
+            detect_pitch_full_lag_range(&samples, sample_rate, 0.1);
+        }
+    }
+
+    #[test]
+    fn test_prefer_fundamental_over_harmonic_basin() {
+        // Weak 110 Hz fundamental under a strong 220 Hz harmonic: the 220 Hz
+        // basin crosses the threshold first, but the 110 Hz basin is deeper
+        let sample_rate = 44100.0;
+        let fundamental = generate_sine_wave(110.0, sample_rate, 2048);
+        let harmonic = generate_sine_wave(220.0, sample_rate, 2048);
+        let samples: Vec<f32> = fundamental
+            .iter()
+            .zip(&harmonic)
+            .map(|(f, h)| 0.15 * f + h)
+            .collect();
+
+        let plain = detect_pitch_full(&samples, sample_rate, 0.1).frequency;
+        assert!((plain - 220.0).abs() < 3.0, "plain YIN got {}", plain);
+
+        let preferred = detect_pitch_prefer_fundamental(&samples, sample_rate, 0.1, 0.1);
+        assert!((preferred - 110.0).abs() < 2.0, "got {}", preferred);
+    }
+
+    #[test]
+    fn test_prefer_fundamental_ignores_subharmonics() {
+        // A pure tone has equally deep troughs at every multiple of its period
+        let sample_rate = 44100.0;
+        let samples = generate_sine_wave(440.0, sample_rate, 2048);
+        let detected = detect_pitch_prefer_fundamental(&samples, sample_rate, 0.1, 0.1);
+        assert!((detected - 440.0).abs() < 2.0, "got {}", detected);
+    }
+
+    #[test]
+    fn test_ambiguity_ratio_flags_octave_rivals() {
+        let sample_rate = 44100.0;
+        let clean = generate_sine_wave(220.0, sample_rate, 2048);
+        let clean = detect_pitch_full(&clean, sample_rate, 0.1).ambiguity_ratio;
+        assert!(clean < 0.2, "clean sine scored {}", clean);
+
+        // The weak-fundamental mix has rival troughs at both octaves
+        let fundamental = generate_sine_wave(110.0, sample_rate, 2048);
+        let harmonic = generate_sine_wave(220.0, sample_rate, 2048);
+        let mixed: Vec<f32> = fundamental
+            .iter()
+            .zip(&harmonic)
+            .map(|(f, h)| 0.15 * f + h)
+            .collect();
+        let ambiguous = detect_pitch_full(&mixed, sample_rate, 0.1).ambiguity_ratio;
+        assert!(ambiguous > 0.8, "octave-ambiguous mix scored {}", ambiguous);
+    }
+
+    #[test]
+    fn test_silence_is_unvoiced() {
+        let samples = vec![0.0f32; 1024];
+        assert_eq!(
+            detect_pitch_full(&samples, 44100.0, 0.1),
+            PitchResult::UNVOICED
+        );
+    }
+}